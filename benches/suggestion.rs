@@ -0,0 +1,48 @@
+//! Sorting suggestions, which compares their texts ignoring case.
+
+use brigadier::suggestion::{Suggestion, Suggestions};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const SUGGESTIONS: usize = 5000;
+
+const INPUT: &str = "give ";
+
+/// Mixed-case texts in an order which differs from the sorted one.
+fn suggestions() -> Vec<Suggestion<'static, 'static>> {
+    (0..SUGGESTIONS)
+        .rev()
+        .map(|i| {
+            let text = match i % 3 {
+                0 => format!("Item_{i:05}"),
+                1 => format!("ITEM_{i:05}"),
+                _ => format!("item_{i:05}"),
+            };
+            Suggestion::new_text(INPUT.len()..INPUT.len(), text)
+        })
+        .collect()
+}
+
+fn create(c: &mut Criterion) {
+    let suggestions = suggestions();
+    c.bench_function("create 5000 mixed-case suggestions", |b| {
+        b.iter_batched(
+            || suggestions.clone(),
+            |suggestions| Suggestions::create(black_box(INPUT), suggestions),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn cmp_ignore_case(c: &mut Criterion) {
+    let suggestions = suggestions();
+    c.bench_function("sort 5000 suggestions ignoring case", |b| {
+        b.iter_batched(
+            || suggestions.clone(),
+            |mut suggestions| suggestions.sort_by(Suggestion::cmp_ignore_case),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, create, cmp_ignore_case);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+//! Building large trees and looking up children while parsing.
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    tree::LiteralMatching,
+    CommandSource,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[derive(Clone)]
+struct Source;
+
+impl CommandSource for Source {}
+
+const COMMANDS: usize = 1000;
+
+fn build(names: &[String]) -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    for name in names {
+        let mut value = argument("value", BoolArgumentType);
+        value.executes(|_| Ok(1));
+        let mut set = literal("set");
+        set.then(value);
+        let mut reset = literal("reset");
+        reset.executes(|_| Ok(0));
+        let mut command = literal(name);
+        command.then(set).then(reset);
+        dispatcher.register(command);
+    }
+    dispatcher
+}
+
+fn names() -> Vec<String> {
+    (0..COMMANDS).map(|i| format!("command{i}")).collect()
+}
+
+fn build_tree(c: &mut Criterion) {
+    let names = names();
+    c.bench_function("build 1000 commands", |b| {
+        b.iter(|| build(black_box(&names)))
+    });
+}
+
+fn child_lookup(c: &mut Criterion) {
+    let dispatcher = build(&names());
+    c.bench_function("parse among 1000 commands", |b| {
+        b.iter(|| dispatcher.parse(black_box("command500 set true"), Source))
+    });
+    c.bench_function("find node among 1000 commands", |b| {
+        b.iter(|| dispatcher.find_node(black_box(&["command500", "set", "value"])))
+    });
+}
+
+fn literal_dispatch(c: &mut Criterion) {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    for i in 0..500 {
+        dispatcher.register(literal(&format!("literal{i}")));
+    }
+    dispatcher.set_literal_matching(LiteralMatching {
+        case_insensitive: true,
+        unique_prefix: false,
+    });
+    c.bench_function("case-insensitive parse among 500 literals", |b| {
+        b.iter(|| dispatcher.parse(black_box("LITERAL250"), Source))
+    });
+}
+
+criterion_group!(benches, build_tree, child_lookup, literal_dispatch);
+criterion_main!(benches);
@@ -0,0 +1,99 @@
+use brigadier::{
+    arguments::{ArgumentType, BoolArgumentType, DoubleArgumentType},
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    suggestion::{Suggestions, SuggestionsBuilder},
+    CommandSource, StringReader,
+};
+
+#[derive(Clone)]
+struct Source;
+
+impl CommandSource for Source {}
+
+/// All argument types used by the commands of this example.
+enum Type {
+    Bool(BoolArgumentType),
+    Double(DoubleArgumentType),
+}
+
+#[derive(Clone)]
+enum Value {
+    Bool(bool),
+    Double(f64),
+}
+
+brigadier::impl_from_argument_value!(Value { Bool => bool, Double => f64 });
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i, S> ArgumentType<'i, S> for Type
+where
+    S: CommandSource,
+{
+    type Output = Value;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Value, CommandSyntaxError<'i>> {
+        match self {
+            Self::Bool(bool) => ArgumentType::<S>::parse(bool, reader).map(Value::Bool),
+            Self::Double(double) => ArgumentType::<S>::parse(double, reader).map(Value::Double),
+        }
+    }
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        match self {
+            Self::Bool(bool) => bool.list_suggestions(context, builder).await,
+            Self::Double(double) => double.list_suggestions(context, builder).await,
+        }
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        match self {
+            Self::Bool(bool) => ArgumentType::<S>::examples(bool),
+            Self::Double(double) => ArgumentType::<S>::examples(double),
+        }
+    }
+}
+
+fn set_speed<'i>(context: &CommandContext<'i, Source, Type>) -> Result<i32, CommandSyntaxError<'i>> {
+    let value: &f64 = context.get_typed("value")?;
+    let relative: &bool = context.get_typed("relative")?;
+    println!("speed = {value}, relative = {relative}");
+    Ok(1)
+}
+
+fn main() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let mut speed = literal("speed");
+    let mut value = argument("value", Type::Double(DoubleArgumentType::new(0.0..=10.0)));
+    let mut relative = argument("relative", Type::Bool(BoolArgumentType));
+    relative.executes(set_speed);
+    value.then(relative);
+    speed.then(value);
+    dispatcher.register(speed);
+
+    // The same command, nested in the shape of the tree
+    let mut pace = literal("pace");
+    pace.then_build(
+        argument("value", Type::Double(DoubleArgumentType::new(0.0..=10.0))),
+        |value| {
+            value.then_build(argument("relative", Type::Bool(BoolArgumentType)), |relative| {
+                relative.executes(set_speed)
+            })
+        },
+    );
+    dispatcher.register(pace);
+
+    for input in ["speed 2.5 true", "pace 4 false"] {
+        match dispatcher.execute_input(input, Source) {
+            Ok(result) => println!("result = {result}"),
+            Err(error) => println!("error: {error}"),
+        }
+    }
+}
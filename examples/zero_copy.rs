@@ -0,0 +1,135 @@
+//! Parses a command, lists suggestions and reports an error without copying any text:
+//! parsed values and errors borrow the input (`'i`), suggestions borrow a registry
+//! which lives longer than the input (`'t` and `'m`). See the lifetimes section in the
+//! crate documentation.
+
+use std::borrow::Cow;
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    suggestion::{SuggestionProvider, Suggestions, SuggestionsBuilder},
+    CommandSource, Shared, StringReader,
+};
+
+#[derive(Clone)]
+struct Source;
+
+impl CommandSource for Source {}
+
+/// A word which borrows the input instead of copying it.
+struct Word;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Word {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string()
+    }
+}
+
+/// The names of the warps with their descriptions, which outlive every input.
+static WARPS: &[(&str, &str)] = &[
+    ("home", "Where your bed is"),
+    ("shop", "Trade with villagers"),
+    ("spawn", "The center of the world"),
+];
+
+/// Suggests the warps of the registry, texts and tooltips borrow from it.
+struct Warps(&'static [(&'static str, &'static str)]);
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl SuggestionProvider<Source, Word> for Warps {
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        _context: &CommandContext<'i, Source, Word>,
+        mut builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        Word: ArgumentType<'i, Source>,
+    {
+        for &(name, description) in self.0 {
+            if name.starts_with(builder.remaining_lower_case()) {
+                builder.suggest_text_with_tooltip(name, Cow::Borrowed(description));
+            }
+        }
+        Ok(builder.build())
+    }
+}
+
+fn warp<'i>(context: &CommandContext<'i, Source, Word>) -> Result<i32, CommandSyntaxError<'i>> {
+    let name: &'i str = context.get_argument("name").unwrap();
+    println!("warping to {name}");
+    Ok(1)
+}
+
+/// What the pipeline produced for one input.
+struct Outcome<'i> {
+    /// Borrows the input
+    name: Option<&'i str>,
+    /// Borrow the registry, so they may outlive the input
+    suggestions: Suggestions<'static, 'static>,
+    /// Borrows the input
+    error: Option<CommandSyntaxError<'i>>,
+}
+
+/// Parses, suggests and executes the input. All results are borrowed, none is cloned.
+fn pipeline<'i>(dispatcher: &CommandDispatcher<Source, Word>, input: &'i str) -> Outcome<'i> {
+    let parse = dispatcher.parse(input, Source);
+    let name = parse
+        .context()
+        .arguments()
+        .get("name")
+        .map(|argument| argument.result);
+
+    let node = dispatcher.find_node(&["warp", "name"]).unwrap();
+    let context = parse.context().clone().build(input);
+    let start = "warp ".len().min(input.len());
+    let builder = SuggestionsBuilder::new_with_lowercase(input, start);
+    let suggestions =
+        pollster::block_on(dispatcher.tree().list_suggestions(node, &context, builder))
+            .unwrap_or_else(|_| Suggestions::empty());
+
+    let error = dispatcher.execute(parse).err();
+    Outcome {
+        name,
+        suggestions,
+        error,
+    }
+}
+
+fn main() {
+    let mut dispatcher = CommandDispatcher::<Source, Word>::new();
+    let mut command = literal("warp");
+    let mut name = argument("name", Word);
+    name.suggests(Shared::new(Warps(WARPS))).executes(warp);
+    command.then(name);
+    dispatcher.register(command);
+
+    let mut kept = Vec::new();
+    for line in ["warp s", "warp home", "warp home now"] {
+        // The input only lives for one iteration
+        let input = line.to_owned();
+        let outcome = pipeline(&dispatcher, &input);
+        println!("{input:?}: name = {:?}", outcome.name);
+        if let Some(error) = &outcome.error {
+            println!("  error: {error}");
+        }
+        // Suggestions borrowing the registry outlive the input
+        kept.push(outcome.suggestions);
+    }
+    for suggestions in &kept {
+        for suggestion in suggestions {
+            println!(
+                "suggestion: {} ({:?})",
+                suggestion.text(),
+                suggestion.tooltip
+            );
+        }
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Checks that the crate, its tests and its docs build without features, with every
+# feature on its own and with all of them, so that no feature relies on another one
+# being enabled by accident.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+FEATURES="serde packet help palette history sync send-futures testing"
+
+check() {
+    echo "==> $*"
+    cargo clippy --all-targets "$@" -- -D warnings
+    RUSTDOCFLAGS="-D warnings" cargo doc --no-deps "$@"
+}
+
+check --no-default-features
+for feature in $FEATURES; do
+    check --no-default-features --features "$feature"
+done
+check --all-features
+
+cargo test --no-default-features
+cargo test --all-features
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::{
+    arguments::ArgumentType,
+    tree::{CommandNodeId, Tree},
+    CommandSource,
+};
+
+/// A child of `parent` which also accepts examples of its `sibling`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmbiguityFinding {
+    pub parent: CommandNodeId,
+    /// Names of the nodes from the root to `parent`
+    pub parent_path: Vec<String>,
+    pub child: CommandNodeId,
+    pub child_name: String,
+    pub sibling: CommandNodeId,
+    pub sibling_name: String,
+    /// The examples of `child` which `sibling` accepts as well
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckProgress {
+    InProgress {
+        done: usize,
+        total: usize,
+    },
+    Finished(Vec<AmbiguityFinding>),
+    /// The tree was modified since the checker was created.
+    TreeChanged,
+}
+
+/// A pair of siblings whose examples are checked against each other.
+struct Pair {
+    parent: CommandNodeId,
+    child: CommandNodeId,
+    sibling: CommandNodeId,
+    examples: Vec<String>,
+}
+
+/// Finds ambiguities in a tree in small steps, so that large trees can be checked
+/// without blocking for long. Only owned data is kept between steps.
+pub struct AmbiguityChecker {
+    generation: u64,
+    paths: HashMap<CommandNodeId, Vec<String>>,
+    pairs: Vec<Pair>,
+    total: usize,
+    done: usize,
+    pair_index: usize,
+    example_index: usize,
+    inputs: Vec<String>,
+    findings: Vec<AmbiguityFinding>,
+}
+
+impl AmbiguityChecker {
+    /// Creates a checker for everything below the roots of the tree.
+    pub fn new<S, A>(tree: &Tree<S, A>) -> Self
+    where
+        S: CommandSource,
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        Self::for_nodes(tree, tree.roots())
+    }
+    /// Creates a checker for everything below the node. Paths of findings are relative
+    /// to it.
+    pub fn for_node<S, A>(tree: &Tree<S, A>, node_id: CommandNodeId) -> Self
+    where
+        S: CommandSource,
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        Self::for_nodes(tree, std::iter::once(node_id))
+    }
+    fn for_nodes<S, A>(tree: &Tree<S, A>, nodes: impl Iterator<Item = CommandNodeId>) -> Self
+    where
+        S: CommandSource,
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        let mut paths = HashMap::new();
+        let mut pairs = Vec::new();
+        let mut stack: Vec<_> = nodes.map(|node_id| (node_id, Vec::new())).collect();
+        while let Some((parent, path)) = stack.pop() {
+            let children = tree.sorted_children(parent);
+            for &child in &children {
+                let examples = tree.examples(child);
+                for &sibling in &children {
+                    if sibling != child && !examples.is_empty() {
+                        pairs.push(Pair {
+                            parent,
+                            child,
+                            sibling,
+                            examples: examples.clone(),
+                        });
+                    }
+                }
+            }
+            for &child in children.iter().rev() {
+                let mut child_path = path.clone();
+                child_path.push(tree.name(child).unwrap_or_default().to_owned());
+                stack.push((child, child_path));
+            }
+            paths.insert(parent, path);
+        }
+        Self {
+            generation: tree.generation(),
+            paths,
+            total: pairs.iter().map(|pair| pair.examples.len()).sum(),
+            pairs,
+            done: 0,
+            pair_index: 0,
+            example_index: 0,
+            inputs: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+    /// Checks at most `max_pairs` examples against a sibling. The tree has to be the
+    /// one the checker was created for.
+    pub fn check_some<S, A>(&mut self, tree: &Tree<S, A>, max_pairs: usize) -> CheckProgress
+    where
+        S: CommandSource,
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        if tree.generation() != self.generation {
+            return CheckProgress::TreeChanged;
+        }
+        let mut budget = max_pairs;
+        while let Some(pair) = self.pairs.get(self.pair_index) {
+            if budget == 0 {
+                return CheckProgress::InProgress {
+                    done: self.done,
+                    total: self.total,
+                };
+            }
+            let example = &pair.examples[self.example_index];
+            if tree.is_valid_input(pair.sibling, example) {
+                self.inputs.push(example.clone());
+            }
+            budget -= 1;
+            self.done += 1;
+            self.example_index += 1;
+            if self.example_index == pair.examples.len() {
+                if !self.inputs.is_empty() {
+                    self.findings.push(AmbiguityFinding {
+                        parent: pair.parent,
+                        parent_path: self.paths[&pair.parent].clone(),
+                        child: pair.child,
+                        child_name: tree.name(pair.child).unwrap_or_default().to_owned(),
+                        sibling: pair.sibling,
+                        sibling_name: tree.name(pair.sibling).unwrap_or_default().to_owned(),
+                        inputs: std::mem::take(&mut self.inputs),
+                    });
+                }
+                self.pair_index += 1;
+                self.example_index = 0;
+            }
+        }
+        CheckProgress::Finished(std::mem::take(&mut self.findings))
+    }
+}
@@ -1,33 +1,128 @@
-use std::ops::{RangeFrom, RangeFull, RangeInclusive, RangeToInclusive};
+use std::{
+    borrow::Cow,
+    ops::{RangeFrom, RangeFull, RangeInclusive, RangeToInclusive},
+};
 
 use crate::{
     context::CommandContext,
     errors::{CommandErrorType, CommandSyntaxError},
+    string_reader::is_quoted_string_start,
     suggestion::{Suggestions, SuggestionsBuilder},
-    CommandSource, StringReader,
+    CommandSource, FutureBounds, StringReader,
 };
 
-#[async_trait::async_trait]
-pub trait ArgumentType<'i, S>
+pub mod any;
+pub mod value;
+
+bitflags::bitflags! {
+    /// Features an argument type supports, so that frontends can fall back gracefully
+    /// for types that lack some of them.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ArgCapabilities: u8 {
+        /// The type can be parsed and suggested by the client, so it may be sent to it.
+        /// Otherwise `tree::protocol::to_protocol_nodes` sends a string parser.
+        const CLIENT_SIDE = 1 << 0;
+        /// [`ArgumentType::examples`] returns inputs that parse successfully.
+        /// Otherwise `testing::assert_examples_parse` skips the type.
+        const EXAMPLES = 1 << 1;
+    }
+}
+
+impl Default for ArgCapabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Describes an argument type to users, e.g. in a tooltip, see
+/// [`ArgumentType::descriptor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentDescriptor {
+    /// The name of the type, like `double`
+    pub type_name: &'static str,
+    /// The values the type accepts if it restricts them, like `0..=100`
+    pub bounds: Option<String>,
+}
+
+impl ArgumentDescriptor {
+    pub fn new(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            bounds: None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+pub trait ArgumentType<'i, S>: FutureBounds
 where
     S: CommandSource,
 {
-    type Output;
+    /// The parsed value. Contexts are copied while exploring alternative branches of
+    /// the tree, so the value needs to be cheaply cloneable.
+    type Output: Clone + FutureBounds;
     fn parse(&self, reader: &mut StringReader<'i>) -> Result<Self::Output, CommandSyntaxError<'i>>;
-    async fn list_suggestions<'t, 'm>(
-        _context: &CommandContext<'i, S>,
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        _context: &CommandContext<'i, S, A>,
         _builder: SuggestionsBuilder<'i, 't, 'm>,
-    ) -> Suggestions<'t, 'm> {
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, S>,
+    {
         Suggestions::EMPTY
     }
     fn examples(&self) -> &'static [&'static str] {
         &[]
     }
+    fn capabilities(&self) -> ArgCapabilities {
+        ArgCapabilities::default()
+    }
+    /// Whether the type consumes the rest of the input, so that nodes after it can
+    /// never match.
+    fn is_greedy(&self) -> bool {
+        false
+    }
+    /// The length of the syntax at the start of the remaining input which surrounds
+    /// the value, e.g. an opening quote. Suggestions of a custom
+    /// [provider](crate::builder::RequiredArgumentBuilder::suggests) start after it, so
+    /// that they only replace the value.
+    fn suggestion_offset(&self, _remaining: &str) -> usize {
+        0
+    }
+    /// The identifier of the parser clients use for this type in the commands packet,
+    /// e.g. `brigadier:bool`. Types without one can't be sent to clients.
+    fn protocol_id(&self) -> Option<&'static str> {
+        None
+    }
+    /// Writes the properties of the parser as encoded in the commands packet.
+    fn write_protocol_properties(&self, _out: &mut Vec<u8>) {}
+    /// The name of the macro variable if the value is a placeholder for one, see
+    /// [`MacroAwareArgumentType`].
+    fn macro_placeholder<'o>(&self, _value: &'o Self::Output) -> Option<&'o str> {
+        None
+    }
+    /// Parses a value which doesn't borrow the text, e.g. one bound to a macro variable,
+    /// which doesn't live as long as the input. Errors are returned as their message.
+    fn parse_owned(&self, _reader: &mut StringReader<'_>) -> Result<Self::Output, String> {
+        Err(String::from("The argument type can't parse owned values"))
+    }
+    /// Describes the type to users, see
+    /// [`ParseResults::hover_info`](crate::dispatcher::ParseResults::hover_info).
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        None
+    }
+    /// Writes a parsed value the way users would type it.
+    fn render_value(&self, _value: &Self::Output) -> Option<String> {
+        None
+    }
 }
 
 pub struct BoolArgumentType;
 
-#[async_trait::async_trait]
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
 impl<'i, S> ArgumentType<'i, S> for BoolArgumentType
 where
     S: CommandSource,
@@ -36,21 +131,136 @@ where
     fn parse(&self, reader: &mut StringReader<'i>) -> Result<bool, CommandSyntaxError<'i>> {
         reader.read_boolean()
     }
-    async fn list_suggestions<'t, 'm>(
-        _context: &CommandContext<'i, S>,
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        _context: &CommandContext<'i, S, A>,
         mut builder: SuggestionsBuilder<'i, 't, 'm>,
-    ) -> Suggestions<'t, 'm> {
-        if "true".starts_with(builder.remaining_lower_case()) {
-            builder.suggest_text("true");
-        }
-        if "false".starts_with(builder.remaining_lower_case()) {
-            builder.suggest_text("false");
-        }
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        builder.suggest_text_matching("true").suggest_text_matching("false");
         builder.build()
     }
     fn examples(&self) -> &'static [&'static str] {
         &["true", "false"]
     }
+    fn capabilities(&self) -> ArgCapabilities {
+        ArgCapabilities::all()
+    }
+    fn protocol_id(&self) -> Option<&'static str> {
+        Some("brigadier:bool")
+    }
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<bool, String> {
+        reader.read_boolean().map_err(|error| error.error_type.to_string())
+    }
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        Some(ArgumentDescriptor::new("bool"))
+    }
+    fn render_value(&self, value: &bool) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+/// How much of the input a [`StringArgumentType`] reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StringType {
+    /// A single unquoted word.
+    SingleWord,
+    /// A single word or a quoted phrase, which may contain spaces.
+    QuotablePhrase,
+    /// The rest of the input, so no nodes can follow.
+    GreedyPhrase,
+}
+
+pub struct StringArgumentType {
+    pub string_type: StringType,
+}
+
+impl StringArgumentType {
+    pub fn new(string_type: StringType) -> Self {
+        Self { string_type }
+    }
+    pub fn word() -> Self {
+        Self::new(StringType::SingleWord)
+    }
+    pub fn string() -> Self {
+        Self::new(StringType::QuotablePhrase)
+    }
+    pub fn greedy_string() -> Self {
+        Self::new(StringType::GreedyPhrase)
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i, S> ArgumentType<'i, S> for StringArgumentType
+where
+    S: CommandSource,
+{
+    type Output = Cow<'i, str>;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Cow<'i, str>, CommandSyntaxError<'i>> {
+        match self.string_type {
+            StringType::SingleWord => reader.read_unquoted_string().map(Cow::Borrowed),
+            StringType::QuotablePhrase => reader.read_string(),
+            StringType::GreedyPhrase => {
+                let text = reader.remaining();
+                reader.set_cursor(reader.input().len());
+                Ok(Cow::Borrowed(text))
+            }
+        }
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        match self.string_type {
+            StringType::SingleWord => &["word", "words_with_underscores"],
+            StringType::QuotablePhrase => &["\"quoted phrase\"", "word", "\"\""],
+            StringType::GreedyPhrase => &["word", "words with spaces", "\"and symbols\""],
+        }
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        ArgCapabilities::all()
+    }
+    fn is_greedy(&self) -> bool {
+        self.string_type == StringType::GreedyPhrase
+    }
+    /// Suggestions for a quoted phrase replace the inside of the quotes.
+    fn suggestion_offset(&self, remaining: &str) -> usize {
+        match remaining.chars().next() {
+            Some(quote)
+                if self.string_type == StringType::QuotablePhrase
+                    && is_quoted_string_start(quote) =>
+            {
+                quote.len_utf8()
+            }
+            _ => 0,
+        }
+    }
+    fn protocol_id(&self) -> Option<&'static str> {
+        Some("brigadier:string")
+    }
+    /// The mode as a VarInt, which is a single byte for these values.
+    fn write_protocol_properties(&self, out: &mut Vec<u8>) {
+        out.push(match self.string_type {
+            StringType::SingleWord => 0,
+            StringType::QuotablePhrase => 1,
+            StringType::GreedyPhrase => 2,
+        });
+    }
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<Cow<'i, str>, String> {
+        ArgumentType::<S>::parse(self, reader)
+            .map(|value| Cow::Owned(value.into_owned()))
+            .map_err(|error| error.error_type.to_string())
+    }
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        Some(ArgumentDescriptor::new(match self.string_type {
+            StringType::SingleWord => "word",
+            StringType::QuotablePhrase => "string",
+            StringType::GreedyPhrase => "greedy_string",
+        }))
+    }
+    fn render_value(&self, value: &Cow<'i, str>) -> Option<String> {
+        Some(value.to_string())
+    }
 }
 
 pub trait NumericArgumentBounds<T> {
@@ -137,42 +347,191 @@ impl<T> NumericArgumentType<T> where RangeInclusive<T>: NumericArgumentBounds<T>
 }
 
 macro_rules! impl_numeric_argument_type {
-    ($Name:ident, $T:ty, $read:ident, $ErrTooSmall:ident, $ErrTooBig:ident) => {
+    ($Name:ident, $T:ty, $read:ident, $ErrTooSmall:ident, $ErrTooBig:ident, $type_name:literal, $protocol_id:literal, $Wire:ty, $examples:expr) => {
         pub type $Name = NumericArgumentType<$T>;
         
-        #[async_trait::async_trait]
+        #[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+        #[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
         impl<'i, S> ArgumentType<'i, S> for $Name
         where
             S: CommandSource,
         {
             type Output = $T;
             fn parse(&self, reader: &mut StringReader<'i>) -> Result<$T, CommandSyntaxError<'i>> {
-                let start = reader.cursor();
-                let result = reader.$read()?;
-                if result < *self.range.start() {
-                   reader.set_cursor(start);
-                   return Err(CommandSyntaxError::with_context(
-                       CommandErrorType::$ErrTooSmall {
-                           found: result,
-                           min: *self.range.start(),
-                       },
-                       reader.context(),
-                   ));
+                let mut transaction = reader.transaction();
+                let result = transaction.$read()?;
+                let error = if result < *self.range.start() {
+                    CommandErrorType::$ErrTooSmall {
+                        found: result,
+                        min: *self.range.start(),
+                    }
+                } else if result > *self.range.end() {
+                    CommandErrorType::$ErrTooBig {
+                        found: result,
+                        max: *self.range.end(),
+                    }
+                } else {
+                    transaction.commit();
+                    return Ok(result);
+                };
+                drop(transaction);
+                Err(CommandSyntaxError::with_context(error, reader.context()))
+            }
+            /// Examples of the unrestricted type, which may be out of the range.
+            fn examples(&self) -> &'static [&'static str] {
+                $examples
+            }
+            /// The examples only parse if the range contains all of them.
+            fn capabilities(&self) -> ArgCapabilities {
+                let mut capabilities = ArgCapabilities::CLIENT_SIDE;
+                let examples: &[&str] = $examples;
+                let examples_in_range = examples.iter().all(|example| {
+                    StringReader::new(example)
+                        .$read()
+                        .is_ok_and(|value| self.range.contains(&value))
+                });
+                capabilities.set(ArgCapabilities::EXAMPLES, examples_in_range);
+                capabilities
+            }
+            fn protocol_id(&self) -> Option<&'static str> {
+                Some($protocol_id)
+            }
+            /// A flags byte marking whether the minimum (`0x01`) and maximum (`0x02`)
+            /// differ from the limits of the parser's type, followed by those bounds in
+            /// big endian. Bounds which don't fit into the parser's type are clamped to
+            /// its limits.
+            fn write_protocol_properties(&self, out: &mut Vec<u8>) {
+                let min = <$Wire>::try_from(*self.range.start()).unwrap_or(<$Wire>::MAX);
+                let max = <$Wire>::try_from(*self.range.end()).unwrap_or(<$Wire>::MAX);
+                let has_min = min != <$Wire>::MIN;
+                let has_max = max != <$Wire>::MAX;
+                out.push(u8::from(has_min) | u8::from(has_max) << 1);
+                if has_min {
+                    out.extend_from_slice(&min.to_be_bytes());
                 }
-                if result > *self.range.end() {
-                   reader.set_cursor(start);
-                   return Err(CommandSyntaxError::with_context(
-                       CommandErrorType::$ErrTooBig {
-                           found: result,
-                           max: *self.range.end(),
-                       },
-                       reader.context(),
-                   ));
+                if has_max {
+                    out.extend_from_slice(&max.to_be_bytes());
                 }
-                Ok(result)
+            }
+            fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<$T, String> {
+                ArgumentType::<S>::parse(self, reader).map_err(|error| error.error_type.to_string())
+            }
+            /// The bounds which differ from the limits of the type, as a range.
+            fn descriptor(&self) -> Option<ArgumentDescriptor> {
+                let (min, max) = (*self.range.start(), *self.range.end());
+                let bounds = match (min != <$T>::MIN, max != <$T>::MAX) {
+                    (false, false) => None,
+                    (true, false) => Some(format!("{min}..")),
+                    (false, true) => Some(format!("..={max}")),
+                    (true, true) => Some(format!("{min}..={max}")),
+                };
+                Some(ArgumentDescriptor {
+                    type_name: $type_name,
+                    bounds,
+                })
+            }
+            fn render_value(&self, value: &$T) -> Option<String> {
+                Some(value.to_string())
             }
         }
     };
 }
 
-impl_numeric_argument_type!(DoubleArgumentType, f64, read_double, DoubleTooSmall, DoubleTooBig);
+impl_numeric_argument_type!(DoubleArgumentType, f64, read_double, DoubleTooSmall, DoubleTooBig, "double", "brigadier:double", f64, &["0", "1.2", ".5", "-1", "-.5", "-1234.56"]);
+// Clients have no unsigned parsers, so these are sent as the signed parser of the same
+// width with the minimum clamped to 0. The client accepts values up to the signed
+// maximum, larger ones are only accepted by the server.
+impl_numeric_argument_type!(UIntArgumentType, u32, read_uint, UIntegerTooSmall, UIntegerTooBig, "uint", "brigadier:integer", i32, &["0", "123"]);
+impl_numeric_argument_type!(ULongArgumentType, u64, read_ulong, ULongTooSmall, ULongTooBig, "ulong", "brigadier:long", i64, &["0", "123"]);
+
+/// A value that is either known while parsing or a placeholder for a macro variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroValue<'i, T> {
+    /// The name of the variable of a `$(name)` placeholder.
+    Placeholder(&'i str),
+    Concrete(T),
+}
+
+impl<'i, T> MacroValue<'i, T> {
+    #[inline]
+    pub fn placeholder(&self) -> Option<&'i str> {
+        match self {
+            Self::Placeholder(name) => Some(name),
+            Self::Concrete(_) => None,
+        }
+    }
+    #[inline]
+    pub fn concrete(&self) -> Option<&T> {
+        match self {
+            Self::Placeholder(_) => None,
+            Self::Concrete(value) => Some(value),
+        }
+    }
+}
+
+/// Accepts `$(name)` macro placeholders in addition to the values of the inner type,
+/// like the macro lines of function files. Placeholders are parsed without invoking
+/// the inner type and substituted at execution time, see
+/// [`CommandDispatcher::execute_with_macros`](crate::dispatcher::CommandDispatcher::execute_with_macros).
+pub struct MacroAwareArgumentType<A> {
+    pub inner: A,
+}
+
+impl<A> MacroAwareArgumentType<A> {
+    pub fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i, S, T> ArgumentType<'i, S> for MacroAwareArgumentType<T>
+where
+    S: CommandSource,
+    T: ArgumentType<'i, S>,
+{
+    type Output = MacroValue<'i, T::Output>;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Self::Output, CommandSyntaxError<'i>> {
+        if reader.can_read_macro_placeholder() {
+            return reader.read_macro_placeholder().map(MacroValue::Placeholder);
+        }
+        self.inner.parse(reader).map(MacroValue::Concrete)
+    }
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        self.inner.list_suggestions(context, builder).await
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        self.inner.examples()
+    }
+    /// Clients can't parse placeholders, so the type is never sent to them.
+    fn capabilities(&self) -> ArgCapabilities {
+        self.inner.capabilities() - ArgCapabilities::CLIENT_SIDE
+    }
+    fn suggestion_offset(&self, remaining: &str) -> usize {
+        self.inner.suggestion_offset(remaining)
+    }
+    fn macro_placeholder<'o>(&self, value: &'o Self::Output) -> Option<&'o str> {
+        value.placeholder()
+    }
+    /// Bound values are never placeholders.
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<Self::Output, String> {
+        self.inner.parse_owned(reader).map(MacroValue::Concrete)
+    }
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        self.inner.descriptor()
+    }
+    /// Placeholders are written as `$(name)`.
+    fn render_value(&self, value: &Self::Output) -> Option<String> {
+        match value {
+            MacroValue::Placeholder(name) => Some(format!("$({name})")),
+            MacroValue::Concrete(value) => self.inner.render_value(value),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Values of any type, so that one tree can hold argument types with unrelated outputs
+//! without an enum covering all of them.
+//!
+//! The usual way to mix argument types is an enum of the types with an enum of their
+//! outputs, see `examples/builders.rs`. [`BoxedArgumentType`] trades this boilerplate
+//! for an allocation per parsed argument and a type check when reading it, see
+//! [`CommandContext::get_argument_as`]. Matching on the outputs is no longer
+//! exhaustive, so a mismatch between a command and its arguments is only noticed
+//! when the command is run.
+
+use std::{any::Any, fmt};
+
+use super::{
+    value::{ArgumentValue, FromArgumentValue},
+    ArgCapabilities, ArgumentDescriptor, ArgumentType,
+};
+use crate::{
+    context::CommandContext,
+    errors::CommandSyntaxError,
+    suggestion::{Suggestions, SuggestionsBuilder},
+    CommandSource, FutureBounds, Shared, StringReader,
+};
+
+/// An owned value of any type which can be compared, the output of a
+/// [`BoxedArgumentType`].
+#[derive(Clone)]
+pub struct AnyValue(Shared<dyn Value>);
+
+/// The operations of [`AnyValue`] which need to know the type of the value.
+trait Value: FutureBounds {
+    fn as_any(&self) -> &dyn Any;
+    fn type_name(&self) -> &'static str;
+    fn eq_value(&self, other: &dyn Value) -> bool;
+}
+
+impl<T> Value for T
+where
+    T: PartialEq + FutureBounds + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+    fn eq_value(&self, other: &dyn Value) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<T>()
+            .is_some_and(|other| self == other)
+    }
+}
+
+impl AnyValue {
+    pub fn new<T>(value: T) -> Self
+    where
+        T: PartialEq + FutureBounds + 'static,
+    {
+        Self(Shared::new(value))
+    }
+    /// Whether the value is of type `T`.
+    #[inline]
+    pub fn is<T: 'static>(&self) -> bool {
+        self.0.as_any().is::<T>()
+    }
+    #[inline]
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+    /// The name of the type of the value, see [`std::any::type_name`].
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+}
+
+impl ArgumentValue for AnyValue {
+    #[inline]
+    fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+}
+
+/// Lets [`CommandContext::get_typed`] read the arguments of boxed types like
+/// [`CommandContext::get_argument_as`].
+impl<T: 'static> FromArgumentValue<AnyValue> for T {
+    #[inline]
+    fn from_value(value: &AnyValue) -> Option<&Self> {
+        value.downcast_ref()
+    }
+}
+
+impl PartialEq for AnyValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_value(&*other.0)
+    }
+}
+
+impl fmt::Debug for AnyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AnyValue").field(&self.type_name()).finish()
+    }
+}
+
+/// An argument type whose output is wrapped in an [`AnyValue`], created with
+/// [`BoxArgumentType::boxed`].
+///
+/// Argument types receive a context with the source and input when listing
+/// suggestions, but without the parsed arguments, since the context of the tree can't
+/// be passed through the box.
+pub struct BoxedArgumentType<S>(Box<dyn DynArgumentType<S>>);
+
+/// Wraps argument types into a [`BoxedArgumentType`].
+pub trait BoxArgumentType<S>: Sized {
+    fn boxed(self) -> BoxedArgumentType<S>;
+}
+
+impl<S, T> BoxArgumentType<S> for T
+where
+    S: CommandSource,
+    T: for<'i> ArgumentType<'i, S> + 'static,
+    for<'i> <T as ArgumentType<'i, S>>::Output: PartialEq + 'static,
+{
+    fn boxed(self) -> BoxedArgumentType<S> {
+        BoxedArgumentType(Box::new(self))
+    }
+}
+
+/// [`ArgumentType`] without lifetimes in the output and generics in the methods, so
+/// that it can be used as a trait object.
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+trait DynArgumentType<S>: FutureBounds
+where
+    S: CommandSource,
+{
+    fn parse<'i>(&self, reader: &mut StringReader<'i>) -> Result<AnyValue, CommandSyntaxError<'i>>;
+    async fn list_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, BoxedArgumentType<S>>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm>;
+    fn examples(&self) -> &'static [&'static str];
+    fn capabilities(&self) -> ArgCapabilities;
+    fn is_greedy(&self) -> bool;
+    fn suggestion_offset(&self, remaining: &str) -> usize;
+    fn protocol_id(&self) -> Option<&'static str>;
+    fn write_protocol_properties(&self, out: &mut Vec<u8>);
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<AnyValue, String>;
+    fn descriptor(&self) -> Option<ArgumentDescriptor>;
+    fn render_value(&self, value: &AnyValue) -> Option<String>;
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, T> DynArgumentType<S> for T
+where
+    S: CommandSource,
+    T: for<'i> ArgumentType<'i, S>,
+    for<'i> <T as ArgumentType<'i, S>>::Output: PartialEq + 'static,
+{
+    fn parse<'i>(&self, reader: &mut StringReader<'i>) -> Result<AnyValue, CommandSyntaxError<'i>> {
+        ArgumentType::parse(self, reader).map(AnyValue::new)
+    }
+    async fn list_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, BoxedArgumentType<S>>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm> {
+        ArgumentType::list_suggestions(self, context, builder).await
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        ArgumentType::<S>::examples(self)
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        ArgumentType::<S>::capabilities(self)
+    }
+    fn is_greedy(&self) -> bool {
+        ArgumentType::<S>::is_greedy(self)
+    }
+    fn suggestion_offset(&self, remaining: &str) -> usize {
+        ArgumentType::<S>::suggestion_offset(self, remaining)
+    }
+    fn protocol_id(&self) -> Option<&'static str> {
+        ArgumentType::<S>::protocol_id(self)
+    }
+    fn write_protocol_properties(&self, out: &mut Vec<u8>) {
+        ArgumentType::<S>::write_protocol_properties(self, out)
+    }
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<AnyValue, String> {
+        <T as ArgumentType<'static, S>>::parse_owned(self, reader).map(AnyValue::new)
+    }
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        ArgumentType::<S>::descriptor(self)
+    }
+    fn render_value(&self, value: &AnyValue) -> Option<String> {
+        let value = value.downcast_ref::<<T as ArgumentType<'static, S>>::Output>()?;
+        ArgumentType::<S>::render_value(self, value)
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i, S> ArgumentType<'i, S> for BoxedArgumentType<S>
+where
+    S: CommandSource,
+{
+    type Output = AnyValue;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<AnyValue, CommandSyntaxError<'i>> {
+        self.0.parse(reader)
+    }
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let context = context.without_arguments();
+        self.0.list_suggestions(&context, builder).await
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        self.0.examples()
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        self.0.capabilities()
+    }
+    fn is_greedy(&self) -> bool {
+        self.0.is_greedy()
+    }
+    fn suggestion_offset(&self, remaining: &str) -> usize {
+        self.0.suggestion_offset(remaining)
+    }
+    fn protocol_id(&self) -> Option<&'static str> {
+        self.0.protocol_id()
+    }
+    fn write_protocol_properties(&self, out: &mut Vec<u8>) {
+        self.0.write_protocol_properties(out)
+    }
+    fn parse_owned(&self, reader: &mut StringReader<'_>) -> Result<AnyValue, String> {
+        self.0.parse_owned(reader)
+    }
+    fn descriptor(&self) -> Option<ArgumentDescriptor> {
+        self.0.descriptor()
+    }
+    fn render_value(&self, value: &AnyValue) -> Option<String> {
+        self.0.render_value(value)
+    }
+}
@@ -0,0 +1,60 @@
+//! Typed access to the outputs of an enum of argument types, see `examples/builders.rs`.
+//!
+//! Commands read arguments with
+//! [`CommandContext::get_typed`](crate::context::CommandContext::get_typed) instead of
+//! matching on the enum, which reports a mismatch between a command and its arguments
+//! as an error. The traits are implemented for the variants of the enum with
+//! [`impl_from_argument_value!`](crate::impl_from_argument_value).
+
+/// The output of an argument type which holds values of several types.
+pub trait ArgumentValue {
+    /// The name of the type of the value, used in errors.
+    fn type_name(&self) -> &'static str;
+}
+
+/// A type which an [`ArgumentValue`] of type `V` may hold.
+pub trait FromArgumentValue<V>: Sized {
+    /// Returns the value if it's of this type.
+    fn from_value(value: &V) -> Option<&Self>;
+}
+
+/// Implements [`ArgumentValue`] for an enum whose variants hold one value each and
+/// [`FromArgumentValue`] for the types of the values. The enum is named by an
+/// identifier, so it has to be in scope.
+///
+/// ```
+/// # use brigadier::arguments::value::{ArgumentValue, FromArgumentValue};
+/// enum Value {
+///     Int(i32),
+///     String(String),
+/// }
+/// brigadier::impl_from_argument_value!(Value { Int => i32, String => String });
+///
+/// let value = Value::Int(3);
+/// assert_eq!(i32::from_value(&value), Some(&3));
+/// assert_eq!(String::from_value(&value), None);
+/// assert_eq!(value.type_name(), "i32");
+/// ```
+#[macro_export]
+macro_rules! impl_from_argument_value {
+    ($value:ident { $($variant:ident => $type:ty),+ $(,)? }) => {
+        impl $crate::arguments::value::ArgumentValue for $value {
+            fn type_name(&self) -> &'static str {
+                match self {
+                    $($value::$variant(_) => ::std::any::type_name::<$type>(),)+
+                }
+            }
+        }
+        $(
+            impl $crate::arguments::value::FromArgumentValue<$value> for $type {
+                fn from_value(value: &$value) -> ::std::option::Option<&Self> {
+                    match value {
+                        $value::$variant(value) => ::std::option::Option::Some(value),
+                        #[allow(unreachable_patterns)]
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            }
+        )+
+    };
+}
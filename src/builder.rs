@@ -0,0 +1,558 @@
+use crate::{
+    command::{Command, CommandBounds, CommandFuture, NodeCommand},
+    context::CommandContext,
+    errors::CommandSyntaxError,
+    requirement::{CommandRequirement, SharedRequirement},
+    suggestion::{SharedSuggestionProvider, SuggestionMode},
+    tree::{
+        CommandNodeComponent, CommandNodeId, CommandNodeType, RedirectModifier, SourceModifier,
+        Tree, TreeNode,
+    },
+    CommandSource, Shared,
+};
+
+/// Creates a builder for a literal node.
+pub fn literal<S, A>(literal: &str) -> LiteralArgumentBuilder<'_, S, A>
+where
+    S: CommandSource,
+{
+    LiteralArgumentBuilder {
+        literal,
+        aliases: Vec::new(),
+        builder: ArgumentBuilder::new(),
+    }
+}
+
+/// Creates a builder for an argument node.
+pub fn argument<S, A>(name: &str, argument_type: A) -> RequiredArgumentBuilder<'_, S, A>
+where
+    S: CommandSource,
+{
+    RequiredArgumentBuilder {
+        name,
+        argument_type,
+        suggestions: None,
+        suggestion_mode: SuggestionMode::Replace,
+        default: None,
+        builder: ArgumentBuilder::new(),
+    }
+}
+
+/// The properties shared by all node builders.
+pub struct ArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    children: Vec<AnyArgumentBuilder<'a, S, A>>,
+    command: Option<NodeCommand<S, A>>,
+    requirement: Option<SharedRequirement<S>>,
+    target: Option<CommandNodeId>,
+    modifier: Option<SourceModifier<S, A>>,
+    forks: bool,
+    description: Option<String>,
+    keywords: Vec<String>,
+    sensitive: bool,
+}
+
+impl<'a, S, A> ArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            command: None,
+            requirement: None,
+            target: None,
+            modifier: None,
+            forks: false,
+            description: None,
+            keywords: Vec::new(),
+            sensitive: false,
+        }
+    }
+    /// Adds a child node.
+    ///
+    /// # Panics
+    /// Redirected nodes can't have children.
+    pub fn then(&mut self, argument: impl Into<AnyArgumentBuilder<'a, S, A>>) -> &mut Self {
+        assert!(
+            self.target.is_none(),
+            "Cannot add children to a redirected node"
+        );
+        self.children.push(argument.into());
+        self
+    }
+    /// Configures a child node with `build` and adds it, so that nested commands can be
+    /// written in the shape of the tree.
+    ///
+    /// # Panics
+    /// Redirected nodes can't have children.
+    pub fn then_build<B>(&mut self, mut child: B, build: impl FnOnce(&mut B) -> &mut B) -> &mut Self
+    where
+        B: Into<AnyArgumentBuilder<'a, S, A>>,
+    {
+        build(&mut child);
+        self.then(child)
+    }
+    /// Sets the command which is run when the input ends at this node. Closures may
+    /// capture state, other [commands](Command) are set with
+    /// [`executes_command`](Self::executes_command).
+    pub fn executes<F>(&mut self, command: F) -> &mut Self
+    where
+        F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+            + CommandBounds
+            + 'static,
+    {
+        self.executes_command(command)
+    }
+    /// Sets a command which isn't a closure, e.g. a
+    /// [`TransactionalCommand`](crate::command::TransactionalCommand).
+    pub fn executes_command(&mut self, command: impl Command<S, A> + 'static) -> &mut Self {
+        self.command = Some(NodeCommand::Sync(Shared::new(command)));
+        self
+    }
+    /// Sets a command which needs to be awaited, see
+    /// [`AsyncCommand`](crate::command::AsyncCommand). Closures may capture state.
+    pub fn executes_async<F>(&mut self, command: F) -> &mut Self
+    where
+        F: for<'c, 'i> Fn(&'c CommandContext<'i, S, A>) -> CommandFuture<'c, 'i>
+            + CommandBounds
+            + 'static,
+    {
+        self.command = Some(NodeCommand::Async(Shared::new(command)));
+        self
+    }
+    /// Restricts the node to the sources which meet the [requirement](CommandRequirement),
+    /// others can neither parse nor see it. Closures need the type of their parameter,
+    /// like `|source: &Source| source.operator`.
+    pub fn requires(&mut self, requirement: impl CommandRequirement<S> + 'static) -> &mut Self {
+        self.requirement = Some(Shared::new(requirement));
+        self
+    }
+    pub fn redirect(&mut self, target: CommandNodeId) -> &mut Self {
+        self.forward(target, None, false)
+    }
+    /// Redirects the node to `target`, which continues with every source returned by
+    /// `modifier`. Closures may capture state.
+    ///
+    /// # Panics
+    /// Nodes with children can't be redirected.
+    pub fn fork<F>(&mut self, target: CommandNodeId, modifier: F) -> &mut Self
+    where
+        F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<Vec<S>, CommandSyntaxError<'i>>
+            + CommandBounds
+            + 'static,
+    {
+        self.forward(target, Some(Shared::new(modifier)), true)
+    }
+    /// Redirects the node to `target`, which continues parsing the input there.
+    ///
+    /// # Panics
+    /// Nodes with children can't be redirected.
+    pub fn forward(
+        &mut self,
+        target: CommandNodeId,
+        modifier: Option<RedirectModifier<S, A>>,
+        fork: bool,
+    ) -> &mut Self {
+        assert!(
+            self.children.is_empty(),
+            "Cannot forward a node with children"
+        );
+        self.target = Some(target);
+        self.modifier = modifier.map(SourceModifier::Multiple);
+        self.forks = fork;
+        self
+    }
+    /// Redirects the node to `target` and replaces the source for the rest of the
+    /// command with the one returned by `modifier`.
+    ///
+    /// # Panics
+    /// Nodes with children can't be redirected.
+    pub fn redirect_modifier<F>(&mut self, target: CommandNodeId, modifier: F) -> &mut Self
+    where
+        F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<S, CommandSyntaxError<'i>>
+            + CommandBounds
+            + 'static,
+    {
+        self.forward(target, None, false);
+        self.modifier = Some(SourceModifier::Single(Shared::new(modifier)));
+        self
+    }
+    /// Sets a description of the node for users.
+    pub fn describe(&mut self, description: impl Into<String>) -> &mut Self {
+        self.description = Some(description.into());
+        self
+    }
+    /// Adds search keywords for the node, which are inherited by its descendants, see
+    /// `CommandDispatcher::palette_index` (`palette` feature).
+    pub fn keywords(&mut self, keywords: &[&str]) -> &mut Self {
+        self.keywords
+            .extend(keywords.iter().map(|&keyword| keyword.to_owned()));
+        self
+    }
+    /// Marks the input of the node as sensitive, e.g. a password, so that it is never
+    /// recorded in the `SuggestionHistory` (`history` feature).
+    pub fn sensitive(&mut self) -> &mut Self {
+        self.sensitive = true;
+        self
+    }
+    /// Adds the node with all of its children to the tree.
+    fn add_to_tree(
+        self,
+        tree: &mut Tree<S, A>,
+        node_type: CommandNodeType,
+        insert: impl FnOnce(&mut Tree<S, A>, CommandNodeComponent<S, A>) -> CommandNodeId,
+    ) -> CommandNodeId {
+        let mut component = CommandNodeComponent::new(
+            node_type,
+            self.requirement,
+            self.target,
+            self.modifier,
+            self.forks,
+            self.command,
+        );
+        component.description = self.description.map(Into::into);
+        component.keywords = self.keywords.into_iter().map(Into::into).collect();
+        component.sensitive = self.sensitive;
+        let node_id = insert(tree, component);
+        for child in self.children {
+            child.add_to_parent(tree, node_id);
+        }
+        node_id
+    }
+}
+
+macro_rules! delegates {
+    () => {
+        /// Adds a child node.
+        ///
+        /// # Panics
+        /// Redirected nodes can't have children.
+        pub fn then(&mut self, argument: impl Into<AnyArgumentBuilder<'a, S, A>>) -> &mut Self {
+            self.builder.then(argument);
+            self
+        }
+        /// Configures a child node with `build` and adds it, so that nested commands can be
+        /// written in the shape of the tree.
+        ///
+        /// # Panics
+        /// Redirected nodes can't have children.
+        pub fn then_build<B>(
+            &mut self,
+            child: B,
+            build: impl FnOnce(&mut B) -> &mut B,
+        ) -> &mut Self
+        where
+            B: Into<AnyArgumentBuilder<'a, S, A>>,
+        {
+            self.builder.then_build(child, build);
+            self
+        }
+        /// Sets the command which is run when the input ends at this node. Closures may
+        /// capture state, other [commands](Command) are set with
+        /// [`executes_command`](Self::executes_command).
+        pub fn executes<F>(&mut self, command: F) -> &mut Self
+        where
+            F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+                + CommandBounds
+                + 'static,
+        {
+            self.builder.executes(command);
+            self
+        }
+        /// Sets a command which isn't a closure, e.g. a
+        /// [`TransactionalCommand`](crate::command::TransactionalCommand).
+        pub fn executes_command(&mut self, command: impl Command<S, A> + 'static) -> &mut Self {
+            self.builder.executes_command(command);
+            self
+        }
+        /// Sets a command which needs to be awaited, see
+        /// [`AsyncCommand`](crate::command::AsyncCommand). Closures may capture state.
+        pub fn executes_async<F>(&mut self, command: F) -> &mut Self
+        where
+            F: for<'c, 'i> Fn(&'c CommandContext<'i, S, A>) -> CommandFuture<'c, 'i>
+                + CommandBounds
+                + 'static,
+        {
+            self.builder.executes_async(command);
+            self
+        }
+        /// Restricts the node to the sources which meet the
+        /// [requirement](CommandRequirement), others can neither parse nor see it.
+        /// Closures need the type of their parameter, like
+        /// `|source: &Source| source.operator`.
+        pub fn requires(
+            &mut self,
+            requirement: impl CommandRequirement<S> + 'static,
+        ) -> &mut Self {
+            self.builder.requires(requirement);
+            self
+        }
+        pub fn redirect(&mut self, target: CommandNodeId) -> &mut Self {
+            self.builder.redirect(target);
+            self
+        }
+        /// Redirects the node to `target`, which continues with every source returned by
+        /// `modifier`. Closures may capture state.
+        ///
+        /// # Panics
+        /// Nodes with children can't be redirected.
+        pub fn fork<F>(&mut self, target: CommandNodeId, modifier: F) -> &mut Self
+        where
+            F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<Vec<S>, CommandSyntaxError<'i>>
+                + CommandBounds
+                + 'static,
+        {
+            self.builder.fork(target, modifier);
+            self
+        }
+        /// Redirects the node to `target`, which continues parsing the input there.
+        ///
+        /// # Panics
+        /// Nodes with children can't be redirected.
+        pub fn forward(
+            &mut self,
+            target: CommandNodeId,
+            modifier: Option<RedirectModifier<S, A>>,
+            fork: bool,
+        ) -> &mut Self {
+            self.builder.forward(target, modifier, fork);
+            self
+        }
+        /// Redirects the node to `target` and replaces the source for the rest of the
+        /// command with the one returned by `modifier`.
+        ///
+        /// # Panics
+        /// Nodes with children can't be redirected.
+        pub fn redirect_modifier<F>(&mut self, target: CommandNodeId, modifier: F) -> &mut Self
+        where
+            F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<S, CommandSyntaxError<'i>>
+                + CommandBounds
+                + 'static,
+        {
+            self.builder.redirect_modifier(target, modifier);
+            self
+        }
+        /// Sets a description of the node for users.
+        pub fn describe(&mut self, description: impl Into<String>) -> &mut Self {
+            self.builder.describe(description);
+            self
+        }
+        /// Adds search keywords for the node, which are inherited by its descendants, see
+        /// `CommandDispatcher::palette_index` (`palette` feature).
+        pub fn keywords(&mut self, keywords: &[&str]) -> &mut Self {
+            self.builder.keywords(keywords);
+            self
+        }
+        /// Marks the input of the node as sensitive, e.g. a password, so that it is never
+        /// recorded in the `SuggestionHistory` (`history` feature).
+        pub fn sensitive(&mut self) -> &mut Self {
+            self.builder.sensitive();
+            self
+        }
+    };
+}
+
+pub struct LiteralArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    literal: &'a str,
+    aliases: Vec<&'a str>,
+    builder: ArgumentBuilder<'a, S, A>,
+}
+
+impl<'a, S, A> LiteralArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    #[inline]
+    pub fn literal(&self) -> &'a str {
+        self.literal
+    }
+    /// Adds aliases which are registered next to this literal, see [`Tree::add_alias`].
+    pub fn aliases(&mut self, aliases: impl IntoIterator<Item = &'a str>) -> &mut Self {
+        self.aliases.extend(aliases);
+        self
+    }
+    delegates!();
+}
+
+impl<S, A> TreeNode<S, A> for LiteralArgumentBuilder<'_, S, A>
+where
+    S: CommandSource,
+{
+    fn add_to_tree(self, tree: &mut Tree<S, A>) -> CommandNodeId {
+        let literal = self.literal;
+        self.builder
+            .add_to_tree(tree, CommandNodeType::Literal, |tree, component| {
+                tree.insert_literal(literal, component)
+            })
+    }
+    fn add_to_parent(mut self, tree: &mut Tree<S, A>, parent_id: CommandNodeId) -> CommandNodeId {
+        let aliases = std::mem::take(&mut self.aliases);
+        let node_id = tree.add_node(self);
+        let node_id = tree.add_child(parent_id, node_id).unwrap();
+        for alias in aliases {
+            tree.add_alias(parent_id, alias, node_id);
+        }
+        node_id
+    }
+}
+
+pub struct RequiredArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    name: &'a str,
+    argument_type: A,
+    suggestions: Option<SharedSuggestionProvider<S, A>>,
+    suggestion_mode: SuggestionMode,
+    default: Option<&'static str>,
+    builder: ArgumentBuilder<'a, S, A>,
+}
+
+impl<'a, S, A> RequiredArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+    #[inline]
+    pub fn argument_type(&self) -> &A {
+        &self.argument_type
+    }
+    /// Suggests values with the provider instead of the argument type, e.g. with
+    /// [`StaticSuggestions`](crate::suggestion::StaticSuggestions).
+    pub fn suggests(&mut self, provider: SharedSuggestionProvider<S, A>) -> &mut Self {
+        self.suggestions = Some(provider);
+        self
+    }
+    /// Whether the suggestions of the [provider](Self::suggests) replace those of the
+    /// argument type or are merged with them.
+    pub fn suggestion_mode(&mut self, mode: SuggestionMode) -> &mut Self {
+        self.suggestion_mode = mode;
+        self
+    }
+    /// Makes the argument optional if it's the last one. If the input ends before it,
+    /// `default` is parsed with the argument type instead and the command of this node
+    /// is run, unless the parent is executable itself. Usage shows the argument in
+    /// brackets, like `[<message>]`.
+    pub fn optional_with_default(&mut self, default: &'static str) -> &mut Self {
+        self.default = Some(default);
+        self
+    }
+    delegates!();
+}
+
+impl<S, A> TreeNode<S, A> for RequiredArgumentBuilder<'_, S, A>
+where
+    S: CommandSource,
+{
+    fn add_to_tree(self, tree: &mut Tree<S, A>) -> CommandNodeId {
+        let Self {
+            name,
+            argument_type,
+            suggestions,
+            suggestion_mode,
+            default,
+            builder,
+        } = self;
+        builder.add_to_tree(tree, CommandNodeType::Argument, |tree, component| {
+            tree.insert_argument(
+                name,
+                argument_type,
+                suggestions,
+                suggestion_mode,
+                default,
+                component,
+            )
+        })
+    }
+}
+
+/// Either kind of node builder, used to store the children of a builder.
+pub enum AnyArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    Literal(LiteralArgumentBuilder<'a, S, A>),
+    Required(RequiredArgumentBuilder<'a, S, A>),
+}
+
+impl<'a, S, A> From<LiteralArgumentBuilder<'a, S, A>> for AnyArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    fn from(builder: LiteralArgumentBuilder<'a, S, A>) -> Self {
+        Self::Literal(builder)
+    }
+}
+
+impl<'a, S, A> From<RequiredArgumentBuilder<'a, S, A>> for AnyArgumentBuilder<'a, S, A>
+where
+    S: CommandSource,
+{
+    fn from(builder: RequiredArgumentBuilder<'a, S, A>) -> Self {
+        Self::Required(builder)
+    }
+}
+
+impl<S, A> TreeNode<S, A> for AnyArgumentBuilder<'_, S, A>
+where
+    S: CommandSource,
+{
+    fn add_to_tree(self, tree: &mut Tree<S, A>) -> CommandNodeId {
+        match self {
+            Self::Literal(builder) => builder.add_to_tree(tree),
+            Self::Required(builder) => builder.add_to_tree(tree),
+        }
+    }
+    fn add_to_parent(self, tree: &mut Tree<S, A>, parent_id: CommandNodeId) -> CommandNodeId {
+        match self {
+            Self::Literal(builder) => builder.add_to_parent(tree, parent_id),
+            Self::Required(builder) => builder.add_to_parent(tree, parent_id),
+        }
+    }
+}
+
+/// Adds a literal child to a builder and configures it in a nested scope, see
+/// [`ArgumentBuilder::then_build`].
+///
+/// ```
+/// # use brigadier::{build_argument, build_literal, builder::literal, arguments::BoolArgumentType};
+/// # #[derive(Clone)] struct Source;
+/// # impl brigadier::CommandSource for Source {}
+/// let mut gamerule = literal::<Source, BoolArgumentType>("gamerule");
+/// build_literal!(gamerule, "keepInventory", rule => {
+///     rule.executes(|_| Ok(0));
+///     build_argument!(rule, "value", BoolArgumentType, value => {
+///         value.executes(|_| Ok(1));
+///     });
+/// });
+/// ```
+#[macro_export]
+macro_rules! build_literal {
+    ($parent:expr, $literal:expr, $b:ident => $scope:block) => {
+        $parent.then_build($crate::builder::literal($literal), |$b| {
+            $scope;
+            $b
+        })
+    };
+}
+
+/// Adds an argument child to a builder and configures it in a nested scope, like
+/// [`build_literal!`].
+#[macro_export]
+macro_rules! build_argument {
+    ($parent:expr, $name:expr, $type:expr, $b:ident => $scope:block) => {
+        $parent.then_build($crate::builder::argument($name, $type), |$b| {
+            $scope;
+            $b
+        })
+    };
+}
@@ -1,27 +1,250 @@
-use crate::{context::CommandContext, errors::CommandSyntaxError};
-
-// #[derive(Clone, Copy)]
-// pub struct Command<'s, S: 's> {
-//     pub(crate) single_success: i32,
-//     pub(crate) function: &'s dyn for<'i> Fn(CommandContext<'s, 'i, S>) -> i32,
-// }
-
-// impl<'s, S: 's> Command<'s, S> {
-//     pub fn run<'i>(&self, context: CommandContext<'s, 'i, S>) -> i32 {
-//         (*self.function)(context)
-//     }
-// }
-
-// impl<'s, 'i, S: 's, F> From<&'s F> for Command<'s, S>
-// where
-//     F: Fn(CommandContext<'s, 'i, S>) -> i32,
-// {
-//     fn from(f: &'s F) -> Self {
-//         Self {
-//             single_success: 1,
-//             function: f as _,
-//         }
-//     }
-// }
-
-pub type Command<'i, S> = fn(&CommandContext<'i, S>) -> Result<i32, CommandSyntaxError<'i>>;
\ No newline at end of file
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    arguments::ArgumentType,
+    context::CommandContext,
+    errors::{CommandErrorType, CommandSyntaxError},
+    CommandSource, Shared,
+};
+
+/// The bounds of commands, which must be sendable across threads with the `sync`
+/// feature.
+#[cfg(feature = "sync")]
+pub trait CommandBounds: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T> CommandBounds for T where T: Send + Sync {}
+/// The bounds of commands, which must be sendable across threads with the `sync`
+/// feature.
+#[cfg(not(feature = "sync"))]
+pub trait CommandBounds {}
+#[cfg(not(feature = "sync"))]
+impl<T> CommandBounds for T {}
+
+/// The action of an executable node, see
+/// [`executes`](crate::builder::ArgumentBuilder::executes). Closures are commands and
+/// may capture state, e.g. a handle to the game.
+pub trait Command<S, A>: CommandBounds
+where
+    S: CommandSource,
+{
+    fn run<'i>(&self, context: &CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>;
+}
+
+impl<S, A, F> Command<S, A> for F
+where
+    S: CommandSource,
+    F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>> + CommandBounds,
+{
+    #[inline]
+    fn run<'i>(&self, context: &CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        self(context)
+    }
+}
+
+/// A command stored in the tree.
+pub type SharedCommand<S, A> = Shared<dyn Command<S, A>>;
+
+/// The future returned by an [`AsyncCommand`].
+pub type CommandFuture<'c, 'i> =
+    Pin<Box<dyn Future<Output = Result<i32, CommandSyntaxError<'i>>> + 'c>>;
+
+/// A command which needs to await something, e.g. a database. It can only be run by
+/// [`CommandDispatcher::execute_async`](crate::dispatcher::CommandDispatcher::execute_async).
+/// Closures returning a [`CommandFuture`] are async commands and may capture state, the
+/// future clones what it needs from them.
+pub trait AsyncCommand<S, A>: CommandBounds
+where
+    S: CommandSource,
+{
+    fn run<'c, 'i>(&self, context: &'c CommandContext<'i, S, A>) -> CommandFuture<'c, 'i>
+    where
+        A: ArgumentType<'i, S>;
+}
+
+impl<S, A, F> AsyncCommand<S, A> for F
+where
+    S: CommandSource,
+    F: for<'c, 'i> Fn(&'c CommandContext<'i, S, A>) -> CommandFuture<'c, 'i> + CommandBounds,
+{
+    #[inline]
+    fn run<'c, 'i>(&self, context: &'c CommandContext<'i, S, A>) -> CommandFuture<'c, 'i>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        self(context)
+    }
+}
+
+/// An async command stored in the tree.
+pub type SharedAsyncCommand<S, A> = Shared<dyn AsyncCommand<S, A>>;
+
+/// The command of an executable node.
+pub enum NodeCommand<S, A>
+where
+    S: CommandSource,
+{
+    Sync(SharedCommand<S, A>),
+    Async(SharedAsyncCommand<S, A>),
+}
+
+impl<S, A> NodeCommand<S, A>
+where
+    S: CommandSource,
+{
+    #[inline]
+    pub fn is_async(&self) -> bool {
+        matches!(self, Self::Async(_))
+    }
+}
+
+impl<S, A> Clone for NodeCommand<S, A>
+where
+    S: CommandSource,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Sync(command) => Self::Sync(Shared::clone(command)),
+            Self::Async(command) => Self::Async(Shared::clone(command)),
+        }
+    }
+}
+
+/// Called by the dispatcher after every command it executed, with whether the command
+/// succeeded and its result. Failed redirect modifiers and inputs without a command are
+/// reported as failures with the result 0.
+#[cfg(feature = "sync")]
+pub type ResultConsumer<S, A> =
+    Box<dyn for<'i> Fn(&CommandContext<'i, S, A>, bool, i32) + Send + Sync>;
+/// Called by the dispatcher after every command it executed, with whether the command
+/// succeeded and its result. Failed redirect modifiers and inputs without a command are
+/// reported as failures with the result 0.
+#[cfg(not(feature = "sync"))]
+pub type ResultConsumer<S, A> = Box<dyn for<'i> Fn(&CommandContext<'i, S, A>, bool, i32)>;
+
+/// Undoes the effects of a command which succeeded, see [`CompensatedStep`].
+#[cfg(feature = "sync")]
+pub type Compensator<S, A> = Shared<
+    dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<(), CommandSyntaxError<'i>> + Send + Sync,
+>;
+/// Undoes the effects of a command which succeeded, see [`CompensatedStep`].
+#[cfg(not(feature = "sync"))]
+pub type Compensator<S, A> =
+    Shared<dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<(), CommandSyntaxError<'i>>>;
+
+/// A step of a transaction, see [`CommandContext::run_compensated`].
+pub struct CompensatedStep<S, A>
+where
+    S: CommandSource,
+{
+    pub command: SharedCommand<S, A>,
+    pub compensator: Compensator<S, A>,
+}
+
+impl<S, A> CompensatedStep<S, A>
+where
+    S: CommandSource,
+{
+    pub fn new<F, C>(command: F, compensator: C) -> Self
+    where
+        F: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+            + CommandBounds
+            + 'static,
+        C: for<'i> Fn(&CommandContext<'i, S, A>) -> Result<(), CommandSyntaxError<'i>>
+            + CommandBounds
+            + 'static,
+    {
+        Self {
+            command: Shared::new(command),
+            compensator: Shared::new(compensator),
+        }
+    }
+}
+
+impl<S, A> Clone for CompensatedStep<S, A>
+where
+    S: CommandSource,
+{
+    fn clone(&self) -> Self {
+        Self {
+            command: Shared::clone(&self.command),
+            compensator: Shared::clone(&self.compensator),
+        }
+    }
+}
+
+/// A command which runs its steps as a transaction, see
+/// [`CommandContext::run_compensated`]. Every run is a transaction of its own, so if a
+/// fork runs the command for several sources, a failure only undoes the steps for the
+/// source it failed for.
+pub struct TransactionalCommand<S, A>
+where
+    S: CommandSource,
+{
+    steps: Vec<CompensatedStep<S, A>>,
+}
+
+impl<S, A> TransactionalCommand<S, A>
+where
+    S: CommandSource,
+{
+    pub fn new(steps: impl IntoIterator<Item = CompensatedStep<S, A>>) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+    #[inline]
+    pub fn steps(&self) -> &[CompensatedStep<S, A>] {
+        &self.steps
+    }
+}
+
+impl<S, A> Command<S, A> for TransactionalCommand<S, A>
+where
+    S: CommandSource,
+{
+    fn run<'i>(&self, context: &CommandContext<'i, S, A>) -> Result<i32, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        context.run_compensated(&self.steps).map_err(Into::into)
+    }
+}
+
+/// A failed transaction. The compensators of all steps before the failed one have
+/// been run in reverse order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionError<'i> {
+    /// The index of the step that failed
+    pub step: usize,
+    pub error: CommandSyntaxError<'i>,
+    /// The errors of compensators that failed, with the index of their step
+    pub compensator_errors: Vec<(usize, CommandSyntaxError<'i>)>,
+}
+
+impl std::fmt::Display for TransactionError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "step {} failed: {}", self.step, self.error)?;
+        for (step, error) in &self.compensator_errors {
+            write!(f, "; undoing step {step} failed: {error}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for TransactionError<'_> {}
+
+impl<'i> From<TransactionError<'i>> for CommandSyntaxError<'i> {
+    /// Bundles the failure with the errors of the compensators which failed.
+    fn from(error: TransactionError<'i>) -> Self {
+        if error.compensator_errors.is_empty() {
+            return error.error;
+        }
+        let mut errors = vec![error.error];
+        errors.extend(error.compensator_errors.into_iter().map(|(_, error)| error));
+        Self::new(CommandErrorType::DispatcherMultiple(errors))
+    }
+}
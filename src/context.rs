@@ -1,32 +1,552 @@
-use std::ops::Range;
+use std::{collections::HashMap, ops::Range};
 
-use crate::{command::Command, tree::RedirectModifier};
+use crate::{
+    arguments::{
+        any::AnyValue,
+        value::{ArgumentValue, FromArgumentValue},
+        ArgumentType,
+    },
+    command::{CompensatedStep, NodeCommand, TransactionError},
+    errors::{CommandErrorType, CommandSyntaxError},
+    tree::{CommandNodeComponent, CommandNodeId, SourceModifier, TypedLiteral},
+    CommandSource, Shared, StringReader,
+};
 
 pub type StringRange = Range<usize>;
 
-pub struct CommandContext<'i, S> where S: Clone {
-    pub source: S,
-    pub input: &'i str,
-    pub command: Command<'i, S>,
-    pub arguments: (),
-    pub root_node: (),
-    pub nodes: (),
-    pub range: StringRange,
-    child: (),
-    pub modifier: Option<RedirectModifier<'i, S>>,
-    pub forks: (),
+/// The methods of Brigadier's `StringRange` which [`Range`] lacks. Its `isEmpty` is
+/// [`Range::is_empty`].
+pub trait StringRangeExt {
+    /// The text of the range in the input.
+    fn get_text<'s>(&self, input: &'s str) -> &'s str;
+    /// The text of the range in the input of the reader, like Brigadier's overload of
+    /// `get` which takes an `ImmutableStringReader`.
+    fn get_reader_text<'i>(&self, reader: &StringReader<'i>) -> &'i str;
+    /// The number of bytes in the range.
+    fn length(&self) -> usize;
 }
 
-impl<'i, S> CommandContext<'i, S> where S: Clone {
+impl StringRangeExt for StringRange {
+    #[inline]
+    fn get_text<'s>(&self, input: &'s str) -> &'s str {
+        &input[self.clone()]
+    }
+    #[inline]
+    fn get_reader_text<'i>(&self, reader: &StringReader<'i>) -> &'i str {
+        self.get_text(reader.input())
+    }
+    #[inline]
+    fn length(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// A parsed command, created by a [`CommandContextBuilder`].
+pub struct CommandContext<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    source: S,
+    input: &'i str,
+    command: Option<NodeCommand<S, A>>,
+    arguments: Shared<HashMap<Shared<str>, ParsedArgument<A::Output>>>,
+    root_node: CommandNodeId,
+    nodes: Vec<ParsedCommandNode>,
+    range: StringRange,
+    child: Option<Box<Self>>,
+    modifier: Option<SourceModifier<S, A>>,
+    forks: bool,
+}
+
+impl<'i, S, A> CommandContext<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+    #[inline]
+    pub fn child(&self) -> Option<&Self> {
+        self.child.as_deref()
+    }
+    /// Follows the children through all redirects to the context of the node which is
+    /// executed, or returns this context if it has no child.
+    pub fn last_child(&self) -> &Self {
+        self.iter_chain().last().unwrap()
+    }
+    /// Iterates over this context and its children, from the outermost to the
+    /// innermost.
+    pub fn iter_chain(&self) -> impl Iterator<Item = &Self> {
+        std::iter::successors(Some(self), |context| context.child())
+    }
+    /// The number of contexts in the chain, which is 1 if no redirect was followed.
+    pub fn depth(&self) -> usize {
+        self.iter_chain().count()
+    }
+    /// Returns the value of an argument by name.
+    pub fn get_argument(&self, name: &str) -> Option<&A::Output> {
+        self.arguments.get(name).map(|argument| &argument.result)
+    }
+    /// Returns the argument the cursor is in, with its name, e.g. to show hints for the
+    /// argument being edited. Like in [`node_at`](Self::node_at), the spaces after an
+    /// argument belong to it, so there is none while the cursor is on a literal.
+    pub fn find_argument_at(&self, cursor: usize) -> Option<(&str, &ParsedArgument<A::Output>)> {
+        let node = parsed_node_at(&self.nodes, cursor)?;
+        self.arguments
+            .iter()
+            .find(|(_, argument)| argument.range == node.range)
+            .map(|(name, argument)| (&**name, argument))
+    }
+    /// Returns the node the cursor is in. A cursor at the end of a node or in the spaces
+    /// after it belongs to that node, and the later node wins if two touch. Nodes after
+    /// a redirect are in the [child](Self::child).
+    pub fn node_at(&self, cursor: usize) -> Option<CommandNodeId> {
+        parsed_node_at(&self.nodes, cursor).map(|node| node.node)
+    }
+    /// The whole input the command was parsed from.
+    #[inline]
+    pub fn input(&self) -> &'i str {
+        self.input
+    }
+    /// The range of the input parsed by this context, without its child.
+    #[inline]
+    pub fn range(&self) -> StringRange {
+        self.range.clone()
+    }
+    /// The nodes parsed by this context in the order of the input, without those of
+    /// its child.
+    #[inline]
+    pub fn nodes(&self) -> &[ParsedCommandNode] {
+        &self.nodes
+    }
     #[inline]
     pub fn has_nodes(&self) -> bool {
-        //self.nodes
-        todo!("CommandContext.has_nodes")
+        !self.nodes.is_empty()
+    }
+    /// The command of the last node, which is run if the context has no child.
+    #[inline]
+    pub fn command(&self) -> Option<NodeCommand<S, A>> {
+        self.command.clone()
+    }
+    /// The modifier of the redirect to the child, if any.
+    #[inline]
+    pub fn redirect_modifier(&self) -> Option<SourceModifier<S, A>> {
+        self.modifier.clone()
+    }
+    /// Whether the redirect to the child forks.
+    #[inline]
+    pub fn is_forked(&self) -> bool {
+        self.forks
+    }
+    /// Runs the commands of the steps in order and returns the sum of their results.
+    /// If a command fails, the compensators of the steps that succeeded are run in
+    /// reverse order. A node can run steps like this with a
+    /// [`TransactionalCommand`](crate::command::TransactionalCommand).
+    #[allow(clippy::result_large_err)]
+    pub fn run_compensated(
+        &self,
+        steps: &[CompensatedStep<S, A>],
+    ) -> Result<i32, TransactionError<'i>> {
+        let mut result = 0;
+        for (step, current) in steps.iter().enumerate() {
+            match current.command.run(self) {
+                Ok(value) => result += value,
+                Err(error) => {
+                    let compensator_errors = steps[..step]
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .filter_map(|(i, done)| (done.compensator)(self).err().map(|e| (i, e)))
+                        .collect();
+                    return Err(TransactionError {
+                        step,
+                        error,
+                        compensator_errors,
+                    });
+                }
+            }
+        }
+        Ok(result)
+    }
+    /// Creates a copy of this context for another argument type, with the source, input
+    /// and nodes but without arguments, command or children.
+    pub(crate) fn without_arguments<B>(&self) -> CommandContext<'i, S, B>
+    where
+        B: ArgumentType<'i, S>,
+    {
+        CommandContext {
+            source: self.source.clone(),
+            input: self.input,
+            command: None,
+            arguments: Shared::new(HashMap::new()),
+            root_node: self.root_node,
+            nodes: self.nodes.clone(),
+            range: self.range.clone(),
+            child: None,
+            modifier: None,
+            forks: self.forks,
+        }
+    }
+    /// Creates a copy of this context with another source. The arguments are shared.
+    pub fn clone_for(&self, source: S) -> Self {
+        Self {
+            source,
+            input: self.input,
+            command: self.command.clone(),
+            arguments: Shared::clone(&self.arguments),
+            root_node: self.root_node,
+            nodes: self.nodes.clone(),
+            range: self.range.clone(),
+            child: self.child.clone(),
+            modifier: self.modifier.clone(),
+            forks: self.forks,
+        }
+    }
+}
+
+impl<'i, S, A> CommandContext<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S, Output = AnyValue>,
+{
+    /// Returns the value of an argument by name as a `T`, for arguments of a
+    /// [`BoxedArgumentType`](crate::arguments::any::BoxedArgumentType).
+    pub fn get_argument_as<T: 'static>(&self, name: &str) -> Result<&T, CommandSyntaxError<'i>> {
+        let value = self.get_argument(name).ok_or_else(|| {
+            CommandSyntaxError::new(CommandErrorType::DispatcherNoSuchArgument {
+                name: name.to_owned(),
+            })
+        })?;
+        value.downcast_ref().ok_or_else(|| {
+            CommandSyntaxError::new(CommandErrorType::DispatcherWrongArgumentType {
+                name: name.to_owned(),
+                expected: std::any::type_name::<T>(),
+                actual: value.type_name(),
+            })
+        })
+    }
+}
+
+impl<'i, S, A> CommandContext<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+    A::Output: ArgumentValue,
+{
+    /// Returns the value of an argument by name as a `T`, for argument types whose
+    /// output is an enum of values, see [`impl_from_argument_value!`].
+    ///
+    /// [`impl_from_argument_value!`]: crate::impl_from_argument_value
+    pub fn get_typed<T>(&self, name: &str) -> Result<&T, CommandSyntaxError<'i>>
+    where
+        T: FromArgumentValue<A::Output>,
+    {
+        let value = self.get_argument(name).ok_or_else(|| {
+            CommandSyntaxError::new(CommandErrorType::DispatcherNoSuchArgument {
+                name: name.to_owned(),
+            })
+        })?;
+        T::from_value(value).ok_or_else(|| {
+            CommandSyntaxError::new(CommandErrorType::DispatcherWrongArgumentType {
+                name: name.to_owned(),
+                expected: std::any::type_name::<T>(),
+                actual: value.type_name(),
+            })
+        })
+    }
+}
+
+impl<'i, S, A> Clone for CommandContext<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    fn clone(&self) -> Self {
+        self.clone_for(self.source.clone())
+    }
+}
+
+/// Accumulates the nodes and arguments visited while parsing a command.
+pub struct CommandContextBuilder<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    pub(crate) source: S,
+    pub(crate) root: CommandNodeId,
+    pub(crate) range: StringRange,
+    pub(crate) nodes: Vec<ParsedCommandNode>,
+    pub(crate) arguments: HashMap<Shared<str>, ParsedArgument<A::Output>>,
+    pub(crate) command: Option<NodeCommand<S, A>>,
+    pub(crate) child: Option<Box<Self>>,
+    pub(crate) modifier: Option<SourceModifier<S, A>>,
+    pub(crate) forks: bool,
+    /// Only recorded if requested, see [`CommandDispatcher::set_capture_typed_form`].
+    ///
+    /// [`CommandDispatcher::set_capture_typed_form`]: crate::dispatcher::CommandDispatcher::set_capture_typed_form
+    pub(crate) typed_literals: Option<Vec<TypedLiteral<'i>>>,
+}
+
+impl<'i, S, A> CommandContextBuilder<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    pub fn new(source: S, root: CommandNodeId, start: usize) -> Self {
+        Self {
+            source,
+            root,
+            range: start..start,
+            nodes: Vec::new(),
+            arguments: HashMap::new(),
+            command: None,
+            child: None,
+            modifier: None,
+            forks: false,
+            typed_literals: None,
+        }
+    }
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+    #[inline]
+    pub fn root(&self) -> CommandNodeId {
+        self.root
+    }
+    #[inline]
+    pub fn range(&self) -> StringRange {
+        self.range.clone()
+    }
+    #[inline]
+    pub fn nodes(&self) -> &[ParsedCommandNode] {
+        &self.nodes
+    }
+    #[inline]
+    pub fn arguments(&self) -> &HashMap<Shared<str>, ParsedArgument<A::Output>> {
+        &self.arguments
+    }
+    #[inline]
+    pub fn command(&self) -> Option<NodeCommand<S, A>> {
+        self.command.clone()
+    }
+    #[inline]
+    pub fn child(&self) -> Option<&Self> {
+        self.child.as_deref()
+    }
+    /// Follows the children through all redirects, see [`CommandContext::last_child`].
+    pub fn last_child(&self) -> &Self {
+        let mut context = self;
+        while let Some(child) = context.child() {
+            context = child;
+        }
+        context
+    }
+    /// Finds the node whose children complete the input at the cursor and where their
+    /// suggestions start. Returns `None` if the cursor is before this context.
+    pub fn find_suggestion_context(&self, cursor: usize) -> Option<SuggestionContext> {
+        if self.range.start > cursor {
+            return None;
+        }
+        if self.range.end < cursor {
+            if let Some(child) = &self.child {
+                return child.find_suggestion_context(cursor);
+            }
+            // The cursor is after the last node, past the separator following it
+            return Some(match self.nodes.last() {
+                Some(last) => SuggestionContext {
+                    parent: last.node,
+                    start_pos: last.range.end + 1,
+                },
+                None => SuggestionContext {
+                    parent: self.root,
+                    start_pos: self.range.start,
+                },
+            });
+        }
+        let mut previous = self.root;
+        for node in &self.nodes {
+            if node.range.start <= cursor && cursor <= node.range.end {
+                return Some(SuggestionContext {
+                    parent: previous,
+                    start_pos: node.range.start,
+                });
+            }
+            previous = node.node;
+        }
+        Some(SuggestionContext {
+            parent: previous,
+            start_pos: self.range.start,
+        })
+    }
+    pub fn with_source(&mut self, source: S) -> &mut Self {
+        self.source = source;
+        self
+    }
+    pub fn with_argument(
+        &mut self,
+        name: impl Into<Shared<str>>,
+        argument: ParsedArgument<A::Output>,
+    ) -> &mut Self {
+        self.arguments.insert(name.into(), argument);
+        self
+    }
+    pub fn with_command(&mut self, command: Option<NodeCommand<S, A>>) -> &mut Self {
+        self.command = command;
+        self
+    }
+    /// Records a parsed node. The redirect of the context is taken from the last node.
+    pub fn with_node(
+        &mut self,
+        node_id: CommandNodeId,
+        node: &CommandNodeComponent<S, A>,
+        range: StringRange,
+    ) -> &mut Self {
+        self.range = self.range.start.min(range.start)..self.range.end.max(range.end);
+        self.nodes.push(ParsedCommandNode {
+            node: node_id,
+            range,
+        });
+        self.modifier = node.redirect_modifier.clone();
+        self.forks = node.forks;
+        self
+    }
+    /// Sets the context of a redirect target.
+    pub fn with_child(&mut self, child: Self) -> &mut Self {
+        self.child = Some(Box::new(child));
+        self
+    }
+    /// Creates the context for the given input, including all child contexts.
+    pub fn build(self, input: &'i str) -> CommandContext<'i, S, A> {
+        CommandContext {
+            source: self.source,
+            input,
+            command: self.command,
+            arguments: Shared::new(self.arguments),
+            root_node: self.root,
+            nodes: self.nodes,
+            range: self.range,
+            child: self.child.map(|child| Box::new(child.build(input))),
+            modifier: self.modifier,
+            forks: self.forks,
+        }
+    }
+}
+
+impl<'i, S, A> Clone for CommandContextBuilder<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            root: self.root,
+            range: self.range.clone(),
+            nodes: self.nodes.clone(),
+            arguments: self.arguments.clone(),
+            command: self.command.clone(),
+            child: self.child.clone(),
+            modifier: self.modifier.clone(),
+            forks: self.forks,
+            typed_literals: self.typed_literals.clone(),
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The value of an argument together with the range of input it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedArgument<T> {
+    pub range: StringRange,
+    pub result: T,
+}
+
+/// Where suggestions for a cursor begin, see
+/// [`CommandContextBuilder::find_suggestion_context`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuggestionContext {
+    /// The node whose children are asked for suggestions.
+    pub parent: CommandNodeId,
+    /// The position at which the suggestions start.
+    pub start_pos: usize,
+}
+
+/// A node that was visited while parsing, together with the range of input it matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedCommandNode {
+    pub node: CommandNodeId,
+    pub range: StringRange,
+}
+
+/// Returns the node the cursor is in, see [`CommandContext::node_at`].
+pub(crate) fn parsed_node_at(
+    nodes: &[ParsedCommandNode],
+    cursor: usize,
+) -> Option<&ParsedCommandNode> {
+    // The nodes are ordered by their position in the input
+    nodes.iter().rev().find(|node| node.range.start <= cursor)
+}
+
+/// Where in the input an error occurred, see [`StringReader::context`]. Errors about a
+/// whole token, like an invalid number, know its span as well, see
+/// [`StringReader::context_span`].
+///
+/// It's displayed with the span in markers, e.g. `speed <<fast>>`, or with empty
+/// markers at the cursor.
+///
+/// [`StringReader::context`]: crate::StringReader::context
+/// [`StringReader::context_span`]: crate::StringReader::context_span
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct StringReaderContext<'i> {
     pub input: &'i str,
     pub cursor: usize,
+    pub span: Option<StringRange>,
+}
+
+impl<'i> StringReaderContext<'i> {
+    pub fn new(input: &'i str, cursor: usize) -> Self {
+        Self {
+            input,
+            cursor,
+            span: None,
+        }
+    }
+    #[inline]
+    pub fn input(&self) -> &'i str {
+        self.input
+    }
+    #[inline]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    #[inline]
+    pub fn span(&self) -> Option<StringRange> {
+        self.span.clone()
+    }
+    /// The input within the span.
+    pub fn span_text(&self) -> Option<&'i str> {
+        self.span.clone().map(|span| &self.input[span])
+    }
+}
+
+impl std::fmt::Display for StringReaderContext<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let span = self.span.clone().unwrap_or(self.cursor..self.cursor);
+        write!(
+            f,
+            "{}<<{}>>{}",
+            &self.input[..span.start],
+            &self.input[span.clone()],
+            &self.input[span.end..]
+        )
+    }
+}
+
+impl std::fmt::Debug for StringReaderContext<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StringReaderContext({:?})", self.to_string())
+    }
 }
@@ -0,0 +1,1261 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    ambiguity::{AmbiguityChecker, CheckProgress},
+    arguments::{ArgumentDescriptor, ArgumentType},
+    command::{NodeCommand, ResultConsumer},
+    context::{
+        parsed_node_at, CommandContext, CommandContextBuilder, ParsedCommandNode, StringRange,
+        StringRangeExt, StringReaderContext, SuggestionContext,
+    },
+    errors::{CommandErrorType, CommandSyntaxError},
+    shutdown::ShutdownController,
+    suggestion::{Suggestions, SuggestionsBuilder},
+    tree::{
+        CommandNodeId, CommandNodeType, LiteralMatching, RootCommandNode, Tree, TreeNode,
+        TypedLiteral,
+    },
+    CommandSource, Shared, StringReader,
+};
+#[cfg(feature = "history")]
+use crate::{
+    command::CommandBounds,
+    history::{HistoryKey, SuggestionHistory},
+};
+#[cfg(feature = "palette")]
+use crate::palette::PaletteIndex;
+#[cfg(feature = "packet")]
+use crate::tree::protocol::{self, ProtocolNode};
+
+pub mod chain;
+
+/// The character that separates the arguments of a command.
+pub const ARGUMENT_SEPARATOR: char = ' ';
+
+/// The default of [`CommandDispatcher::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 255;
+
+pub struct CommandDispatcher<S, A>
+where
+    S: CommandSource,
+{
+    tree: Tree<S, A>,
+    root: CommandNodeId,
+    literal_matching: LiteralMatching,
+    capture_typed_form: bool,
+    shutdown: ShutdownController,
+    consumer: Option<ResultConsumer<S, A>>,
+    #[cfg(feature = "history")]
+    history: Option<SuggestionHistory<S>>,
+    max_depth: usize,
+    chain_separator: Option<Box<str>>,
+}
+
+impl<S, A> Default for CommandDispatcher<S, A>
+where
+    S: CommandSource,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A> CommandDispatcher<S, A>
+where
+    S: CommandSource,
+{
+    pub fn new() -> Self {
+        let mut tree = Tree::new();
+        let root = tree.add_node(RootCommandNode);
+        Self {
+            tree,
+            root,
+            literal_matching: LiteralMatching::default(),
+            capture_typed_form: false,
+            shutdown: ShutdownController::new(),
+            consumer: None,
+            #[cfg(feature = "history")]
+            history: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            chain_separator: None,
+        }
+    }
+    #[inline]
+    pub fn root(&self) -> CommandNodeId {
+        self.root
+    }
+    #[inline]
+    pub fn tree(&self) -> &Tree<S, A> {
+        &self.tree
+    }
+    #[inline]
+    pub fn tree_mut(&mut self) -> &mut Tree<S, A> {
+        &mut self.tree
+    }
+    /// Builds the states of all
+    /// [stateful suggestion providers](crate::suggestion::StatefulSuggestionProvider),
+    /// see [`Tree::warm_suggestions`].
+    pub fn warm_suggestions(&self) {
+        self.tree.warm_suggestions();
+    }
+    #[inline]
+    pub fn literal_matching(&self) -> LiteralMatching {
+        self.literal_matching
+    }
+    pub fn set_literal_matching(&mut self, literal_matching: LiteralMatching) {
+        self.literal_matching = literal_matching;
+    }
+    /// The number of nodes a command may consist of, including the nodes after
+    /// redirects. Parsing fails with
+    /// [`DispatcherMaxDepthExceeded`](CommandErrorType::DispatcherMaxDepthExceeded)
+    /// after that many nodes, so that input can't nest commands indefinitely through
+    /// a cycle of redirects.
+    #[inline]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+    #[inline]
+    pub fn chain_separator(&self) -> Option<&str> {
+        self.chain_separator.as_deref()
+    }
+    /// Allows several commands in one input, separated by `separator`, e.g. `;` for
+    /// `say one; say two`. See [`execute_chain`](Self::execute_chain). Separators inside
+    /// quoted strings or brackets don't separate commands.
+    ///
+    /// # Panics
+    /// The separator must not be empty.
+    pub fn set_chain_separator(&mut self, separator: Option<&str>) {
+        assert!(
+            separator != Some(""),
+            "The chain separator must not be empty"
+        );
+        self.chain_separator = separator.map(Into::into);
+    }
+    /// Records how each literal was typed while parsing, see [`ParseResults::typed_form`].
+    pub fn set_capture_typed_form(&mut self, capture: bool) {
+        self.capture_typed_form = capture;
+    }
+    /// Sets the callback which is notified about the result of every executed command,
+    /// including each branch of a fork. Branches are reported in the order their
+    /// sources were returned by the redirect modifier.
+    pub fn set_consumer(&mut self, consumer: ResultConsumer<S, A>) {
+        self.consumer = Some(consumer);
+    }
+    /// Records the arguments of every successfully executed command, except for
+    /// [sensitive](crate::builder::ArgumentBuilder::sensitive) ones. Returns the
+    /// history, so that providers can suggest from it with
+    /// [`SuggestionHistory::with_history`].
+    ///
+    /// `key` returns whose history a source records into, `capacity_per_key` is the
+    /// number of values kept per history key and argument node.
+    #[cfg(feature = "history")]
+    pub fn enable_history(
+        &mut self,
+        capacity_per_key: usize,
+        key: impl Fn(&S) -> HistoryKey + CommandBounds + 'static,
+    ) -> SuggestionHistory<S> {
+        let history = SuggestionHistory::new(capacity_per_key, key);
+        self.history = Some(history.clone());
+        history
+    }
+    #[cfg(feature = "history")]
+    #[inline]
+    pub fn history(&self) -> Option<&SuggestionHistory<S>> {
+        self.history.as_ref()
+    }
+    /// Lists the executable commands the source can use with their keywords, to be
+    /// searched by a command palette. See [`PaletteCache`](crate::palette::PaletteCache)
+    /// to reuse the index for sources with the same permissions.
+    #[cfg(feature = "palette")]
+    pub fn palette_index(&self, source: &S) -> PaletteIndex {
+        PaletteIndex::build(&self.tree, self.root, source)
+    }
+    /// Returns a controller to shut the dispatcher down and observe running executions.
+    pub fn shutdown_controller(&self) -> ShutdownController {
+        self.shutdown.clone()
+    }
+    /// Adds a command to the root node and returns the id of the registered node.
+    /// Registering a command with the same name as an existing one merges both.
+    pub fn register(&mut self, command: impl TreeNode<S, A>) -> CommandNodeId {
+        command.add_to_parent(&mut self.tree, self.root)
+    }
+    /// Returns the names of the nodes from the root to the node, or an empty path if the
+    /// node can't be reached through children. Redirects are not followed.
+    pub fn get_path(&self, node_id: CommandNodeId) -> Vec<&str> {
+        let mut stack = vec![(self.root, 0)];
+        let mut path = Vec::new();
+        while let Some((current, depth)) = stack.pop() {
+            if depth > 0 {
+                path.truncate(depth - 1);
+                path.push(current);
+            }
+            if current == node_id {
+                return path
+                    .into_iter()
+                    .filter_map(|node_id| self.tree.name(node_id))
+                    .collect();
+            }
+            for child_id in self.tree.sorted_children(current).into_iter().rev() {
+                if !path.contains(&child_id) {
+                    stack.push((child_id, depth + 1));
+                }
+            }
+        }
+        Vec::new()
+    }
+    /// Finds the node at the path of names from the root, see [`CommandDispatcher::get_path`].
+    pub fn find_node(&self, path: &[&str]) -> Option<CommandNodeId> {
+        let mut node_id = self.root;
+        for &name in path {
+            node_id = self.tree.get(node_id)?.child(name)?;
+        }
+        Some(node_id)
+    }
+    /// Registers an alias of a command, see [`Tree::add_alias`].
+    pub fn register_alias(&mut self, alias: &str, target: CommandNodeId) -> CommandNodeId {
+        self.tree.add_alias(self.root, alias, target)
+    }
+}
+
+impl<S, A> CommandDispatcher<S, A>
+where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    /// Calls the consumer for every node below the root which accepts the examples of
+    /// a sibling, with the parent, the node, the sibling and the ambiguous inputs.
+    pub fn find_ambiguities<F>(&self, mut consumer: F)
+    where
+        F: FnMut(CommandNodeId, CommandNodeId, CommandNodeId, HashSet<String>),
+    {
+        let mut checker = AmbiguityChecker::for_node(&self.tree, self.root);
+        if let CheckProgress::Finished(findings) = checker.check_some(&self.tree, usize::MAX) {
+            for finding in findings {
+                consumer(
+                    finding.parent,
+                    finding.child,
+                    finding.sibling,
+                    finding.inputs.into_iter().collect(),
+                );
+            }
+        }
+    }
+    /// Converts the nodes the source can use into the nodes of Minecraft's commands
+    /// packet, see [`protocol::to_protocol_nodes`].
+    #[cfg(feature = "packet")]
+    pub fn to_protocol_nodes(&self, source: &S) -> Vec<ProtocolNode> {
+        protocol::to_protocol_nodes(&self.tree, self.root, source)
+    }
+    /// Parses the command without executing it. Parsing never fails as a whole, the
+    /// returned results describe how far the input could be parsed and why the
+    /// remaining nodes failed.
+    pub fn parse<'i>(&self, input: &'i str, source: S) -> ParseResults<'i, S, A> {
+        self.parse_reader(StringReader::new(input), source)
+    }
+    pub fn parse_reader<'i>(&self, reader: StringReader<'i>, source: S) -> ParseResults<'i, S, A> {
+        let context = self.new_context(source, self.root, reader.cursor());
+        if self.shutdown.is_shutting_down() {
+            return ParseResults {
+                context,
+                reader,
+                exceptions: HashMap::from([(self.root, shutting_down_error())]),
+            };
+        }
+        self.parse_nodes(self.root, reader, context, 0)
+    }
+    /// Parses input that was edited since it was parsed into `previous`, e.g. on every
+    /// keystroke in an editor. The source must be the one `previous` was parsed with.
+    ///
+    /// The nodes of `previous` which lie in the prefix both inputs have in common are
+    /// reused, as long as they are followed by a separator in that prefix. Parsing
+    /// continues after the last reused node, so the results are the same as those of
+    /// [`parse`](Self::parse), provided that argument types don't look past the
+    /// separator after their value. Nodes are only reused if none of their siblings
+    /// parse as well, since the rest of the input decides between those.
+    pub fn parse_incremental<'i>(
+        &self,
+        previous: &ParseResults<'i, S, A>,
+        new_input: &'i str,
+        source: S,
+    ) -> ParseResults<'i, S, A> {
+        let reader = StringReader::new(new_input);
+        if self.shutdown.is_shutting_down() || previous.context.root != self.root {
+            return self.parse_reader(reader, source);
+        }
+        let common = previous
+            .reader
+            .input()
+            .char_indices()
+            .zip(new_input.chars())
+            .find(|&((_, a), b)| a != b)
+            .map_or_else(
+                || previous.reader.input().len().min(new_input.len()),
+                |((i, _), _)| i,
+            );
+        match self.reparse_after_prefix(&previous.context, reader, common, &source, 0) {
+            Some(results) => results,
+            None => self.parse_reader(reader, source),
+        }
+    }
+    /// Reuses the nodes of the context which end in the common prefix and parses the
+    /// rest of the input, see [`parse_incremental`](Self::parse_incremental). Returns
+    /// `None` if no node of the context can be reused. `depth` is the number of nodes
+    /// before the context.
+    fn reparse_after_prefix<'i>(
+        &self,
+        previous: &CommandContextBuilder<'i, S, A>,
+        mut reader: StringReader<'i>,
+        common: usize,
+        source: &S,
+        depth: usize,
+    ) -> Option<ParseResults<'i, S, A>> {
+        let input = reader.input();
+        let mut parent_id = previous.root;
+        let mut reused = 0;
+        for parsed in &previous.nodes {
+            let end = parsed.range.end;
+            if end >= common
+                || !input[end..].starts_with(ARGUMENT_SEPARATOR)
+                || input.len() < end + 2
+                || self.has_parsing_sibling(parent_id, parsed, input, source)
+                || !self.has_own_argument(previous, parsed)
+            {
+                break;
+            }
+            parent_id = parsed.node;
+            reused += 1;
+        }
+        if reused == 0 {
+            return None;
+        }
+
+        let mut context = self.new_context(source.clone(), previous.root, previous.range.start);
+        let mut literals = 0;
+        for parsed in &previous.nodes[..reused] {
+            let node = self.tree.get(parsed.node).unwrap();
+            match node.node_type() {
+                CommandNodeType::Literal => literals += 1,
+                CommandNodeType::Argument => {
+                    let (name, argument) = previous
+                        .arguments
+                        .get_key_value(self.tree.name(parsed.node).unwrap())
+                        .unwrap();
+                    context.with_argument(Shared::clone(name), argument.clone());
+                }
+                CommandNodeType::Root => {}
+            }
+            context.with_node(parsed.node, node, parsed.range.clone());
+            context.with_command(node.command.clone());
+        }
+        if let (Some(typed_literals), Some(previous)) =
+            (&mut context.typed_literals, &previous.typed_literals)
+        {
+            typed_literals.extend_from_slice(&previous[..literals]);
+        }
+
+        let last = &previous.nodes[reused - 1];
+        let depth = depth + reused;
+        reader.set_cursor(last.range.end + 1);
+        let Some(redirect) = self.tree.get(last.node).unwrap().redirect else {
+            return Some(self.parse_nodes(last.node, reader, context, depth));
+        };
+        let parse = previous
+            .child()
+            .filter(|_| reused == previous.nodes.len())
+            .and_then(|child| self.reparse_after_prefix(child, reader, common, source, depth))
+            .unwrap_or_else(|| {
+                let child_context = self.new_context(source.clone(), redirect, reader.cursor());
+                self.parse_nodes(redirect, reader, child_context, depth)
+            });
+        context.with_child(parse.context);
+        Some(ParseResults {
+            context,
+            reader: parse.reader,
+            exceptions: parse.exceptions,
+        })
+    }
+    /// Whether the argument value of the context belongs to the parsed node, and not to
+    /// a later argument with the same name. True for other nodes.
+    fn has_own_argument<'i>(
+        &self,
+        context: &CommandContextBuilder<'i, S, A>,
+        parsed: &ParsedCommandNode,
+    ) -> bool {
+        let node = self.tree.get(parsed.node).unwrap();
+        node.node_type() != CommandNodeType::Argument
+            || context
+                .arguments
+                .get(self.tree.name(parsed.node).unwrap())
+                .is_some_and(|argument| argument.range == parsed.range)
+    }
+    /// Whether another child of the parent parses at the start of the node, in which
+    /// case the rest of the input decides which of them is used.
+    fn has_parsing_sibling(
+        &self,
+        parent_id: CommandNodeId,
+        parsed: &ParsedCommandNode,
+        input: &str,
+        source: &S,
+    ) -> bool {
+        let mut start = StringReader::new(input);
+        start.set_cursor(parsed.range.start);
+        self.tree
+            .get_relevant_nodes(parent_id, &start, self.literal_matching)
+            .into_iter()
+            .filter(|&child_id| child_id != parsed.node)
+            .any(|child_id| {
+                if !self.tree.get(child_id).unwrap().can_use(source) {
+                    return false;
+                }
+                let mut reader = start;
+                let mut context = CommandContextBuilder::new(source.clone(), parent_id, 0);
+                self.tree
+                    .parse_node(
+                        parent_id,
+                        child_id,
+                        &mut reader,
+                        &mut context,
+                        self.literal_matching,
+                    )
+                    .is_ok()
+                    && (!reader.can_read() || reader.peek() == Some(ARGUMENT_SEPARATOR))
+            })
+    }
+    /// Lists the suggestions for the parsed input at the cursor, asking the children of
+    /// the node before the cursor which the source can use. The input after the cursor
+    /// is ignored.
+    pub async fn get_completion_suggestions<'i>(
+        &self,
+        parse: &ParseResults<'i, S, A>,
+        cursor: usize,
+    ) -> Result<Suggestions<'i, 'i>, CommandSyntaxError<'i>> {
+        let input = parse.reader.input();
+        let mut cursor = cursor.min(input.len());
+        while !input.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let Some(suggestion_context) = parse.context.find_suggestion_context(cursor) else {
+            return Ok(Suggestions::empty());
+        };
+        let truncated = &input[..cursor];
+        let start = suggestion_context.start_pos.min(cursor);
+        let context = parse.context.clone().build(truncated);
+        let builder = SuggestionsBuilder::new_with_lowercase(truncated, start);
+        let mut all = Vec::new();
+        for child_id in self.tree.sorted_children(suggestion_context.parent) {
+            if !self.tree.get(child_id).unwrap().can_use(&parse.context.source) {
+                continue;
+            }
+            all.push(
+                self.tree
+                    .list_suggestions(child_id, &context, builder.restart())
+                    .await?,
+            );
+        }
+        Ok(Suggestions::merge(truncated, all))
+    }
+    /// Parses and executes a command, see [`execute`](Self::execute).
+    pub fn execute_input<'i>(
+        &self,
+        input: &'i str,
+        source: S,
+    ) -> Result<i32, CommandSyntaxError<'i>> {
+        self.execute(self.parse(input, source))
+    }
+    /// Executes a parsed command. While it runs, it is counted as in flight by the
+    /// [shutdown controller](Self::shutdown_controller).
+    ///
+    /// A redirect with a modifier runs the rest of the command once for every source the
+    /// modifier returns. Without a fork, the result is the sum of all command results and
+    /// the first error of a command or modifier aborts the execution. Once a forking
+    /// redirect was passed, errors only abort their own branch and the result is the
+    /// number of commands that succeeded.
+    ///
+    /// [Async commands](crate::command::AsyncCommand) can't be run this way and fail with
+    /// [`DispatcherAsyncCommand`](CommandErrorType::DispatcherAsyncCommand), use
+    /// [`execute_async`](Self::execute_async) instead.
+    pub fn execute<'i>(
+        &self,
+        parse: ParseResults<'i, S, A>,
+    ) -> Result<i32, CommandSyntaxError<'i>> {
+        let mut execution = pin!(self.run(parse, false));
+        match execution
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(result) => result,
+            // Only async commands can suspend the execution, but they are rejected
+            Poll::Pending => unreachable!("synchronous execution suspended"),
+        }
+    }
+    /// Executes a parsed command like [`execute`](Self::execute), awaiting async
+    /// commands.
+    pub async fn execute_async<'i>(
+        &self,
+        parse: ParseResults<'i, S, A>,
+    ) -> Result<i32, CommandSyntaxError<'i>> {
+        self.run(parse, true).await
+    }
+    /// Parses and executes a line of a function file with `$(name)` placeholders, see
+    /// [`MacroAwareArgumentType`](crate::arguments::MacroAwareArgumentType).
+    ///
+    /// Every placeholder is replaced by parsing its bound value with
+    /// [`ArgumentType::parse_owned`], the rest of the input is not parsed again. Errors in
+    /// a bound value point at the start of its placeholder.
+    pub fn execute_with_macros<'i>(
+        &self,
+        input: &'i str,
+        source: S,
+        bindings: &HashMap<String, String>,
+    ) -> Result<i32, CommandSyntaxError<'i>> {
+        let mut parse = self.parse(input, source);
+        let mut missing: Vec<(usize, String)> = Vec::new();
+        let mut next = Some(&mut parse.context);
+        while let Some(context) = next {
+            self.bind_macros(context, input, bindings, &mut missing)?;
+            next = context.child.as_deref_mut();
+        }
+        if let Some(&(cursor, _)) = missing.first() {
+            let mut names: Vec<String> = Vec::new();
+            for (_, name) in missing {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::DispatcherMissingMacroBinding { names },
+                StringReaderContext::new(input, cursor),
+            ));
+        }
+        self.execute(parse)
+    }
+    /// Replaces the placeholders of the arguments in the context, without its child.
+    fn bind_macros<'i>(
+        &self,
+        context: &mut CommandContextBuilder<'i, S, A>,
+        input: &'i str,
+        bindings: &HashMap<String, String>,
+        missing: &mut Vec<(usize, String)>,
+    ) -> Result<(), CommandSyntaxError<'i>> {
+        for parsed_node in &context.nodes {
+            let Some(argument_type) = self.tree.argument_type(parsed_node.node) else {
+                continue;
+            };
+            let Some(argument) = context
+                .arguments
+                .values_mut()
+                .find(|argument| argument.range == parsed_node.range)
+            else {
+                continue;
+            };
+            let Some(variable) = argument_type.macro_placeholder(&argument.result) else {
+                continue;
+            };
+            let variable = variable.to_owned();
+            let Some(value) = bindings.get(&variable) else {
+                missing.push((argument.range.start, variable));
+                continue;
+            };
+            let placeholder = StringReaderContext {
+                input,
+                cursor: argument.range.start,
+                span: Some(argument.range.clone()),
+            };
+            let mut reader = StringReader::new(value);
+            argument.result = argument_type.parse_owned(&mut reader).map_err(|message| {
+                CommandSyntaxError::with_context(
+                    CommandErrorType::DispatcherInvalidMacroBinding {
+                        name: variable,
+                        message,
+                    },
+                    placeholder.clone(),
+                )
+            })?;
+            if reader.can_read() {
+                return Err(CommandSyntaxError::with_context(
+                    CommandErrorType::DispatcherExpectedArgumentSeparator,
+                    placeholder,
+                ));
+            }
+        }
+        Ok(())
+    }
+    async fn run<'i>(
+        &self,
+        parse: ParseResults<'i, S, A>,
+        allow_async: bool,
+    ) -> Result<i32, CommandSyntaxError<'i>> {
+        if self.shutdown.is_shutting_down() {
+            return Err(shutting_down_error());
+        }
+        let _guard = self.shutdown.track();
+        let ParseResults {
+            context,
+            reader,
+            mut exceptions,
+        } = parse;
+        if reader.can_read() {
+            return Err(if let Some(error) = most_specific_error(&mut exceptions) {
+                error
+            } else if context.range.is_empty() {
+                CommandSyntaxError::with_context(
+                    CommandErrorType::DispatcherUnknownCommand,
+                    reader.context(),
+                )
+            } else {
+                CommandSyntaxError::with_context(
+                    CommandErrorType::DispatcherUnknownArgument,
+                    reader.context(),
+                )
+            });
+        }
+        let mut result = 0;
+        let mut successful_forks = 0;
+        let mut forked = false;
+        let mut found_command = false;
+        let original = context.build(reader.input());
+        let mut contexts = vec![original.clone()];
+        while !contexts.is_empty() {
+            let mut next = Vec::new();
+            for context in &contexts {
+                if let Some(child) = context.child() {
+                    forked |= context.is_forked();
+                    if !child.has_nodes() {
+                        continue;
+                    }
+                    found_command = true;
+                    let Some(modifier) = context.redirect_modifier() else {
+                        next.push(child.clone_for(context.source().clone()));
+                        continue;
+                    };
+                    match modifier.apply(context) {
+                        Ok(sources) => {
+                            next.extend(sources.into_iter().map(|source| child.clone_for(source)))
+                        }
+                        Err(error) => {
+                            self.notify(context, false, 0);
+                            if !forked {
+                                return Err(error);
+                            }
+                        }
+                    }
+                } else if let Some(command) = context.command() {
+                    found_command = true;
+                    let outcome = match command {
+                        NodeCommand::Sync(command) => command.run(context),
+                        NodeCommand::Async(command) if allow_async => command.run(context).await,
+                        NodeCommand::Async(_) => Err(CommandSyntaxError::new(
+                            CommandErrorType::DispatcherAsyncCommand,
+                        )),
+                    };
+                    match outcome {
+                        Ok(value) => {
+                            result += value;
+                            self.notify(context, true, value);
+                            successful_forks += 1;
+                        }
+                        Err(error) => {
+                            self.notify(context, false, 0);
+                            if !forked {
+                                return Err(error);
+                            }
+                        }
+                    }
+                }
+            }
+            contexts = next;
+        }
+        if !found_command {
+            self.notify(&original, false, 0);
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::DispatcherUnknownCommand,
+                reader.context(),
+            ));
+        }
+        #[cfg(feature = "history")]
+        if successful_forks > 0 {
+            if let Some(history) = &self.history {
+                self.record_history(history, &original);
+            }
+        }
+        Ok(if forked { successful_forks } else { result })
+    }
+    /// Records the values of the arguments in the context and its children, except for
+    /// sensitive ones.
+    #[cfg(feature = "history")]
+    fn record_history(&self, history: &SuggestionHistory<S>, context: &CommandContext<'_, S, A>) {
+        let key = history.key(context.source());
+        for current in context.iter_chain() {
+            for parsed in current.nodes() {
+                let node = self.tree.get(parsed.node).unwrap();
+                if node.node_type() == CommandNodeType::Argument && !node.is_sensitive() {
+                    history.record(key, parsed.node, parsed.range.get_text(current.input()));
+                }
+            }
+        }
+    }
+    fn notify(&self, context: &CommandContext<'_, S, A>, success: bool, result: i32) {
+        if let Some(consumer) = &self.consumer {
+            consumer(context, success, result);
+        }
+    }
+    /// Parses input which was already split into arguments, e.g. by a frontend with a
+    /// field per argument. Every node consumes exactly one token, so tokens don't need
+    /// to be quoted, except for [greedy](ArgumentType::is_greedy) arguments, which
+    /// consume the remaining tokens joined with single spaces.
+    ///
+    /// The ranges in the context are positions in the [joined](Tokens::joined) tokens,
+    /// while errors have a cursor relative to their token.
+    pub fn parse_tokens<'i>(
+        &self,
+        tokens: &'i Tokens,
+        source: S,
+    ) -> Result<CommandContextBuilder<'i, S, A>, TokenError<'i>> {
+        if self.shutdown.is_shutting_down() {
+            return Err(TokenError {
+                token: 0,
+                error: shutting_down_error(),
+            });
+        }
+        let context = self.new_context(source, self.root, 0);
+        self.parse_tokens_from(self.root, tokens, 0, context)
+    }
+    /// Parses and executes input which was already split into arguments, see
+    /// [`parse_tokens`](Self::parse_tokens) and [`execute`](Self::execute). Errors of
+    /// the execution are reported at the last token.
+    pub fn execute_tokens<'i>(&self, tokens: &'i Tokens, source: S) -> Result<i32, TokenError<'i>> {
+        let context = self.parse_tokens(tokens, source)?;
+        let input = tokens.joined();
+        let mut reader = StringReader::new(input);
+        reader.set_cursor(input.len());
+        let parse = ParseResults {
+            context,
+            reader,
+            exceptions: HashMap::new(),
+        };
+        self.execute(parse)
+            .map_err(|error| tokens.error_at(tokens.len().saturating_sub(1), error))
+    }
+    fn parse_tokens_from<'i>(
+        &self,
+        node_id: CommandNodeId,
+        tokens: &'i Tokens,
+        index: usize,
+        context_so_far: CommandContextBuilder<'i, S, A>,
+    ) -> Result<CommandContextBuilder<'i, S, A>, TokenError<'i>> {
+        let Some(range) = tokens.range(index) else {
+            let mut context = context_so_far;
+            if index > 0 {
+                self.tree
+                    .parse_defaults(node_id, &mut context)
+                    .map_err(|error| TokenError {
+                        token: index - 1,
+                        error: CommandSyntaxError::new(error.error_type),
+                    })?;
+            }
+            return Ok(context);
+        };
+        if index >= self.max_depth {
+            let token = StringReader::new(&tokens.joined()[range]);
+            return Err(TokenError {
+                token: index,
+                error: max_depth_error(self.max_depth, token.context()),
+            });
+        }
+        let mut children = self.tree.sorted_children(node_id);
+        // Literals take precedence over arguments
+        children.sort_by_key(|&child_id| {
+            self.tree.get(child_id).unwrap().node_type() != CommandNodeType::Literal
+        });
+        let mut furthest_error: Option<TokenError<'i>> = None;
+        for child_id in children {
+            let child = self.tree.get(child_id).unwrap();
+            if !child.can_use(&context_so_far.source) {
+                continue;
+            }
+            // Greedy arguments take the remaining tokens
+            let greedy = self
+                .tree
+                .argument_type(child_id)
+                .is_some_and(ArgumentType::is_greedy);
+            let (end, next) = match greedy {
+                true => (tokens.joined().len(), tokens.len()),
+                false => (range.end, index + 1),
+            };
+            let mut context = context_so_far.clone();
+            let mut reader = StringReader::new(&tokens.joined()[..end]);
+            reader.set_cursor(range.start);
+            let parsed = self
+                .tree
+                .parse_node(
+                    node_id,
+                    child_id,
+                    &mut reader,
+                    &mut context,
+                    self.literal_matching,
+                )
+                .and_then(|()| {
+                    if reader.can_read() {
+                        Err(CommandSyntaxError::with_context(
+                            CommandErrorType::DispatcherExpectedArgumentSeparator,
+                            reader.context(),
+                        ))
+                    } else {
+                        Ok(())
+                    }
+                });
+            let result = match parsed {
+                Err(error) => Err(tokens.error_at(index, error)),
+                Ok(()) => {
+                    context.with_command(child.command.clone());
+                    match (child.redirect, tokens.range(next)) {
+                        (Some(redirect), Some(next_range)) => {
+                            let child_context = self.new_context(
+                                context.source.clone(),
+                                redirect,
+                                next_range.start,
+                            );
+                            self.parse_tokens_from(redirect, tokens, next, child_context)
+                                .map(|child_context| {
+                                    context.with_child(child_context);
+                                    context
+                                })
+                        }
+                        _ => self.parse_tokens_from(child_id, tokens, next, context),
+                    }
+                }
+            };
+            match result {
+                Ok(context) => return Ok(context),
+                Err(error) => {
+                    if furthest_error
+                        .as_ref()
+                        .is_none_or(|furthest| error.token > furthest.token)
+                    {
+                        furthest_error = Some(error);
+                    }
+                }
+            }
+        }
+        Err(furthest_error.unwrap_or_else(|| TokenError {
+            token: index,
+            error: CommandSyntaxError::new(if context_so_far.nodes().is_empty() {
+                CommandErrorType::DispatcherUnknownCommand
+            } else {
+                CommandErrorType::DispatcherUnknownArgument
+            }),
+        }))
+    }
+    fn new_context<'i>(
+        &self,
+        source: S,
+        root: CommandNodeId,
+        start: usize,
+    ) -> CommandContextBuilder<'i, S, A> {
+        let mut context = CommandContextBuilder::new(source, root, start);
+        if self.capture_typed_form {
+            context.typed_literals = Some(Vec::new());
+        }
+        context
+    }
+    /// `depth` is the number of nodes parsed before the children of the node.
+    fn parse_nodes<'i>(
+        &self,
+        node_id: CommandNodeId,
+        original_reader: StringReader<'i>,
+        context_so_far: CommandContextBuilder<'i, S, A>,
+        depth: usize,
+    ) -> ParseResults<'i, S, A> {
+        let mut exceptions = HashMap::new();
+        let mut potentials = Vec::new();
+
+        let relevant =
+            self.tree
+                .get_relevant_nodes(node_id, &original_reader, self.literal_matching);
+        for &child_id in &relevant {
+            let child = self.tree.get(child_id).unwrap();
+            if !child.can_use(&context_so_far.source) {
+                continue;
+            }
+            let mut context = context_so_far.clone();
+            let mut reader = original_reader;
+            if let Err(error) = self.tree.parse_node(
+                node_id,
+                child_id,
+                &mut reader,
+                &mut context,
+                self.literal_matching,
+            ) {
+                // Errors without a position failed at the start of the node
+                let context = error.context.unwrap_or(original_reader.context());
+                exceptions.insert(
+                    child_id,
+                    CommandSyntaxError::with_context(error.error_type, context),
+                );
+                continue;
+            }
+            if reader.can_read() && reader.peek() != Some(ARGUMENT_SEPARATOR) {
+                exceptions.insert(
+                    child_id,
+                    CommandSyntaxError::with_context(
+                        CommandErrorType::DispatcherExpectedArgumentSeparator,
+                        reader.context(),
+                    ),
+                );
+                continue;
+            }
+
+            context.with_command(child.command.clone());
+            if reader.can_read_length(if child.redirect.is_none() { 2 } else { 1 }) {
+                reader.skip();
+                if depth + 1 >= self.max_depth {
+                    exceptions.insert(child_id, max_depth_error(self.max_depth, reader.context()));
+                    continue;
+                }
+                if let Some(redirect) = child.redirect {
+                    let child_context =
+                        self.new_context(context.source.clone(), redirect, reader.cursor());
+                    let parse = self.parse_nodes(redirect, reader, child_context, depth + 1);
+                    context.with_child(parse.context);
+                    return ParseResults {
+                        context,
+                        reader: parse.reader,
+                        exceptions: parse.exceptions,
+                    };
+                } else {
+                    potentials.push(self.parse_nodes(child_id, reader, context, depth + 1));
+                }
+            } else {
+                // Errors of a default are the fault of the tree, not of the input
+                if let Err(error) = self.tree.parse_defaults(child_id, &mut context) {
+                    exceptions.insert(
+                        child_id,
+                        CommandSyntaxError::with_context(error.error_type, reader.context()),
+                    );
+                    continue;
+                }
+                potentials.push(ParseResults {
+                    context,
+                    reader,
+                    exceptions: HashMap::new(),
+                });
+            }
+        }
+
+        if potentials.is_empty() {
+            // Report the literals which don't match as well, without parsing them
+            for child_id in self.tree.get(node_id).unwrap().literals() {
+                if !relevant.contains(&child_id)
+                    && self.tree.get(child_id).unwrap().can_use(&context_so_far.source)
+                {
+                    exceptions.insert(
+                        child_id,
+                        self.tree.literal_incorrect(child_id, &original_reader),
+                    );
+                }
+            }
+        }
+        potentials
+            .into_iter()
+            .min_by(ParseResults::cmp_potentials)
+            .unwrap_or(ParseResults {
+                context: context_so_far,
+                reader: original_reader,
+                exceptions,
+            })
+    }
+}
+
+/// The error of the branch which got furthest. If several branches failed at the same
+/// position, their errors are combined, unless they are all mismatched literals, which
+/// are reported as an unknown command or argument instead.
+fn most_specific_error<'i>(
+    exceptions: &mut HashMap<CommandNodeId, CommandSyntaxError<'i>>,
+) -> Option<CommandSyntaxError<'i>> {
+    let furthest = exceptions.values().map(error_cursor).max()?;
+    let mut errors: Vec<_> = exceptions
+        .drain()
+        .map(|(_, error)| error)
+        .filter(|error| error_cursor(error) == furthest)
+        .collect();
+    if errors.len() == 1 {
+        return errors.pop();
+    }
+    if errors
+        .iter()
+        .all(|error| matches!(error.error_type, CommandErrorType::LiteralIncorrect { .. }))
+    {
+        return None;
+    }
+    errors.sort_by_cached_key(|error| error.error_type.to_string());
+    let context = errors[0].context.clone();
+    Some(CommandSyntaxError {
+        error_type: CommandErrorType::DispatcherMultiple(errors),
+        context,
+    })
+}
+
+fn error_cursor(error: &CommandSyntaxError<'_>) -> usize {
+    error.context.as_ref().map_or(0, |context| context.cursor)
+}
+
+fn shutting_down_error<'i>() -> CommandSyntaxError<'i> {
+    CommandSyntaxError::new(CommandErrorType::DispatcherShuttingDown)
+}
+
+fn max_depth_error(max_depth: usize, context: StringReaderContext<'_>) -> CommandSyntaxError<'_> {
+    CommandSyntaxError::with_context(
+        CommandErrorType::DispatcherMaxDepthExceeded { max_depth },
+        context,
+    )
+}
+
+/// The outcome of parsing a command, which may be incomplete.
+pub struct ParseResults<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    context: CommandContextBuilder<'i, S, A>,
+    reader: StringReader<'i>,
+    exceptions: HashMap<CommandNodeId, CommandSyntaxError<'i>>,
+}
+
+impl<'i, S, A> ParseResults<'i, S, A>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    #[inline]
+    pub fn context(&self) -> &CommandContextBuilder<'i, S, A> {
+        &self.context
+    }
+    /// The reader at the position where parsing stopped.
+    #[inline]
+    pub fn reader(&self) -> &StringReader<'i> {
+        &self.reader
+    }
+    /// The errors of all nodes that failed to parse at the position where parsing stopped.
+    #[inline]
+    pub fn exceptions(&self) -> &HashMap<CommandNodeId, CommandSyntaxError<'i>> {
+        &self.exceptions
+    }
+    /// Describes how the literals of the command were typed, if the dispatcher was set
+    /// to [capture](CommandDispatcher::set_capture_typed_form) it.
+    pub fn typed_form(&self) -> Option<TypedForm<'i>> {
+        let input = self.reader.input();
+        let mut literals = Vec::new();
+        let mut separators = SeparatorStyle::Single;
+        let mut previous_end = None;
+        let mut context = Some(&self.context);
+        while let Some(current) = context {
+            literals.extend_from_slice(current.typed_literals.as_ref()?);
+            for node in current.nodes() {
+                if let Some(end) = previous_end {
+                    if input[end..node.range.start].chars().count() > 1 {
+                        separators = SeparatorStyle::Multiple;
+                    }
+                }
+                previous_end = Some(node.range.end);
+            }
+            context = current.child();
+        }
+        Some(TypedForm {
+            literals,
+            separators,
+        })
+    }
+    /// Where the suggestions for the cursor begin, see
+    /// [`get_completion_suggestions`](CommandDispatcher::get_completion_suggestions).
+    pub fn find_suggestion_context(&self, cursor: usize) -> Option<SuggestionContext> {
+        self.context.find_suggestion_context(cursor)
+    }
+    /// Finds the parsed node the cursor is in, including nodes parsed after a redirect.
+    /// Like [`CommandContext::node_at`], a cursor at the end of a node or in the spaces
+    /// after it belongs to that node.
+    pub fn find_node_at(&self, cursor: usize) -> Option<&ParsedCommandNode> {
+        self.find_context_at(cursor).map(|(_, node)| node)
+    }
+    /// Like [`find_node_at`](Self::find_node_at), with the context the node was parsed
+    /// into.
+    fn find_context_at(
+        &self,
+        cursor: usize,
+    ) -> Option<(&CommandContextBuilder<'i, S, A>, &ParsedCommandNode)> {
+        let mut contexts = vec![];
+        let mut context = Some(&self.context);
+        while let Some(current) = context {
+            contexts.push(current);
+            context = current.child();
+        }
+        // Nodes after a redirect are further in the input
+        contexts.into_iter().rev().find_map(|context| {
+            parsed_node_at(context.nodes(), cursor).map(|node| (context, node))
+        })
+    }
+    /// Describes the node at the cursor, e.g. for a tooltip. The tree has to be the one
+    /// the input was parsed with.
+    pub fn hover_info<'a>(&'a self, tree: &'a Tree<S, A>, cursor: usize) -> Option<HoverInfo<'a>> {
+        let (context, parsed) = self.find_context_at(cursor)?;
+        let node = tree.get(parsed.node)?;
+        let name = tree.name(parsed.node)?;
+        let argument_type = tree.argument_type(parsed.node);
+        let value = argument_type.and_then(|argument_type| {
+            let argument = context
+                .arguments()
+                .get(name)
+                .filter(|argument| argument.range == parsed.range)?;
+            argument_type.render_value(&argument.result)
+        });
+        Some(HoverInfo {
+            node: parsed.node,
+            name,
+            kind: node.node_type(),
+            description: node.description(),
+            argument_type: argument_type.and_then(ArgumentType::descriptor),
+            range: parsed.range.clone(),
+            text: parsed.range.get_text(self.reader.input()),
+            value,
+        })
+    }
+    /// Prefers results that consumed the whole input, then results without errors.
+    fn cmp_potentials(a: &Self, b: &Self) -> Ordering {
+        match (a.reader.can_read(), b.reader.can_read()) {
+            (false, true) => return Ordering::Less,
+            (true, false) => return Ordering::Greater,
+            _ => {}
+        }
+        match (a.exceptions.is_empty(), b.exceptions.is_empty()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            // The branch which got further has the more specific errors
+            _ => b.furthest_exception().cmp(&a.furthest_exception()),
+        }
+    }
+    fn furthest_exception(&self) -> Option<usize> {
+        self.exceptions.values().map(error_cursor).max()
+    }
+}
+
+/// Input which was already split into arguments, see [`CommandDispatcher::parse_tokens`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tokens {
+    joined: String,
+    ranges: Vec<StringRange>,
+}
+
+impl Tokens {
+    pub fn new<'t>(tokens: impl IntoIterator<Item = &'t str>) -> Self {
+        let mut joined = String::new();
+        let mut ranges = Vec::new();
+        for token in tokens {
+            if !ranges.is_empty() {
+                joined.push(ARGUMENT_SEPARATOR);
+            }
+            let start = joined.len();
+            joined.push_str(token);
+            ranges.push(start..joined.len());
+        }
+        Self { joined, ranges }
+    }
+    /// The tokens separated by single spaces, which is the input of parsed contexts.
+    #[inline]
+    pub fn joined(&self) -> &str {
+        &self.joined
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.range(index).map(|range| &self.joined[range])
+    }
+    /// The position of the token in the [joined](Self::joined) tokens.
+    pub fn range(&self, index: usize) -> Option<StringRange> {
+        self.ranges.get(index).cloned()
+    }
+    /// Moves the cursor of the error from the joined tokens into the token.
+    fn error_at<'i>(&'i self, index: usize, mut error: CommandSyntaxError<'i>) -> TokenError<'i> {
+        if let (Some(context), Some(range)) = (&mut error.context, self.range(index)) {
+            if context.input.len() >= range.start && context.cursor >= range.start {
+                context.input = &context.input[range.start..];
+                context.cursor -= range.start;
+                context.span = context.span.take().map(|span| {
+                    span.start.saturating_sub(range.start)..span.end.saturating_sub(range.start)
+                });
+            }
+        }
+        TokenError {
+            token: index,
+            error,
+        }
+    }
+}
+
+impl<'t> FromIterator<&'t str> for Tokens {
+    fn from_iter<T: IntoIterator<Item = &'t str>>(tokens: T) -> Self {
+        Self::new(tokens)
+    }
+}
+
+/// An error while parsing tokens, see [`CommandDispatcher::parse_tokens`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenError<'i> {
+    /// The index of the token that failed to parse
+    pub token: usize,
+    /// The error, with a cursor relative to the token
+    pub error: CommandSyntaxError<'i>,
+}
+
+impl std::fmt::Display for TokenError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in argument {}", self.error, self.token + 1)
+    }
+}
+impl std::error::Error for TokenError<'_> {}
+
+/// Information about a parsed node, see [`ParseResults::hover_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverInfo<'a> {
+    pub node: CommandNodeId,
+    /// The literal or the name of the argument
+    pub name: &'a str,
+    pub kind: CommandNodeType,
+    pub description: Option<&'a str>,
+    /// The type of an argument node with its bounds, if the type describes itself
+    pub argument_type: Option<ArgumentDescriptor>,
+    pub range: StringRange,
+    /// The input the node was parsed from
+    pub text: &'a str,
+    /// The parsed value of an argument node, if the type can render it
+    pub value: Option<String>,
+}
+
+/// How a command was actually typed, as opposed to its canonical form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedForm<'i> {
+    /// The matched literals in the order they were typed.
+    pub literals: Vec<TypedLiteral<'i>>,
+    pub separators: SeparatorStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeparatorStyle {
+    /// Every argument is separated by exactly one separator.
+    Single,
+    /// At least two arguments are separated by more than one character.
+    Multiple,
+}
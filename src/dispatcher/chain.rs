@@ -0,0 +1,187 @@
+//! Several commands in one input, separated by the
+//! [chain separator](CommandDispatcher::set_chain_separator), e.g.
+//! `say one; say two`.
+
+use super::{CommandDispatcher, ParseResults, ARGUMENT_SEPARATOR};
+use crate::{
+    arguments::ArgumentType,
+    context::StringRange,
+    errors::CommandSyntaxError,
+    string_reader::{is_quoted_string_start, SYNTAX_ESCAPE},
+    CommandSource, StringReader,
+};
+
+/// The outcome of [`CommandDispatcher::execute_chain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchReport<'i> {
+    /// The executed commands in the order of the input.
+    pub segments: Vec<SegmentReport<'i>>,
+    /// The commands which weren't executed because an earlier one failed.
+    pub skipped: Vec<StringRange>,
+}
+
+/// The outcome of one command of a chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentReport<'i> {
+    /// The range of the command in the whole input, without surrounding spaces.
+    pub range: StringRange,
+    /// The cursors of errors are positions in the whole input as well.
+    pub result: Result<i32, CommandSyntaxError<'i>>,
+}
+
+impl<'i> BatchReport<'i> {
+    /// Whether every command was executed and succeeded.
+    pub fn is_success(&self) -> bool {
+        self.skipped.is_empty() && self.segments.iter().all(|segment| segment.result.is_ok())
+    }
+    /// The sum of the results of the successful commands.
+    pub fn total(&self) -> i32 {
+        self.segments
+            .iter()
+            .filter_map(|segment| segment.result.as_ref().ok())
+            .sum()
+    }
+    pub fn errors(&self) -> impl Iterator<Item = &CommandSyntaxError<'i>> {
+        self.segments
+            .iter()
+            .filter_map(|segment| segment.result.as_ref().err())
+    }
+}
+
+impl<S, A> CommandDispatcher<S, A>
+where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    /// Splits the input at the chain separators which are neither inside a quoted
+    /// string nor inside brackets. Returns the range of every command without
+    /// surrounding spaces, leaving out empty ones. Without a chain separator, the whole
+    /// input is one command.
+    pub fn split_chain(&self, input: &str) -> Vec<StringRange> {
+        self.chain_spans(input)
+            .into_iter()
+            .map(|span| trim_span(input, span))
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+    /// Parses and executes the commands of the input in order, each with a clone of the
+    /// source. If `stop_on_error` is set, the commands after the first failing one are
+    /// skipped.
+    pub fn execute_chain<'i>(
+        &self,
+        input: &'i str,
+        source: S,
+        stop_on_error: bool,
+    ) -> BatchReport<'i> {
+        let mut report = BatchReport {
+            segments: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for range in self.split_chain(input) {
+            if stop_on_error && !report.is_success() {
+                report.skipped.push(range);
+                continue;
+            }
+            let parse = self.parse_segment(input, range.clone(), source.clone());
+            let result = self.execute(parse);
+            report.segments.push(SegmentReport { range, result });
+        }
+        report
+    }
+    /// Executes a chain like [`execute_chain`](Self::execute_chain), awaiting async
+    /// commands.
+    pub async fn execute_chain_async<'i>(
+        &self,
+        input: &'i str,
+        source: S,
+        stop_on_error: bool,
+    ) -> BatchReport<'i> {
+        let mut report = BatchReport {
+            segments: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for range in self.split_chain(input) {
+            if stop_on_error && !report.is_success() {
+                report.skipped.push(range);
+                continue;
+            }
+            let parse = self.parse_segment(input, range.clone(), source.clone());
+            let result = self.execute_async(parse).await;
+            report.segments.push(SegmentReport { range, result });
+        }
+        report
+    }
+    /// Parses the command of the chain the cursor is in, up to the cursor, e.g. to list
+    /// suggestions for it. The ranges of the results are positions in the whole input.
+    pub fn parse_chain_at<'i>(
+        &self,
+        input: &'i str,
+        cursor: usize,
+        source: S,
+    ) -> ParseResults<'i, S, A> {
+        let span = self
+            .chain_spans(input)
+            .into_iter()
+            .find(|span| span.end >= cursor)
+            .unwrap_or(cursor..cursor);
+        let start = trim_span(input, span).start.min(cursor);
+        self.parse_segment(input, start..cursor, source)
+    }
+    /// Parses the range of the input, so that cursors and ranges stay positions in the
+    /// whole input.
+    fn parse_segment<'i>(
+        &self,
+        input: &'i str,
+        range: StringRange,
+        source: S,
+    ) -> ParseResults<'i, S, A> {
+        let mut reader = StringReader::new(&input[..range.end]);
+        reader.set_cursor(range.start);
+        self.parse_reader(reader, source)
+    }
+    /// The ranges between the top level chain separators, including spaces.
+    fn chain_spans(&self, input: &str) -> Vec<StringRange> {
+        let Some(separator) = self.chain_separator.as_deref() else {
+            return std::iter::once(0..input.len()).collect();
+        };
+        let mut spans = Vec::new();
+        let mut start = 0;
+        let mut brackets = 0usize;
+        let mut quote = None;
+        let mut escaped = false;
+        let mut index = 0;
+        while let Some(c) = input[index..].chars().next() {
+            if let Some(terminator) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == SYNTAX_ESCAPE {
+                    escaped = true;
+                } else if c == terminator {
+                    quote = None;
+                }
+            } else if brackets == 0 && input[index..].starts_with(separator) {
+                spans.push(start..index);
+                index += separator.len();
+                start = index;
+                continue;
+            } else if is_quoted_string_start(c) {
+                quote = Some(c);
+            } else if matches!(c, '(' | '[' | '{') {
+                brackets += 1;
+            } else if matches!(c, ')' | ']' | '}') {
+                brackets = brackets.saturating_sub(1);
+            }
+            index += c.len_utf8();
+        }
+        spans.push(start..input.len());
+        spans
+    }
+}
+
+/// Removes the argument separators around the span.
+fn trim_span(input: &str, span: StringRange) -> StringRange {
+    let text = &input[span.clone()];
+    let start = span.start + (text.len() - text.trim_start_matches(ARGUMENT_SEPARATOR).len());
+    let end = span.end - (text.len() - text.trim_end_matches(ARGUMENT_SEPARATOR).len());
+    start..end.max(start)
+}
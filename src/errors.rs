@@ -1,6 +1,9 @@
-use std::{rc::Rc, borrow::Cow};
+use std::borrow::Cow;
 
-use crate::context::StringReaderContext;
+use crate::{
+    context::{StringRange, StringReaderContext},
+    Shared,
+};
 
 pub static CONTEXT_AMOUNT: usize = 10;
 
@@ -27,13 +30,21 @@ impl<'i> CommandSyntaxError<'i> {
         self.error_type.to_string()
     }
     pub fn context(&self) -> Option<String> {
-        if let Some(StringReaderContext { input, cursor }) = self.context {
+        if let Some(StringReaderContext { input, cursor, .. }) = self.context {
             let mut result = String::new();
             if cursor > CONTEXT_AMOUNT {
                 result.push_str("...");
             }
-            result.push_str(&input[0.max(cursor - CONTEXT_AMOUNT)..cursor]);
-            result.push_str("<--[HERE]");
+            result.push_str(&input[cursor.saturating_sub(CONTEXT_AMOUNT)..cursor]);
+            // Tokens with a span are marked as a whole
+            match self.context.as_ref().and_then(StringReaderContext::span_text) {
+                Some(token) => {
+                    result.push_str("<<");
+                    result.push_str(token);
+                    result.push_str(">>");
+                }
+                None => result.push_str("<--[HERE]"),
+            }
             Some(result)
         } else {
             None
@@ -48,16 +59,15 @@ impl std::fmt::Display for CommandSyntaxError<'_> {
                 f,
                 "{message} at position {cursor}: {context}",
                 message = self.error_type,
-                cursor = self.context.unwrap().cursor,
-            )
-            .into(),
+                cursor = self.context.as_ref().unwrap().cursor,
+            ),
             None => write!(f, "{}", self.error_type),
         }
     }
 }
 impl std::error::Error for CommandSyntaxError<'_> {}
 
-/// https://github.com/Mojang/brigadier/blob/master/src/main/java/com/mojang/brigadier/exceptions/BuiltInExceptions.java
+/// <https://github.com/Mojang/brigadier/blob/master/src/main/java/com/mojang/brigadier/exceptions/BuiltInExceptions.java>
 #[derive(thiserror::Error, Debug, Clone, PartialEq)]
 pub enum CommandErrorType<'i> {
     #[error("Double must not be less than {min}, found {found}")]
@@ -80,8 +90,18 @@ pub enum CommandErrorType<'i> {
     #[error("Long must not be more than {max}, found {found}")]
     LongTooBig { found: i64, max: i64 },
 
+    #[error("Integer must not be less than {min}, found {found}")]
+    UIntegerTooSmall { found: u32, min: u32 },
+    #[error("Integer must not be more than {max}, found {found}")]
+    UIntegerTooBig { found: u32, max: u32 },
+
+    #[error("Long must not be less than {min}, found {found}")]
+    ULongTooSmall { found: u64, min: u64 },
+    #[error("Long must not be more than {max}, found {found}")]
+    ULongTooBig { found: u64, max: u64 },
+
     #[error("Expected literal {expected}")]
-    LiteralIncorrect { expected: Rc<str> },
+    LiteralIncorrect { expected: Shared<str> },
 
     #[error("Expected quote to start a string")]
     ReaderExpectedStartOfQuote,
@@ -109,8 +129,14 @@ pub enum CommandErrorType<'i> {
     ReaderInvalidFloat(&'i str),
     #[error("Expected float")]
     ReaderExpectedFloat,
+    #[error("Expected a number that isn't negative")]
+    ReaderExpectedNonNegative,
     #[error("Expected '{0}'")]
     ReaderExpectedSymbol(String),
+    #[error("Unclosed macro placeholder")]
+    ReaderExpectedEndOfMacro,
+    #[error("Invalid macro placeholder '{0}'")]
+    ReaderInvalidMacroName(&'i str),
 
     #[error("Unknown command")]
     DispatcherUnknownCommand,
@@ -120,4 +146,39 @@ pub enum CommandErrorType<'i> {
     DispatcherExpectedArgumentSeparator,
     #[error("Could not parse command: {0}")]
     DispatcherParseException(String),
+    #[error("The command dispatcher is shutting down")]
+    DispatcherShuttingDown,
+    #[error("The command can only be executed asynchronously")]
+    DispatcherAsyncCommand,
+    #[error("Commands must not consist of more than {max_depth} nodes")]
+    DispatcherMaxDepthExceeded { max_depth: usize },
+    #[error("No value bound to the macro variables {}", .names.join(", "))]
+    DispatcherMissingMacroBinding { names: Vec<String> },
+    #[error("Invalid value bound to the macro variable {name}: {message}")]
+    DispatcherInvalidMacroBinding { name: String, message: String },
+    #[error("{}", join_alternatives(.0))]
+    DispatcherMultiple(Vec<CommandSyntaxError<'i>>),
+    #[error("No argument named '{name}'")]
+    DispatcherNoSuchArgument { name: String },
+    #[error("The argument '{name}' is a {actual}, not a {expected}")]
+    DispatcherWrongArgumentType {
+        name: String,
+        expected: &'static str,
+        actual: &'static str,
+    },
+    #[error("Suggestion for {range:?} is outside of the argument at {allowed:?}")]
+    DispatcherSuggestionOutOfRange {
+        range: StringRange,
+        allowed: StringRange,
+    },
+}
+
+/// Lists the messages of errors at the same position, e.g.
+/// `Expected integer / Expected quoted string`.
+fn join_alternatives(errors: &[CommandSyntaxError<'_>]) -> String {
+    errors
+        .iter()
+        .map(|error| error.error_type.to_string())
+        .collect::<Vec<_>>()
+        .join(" / ")
 }
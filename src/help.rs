@@ -0,0 +1,235 @@
+//! The output of the vanilla `/help` command, built from smart usages.
+
+use std::fmt;
+
+use crate::{
+    arguments::ArgumentType,
+    dispatcher::{CommandDispatcher, ARGUMENT_SEPARATOR},
+    tree::{CommandNodeId, Tree},
+    CommandSource,
+};
+
+/// Renders `/help` and `/help <command>` for the commands a source can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HelpSystem {
+    page_size: usize,
+}
+
+/// A command in a [`HelpPage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpEntry {
+    pub node: CommandNodeId,
+    pub name: String,
+    /// The smart usage, see [`Tree::get_smart_usage`]. Aliases are written as
+    /// `alias -> command`.
+    pub usage: String,
+    /// The command the entry is an alias of.
+    pub alias_of: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A page of `/help`, see [`HelpSystem::render_page`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpPage {
+    pub entries: Vec<HelpEntry>,
+    /// The number of this page, starting at 1.
+    pub page: usize,
+    pub total_pages: usize,
+}
+
+/// The output of `/help <command>`, see [`HelpSystem::render_command`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandHelp {
+    pub node: CommandNodeId,
+    pub name: String,
+    /// Every executable usage of the command, e.g. `teleport <x> <y>`.
+    pub usages: Vec<String>,
+    /// The aliases of the command, ordered by name.
+    pub aliases: Vec<String>,
+    pub description: Option<String>,
+    /// An example input for every usage whose arguments provide examples.
+    pub examples: Vec<String>,
+}
+
+impl HelpSystem {
+    /// # Panics
+    /// The page size must not be zero.
+    pub fn new(page_size: usize) -> Self {
+        assert!(page_size > 0, "The page size must not be zero");
+        Self { page_size }
+    }
+    #[inline]
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+    /// Lists the commands the source can use on the given page, ordered by name. Pages
+    /// are counted from 1, pages after the last one are empty.
+    pub fn render_page<S, A>(
+        &self,
+        dispatcher: &CommandDispatcher<S, A>,
+        source: &S,
+        page: usize,
+    ) -> HelpPage
+    where
+        S: CommandSource,
+    {
+        let tree = dispatcher.tree();
+        let usages = tree.get_smart_usage(dispatcher.root(), source);
+        let total_pages = usages.len().div_ceil(self.page_size).max(1);
+        let entries = usages
+            .into_iter()
+            .skip(page.saturating_sub(1).saturating_mul(self.page_size))
+            .take(if page == 0 { 0 } else { self.page_size })
+            .map(|(node_id, usage)| {
+                let node = tree.get(node_id).unwrap();
+                HelpEntry {
+                    node: node_id,
+                    name: tree.name(node_id).unwrap().to_owned(),
+                    usage,
+                    alias_of: alias_target(tree, node_id)
+                        .map(|target| tree.name(target).unwrap().to_owned()),
+                    description: node.description().map(str::to_owned),
+                }
+            })
+            .collect();
+        HelpPage {
+            entries,
+            page,
+            total_pages,
+        }
+    }
+    /// Describes the command with the given name. Aliases are resolved to the command
+    /// they redirect to. Returns `None` if there is no such command or the source can't
+    /// use it.
+    pub fn render_command<S, A>(
+        &self,
+        dispatcher: &CommandDispatcher<S, A>,
+        source: &S,
+        name: &str,
+    ) -> Option<CommandHelp>
+    where
+        S: CommandSource,
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        let tree = dispatcher.tree();
+        let root = dispatcher.root();
+        let mut node_id = dispatcher.find_node(&[name])?;
+        if !tree.get(node_id)?.can_use(source) {
+            return None;
+        }
+        if let Some(target) = alias_target(tree, node_id) {
+            node_id = target;
+        }
+        let node = tree.get(node_id)?;
+        let name = tree.name(node_id)?.to_owned();
+        let usages = tree
+            .get_all_usage(node_id, source, true)
+            .into_iter()
+            .map(|usage| match usage.is_empty() {
+                true => name.clone(),
+                // Redirects without a prefix already start with the command
+                false if node.redirect.is_some() => usage,
+                false => format!("{name}{ARGUMENT_SEPARATOR}{usage}"),
+            })
+            .collect();
+        let mut aliases: Vec<String> = tree
+            .get(root)?
+            .literals()
+            .filter(|&alias_id| {
+                alias_target(tree, alias_id) == Some(node_id)
+                    && tree.get(alias_id).unwrap().can_use(source)
+            })
+            .map(|alias_id| tree.name(alias_id).unwrap().to_owned())
+            .collect();
+        aliases.sort();
+        let mut examples = Vec::new();
+        collect_examples(tree, node_id, source, &mut vec![node_id], &mut examples);
+        Some(CommandHelp {
+            node: node_id,
+            name,
+            usages,
+            aliases,
+            description: node.description().map(str::to_owned),
+            examples,
+        })
+    }
+}
+
+/// The target of the node if it is an alias.
+fn alias_target<S, A>(tree: &Tree<S, A>, node_id: CommandNodeId) -> Option<CommandNodeId>
+where
+    S: CommandSource,
+{
+    let node = tree.get(node_id)?;
+    tree.is_alias(node).then(|| node.redirect.unwrap())
+}
+
+/// Adds an example for every executable node below the last node of the path, using
+/// the first example of every node on the way. Redirects are not followed.
+fn collect_examples<S, A>(
+    tree: &Tree<S, A>,
+    node_id: CommandNodeId,
+    source: &S,
+    path: &mut Vec<CommandNodeId>,
+    examples: &mut Vec<String>,
+) where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    if tree.get(node_id).unwrap().command().is_some() {
+        let example: Option<Vec<String>> = path
+            .iter()
+            .map(|&id| tree.examples(id).into_iter().next())
+            .collect();
+        if let Some(example) = example {
+            examples.push(example.join(&ARGUMENT_SEPARATOR.to_string()));
+        }
+    }
+    for child_id in tree.sorted_children(node_id) {
+        if path.contains(&child_id) || !tree.get(child_id).unwrap().can_use(source) {
+            continue;
+        }
+        path.push(child_id);
+        collect_examples(tree, child_id, source, path, examples);
+        path.pop();
+    }
+}
+
+impl fmt::Display for HelpEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}", self.usage)
+    }
+}
+
+impl fmt::Display for HelpPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "--- Showing help page {} of {} ---",
+            self.page, self.total_pages
+        )?;
+        for entry in &self.entries {
+            write!(f, "\n{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CommandHelp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--- Help: /{} ---", self.name)?;
+        if let Some(description) = &self.description {
+            write!(f, "\n{description}")?;
+        }
+        for usage in &self.usages {
+            write!(f, "\n/{usage}")?;
+        }
+        if !self.aliases.is_empty() {
+            write!(f, "\nAliases: {}", self.aliases.join(", "))?;
+        }
+        for example in &self.examples {
+            write!(f, "\nExample: /{example}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+//! Recently used argument values, suggested ahead of the other suggestions.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+use crate::{
+    arguments::ArgumentType,
+    command::CommandBounds,
+    context::CommandContext,
+    errors::CommandSyntaxError,
+    suggestion::{ProviderState, Suggestion, SuggestionProvider, Suggestions, SuggestionsBuilder},
+    tree::CommandNodeId,
+    CommandSource, Shared,
+};
+
+/// Identifies whose history an entry belongs to, e.g. a hash of a player's id.
+pub type HistoryKey = u64;
+
+/// Returns the history key of a source, see [`SuggestionHistory::new`].
+#[cfg(feature = "sync")]
+pub type HistoryKeyFn<S> = Shared<dyn Fn(&S) -> HistoryKey + Send + Sync>;
+/// Returns the history key of a source, see [`SuggestionHistory::new`].
+#[cfg(not(feature = "sync"))]
+pub type HistoryKeyFn<S> = Shared<dyn Fn(&S) -> HistoryKey>;
+
+/// The tooltip of suggestions from the history.
+pub const RECENT_TOOLTIP: &str = "recent";
+
+/// The number of entries a history keeps at most, unless changed with
+/// [`SuggestionHistory::set_max_entries`].
+pub const DEFAULT_MAX_HISTORY_ENTRIES: usize = 10_000;
+
+/// The values of arguments in successfully executed commands, see
+/// [`CommandDispatcher::enable_history`](crate::dispatcher::CommandDispatcher::enable_history).
+///
+/// Values are recorded per history key of the source and argument node, where the
+/// least recently used values are evicted first. The history is shared by its clones,
+/// e.g. the ones in [`HistorySuggestions`].
+pub struct SuggestionHistory<S> {
+    key: HistoryKeyFn<S>,
+    entries: Shared<Mutex<HistoryEntries>>,
+}
+
+struct HistoryEntries {
+    capacity_per_key: usize,
+    max_entries: usize,
+    /// Increases with every recorded value
+    tick: u64,
+    /// The values with the tick they were last used at, most recent first
+    values: HashMap<(HistoryKey, CommandNodeId), VecDeque<(String, u64)>>,
+    /// The list of every value by the tick it was last used at
+    by_age: BTreeMap<u64, (HistoryKey, CommandNodeId)>,
+}
+
+impl<S> Clone for SuggestionHistory<S> {
+    fn clone(&self) -> Self {
+        Self {
+            key: Shared::clone(&self.key),
+            entries: Shared::clone(&self.entries),
+        }
+    }
+}
+
+impl<S> SuggestionHistory<S>
+where
+    S: CommandSource,
+{
+    /// Creates a history keeping up to `capacity_per_key` values per history key and
+    /// argument node. `key` returns the history key of a source and may capture state.
+    pub fn new(
+        capacity_per_key: usize,
+        key: impl Fn(&S) -> HistoryKey + CommandBounds + 'static,
+    ) -> Self {
+        Self {
+            key: Shared::new(key),
+            entries: Shared::new(Mutex::new(HistoryEntries {
+                capacity_per_key,
+                max_entries: DEFAULT_MAX_HISTORY_ENTRIES,
+                tick: 0,
+                values: HashMap::new(),
+                by_age: BTreeMap::new(),
+            })),
+        }
+    }
+    #[inline]
+    pub fn key(&self, source: &S) -> HistoryKey {
+        (self.key)(source)
+    }
+    pub fn capacity_per_key(&self) -> usize {
+        self.entries.lock().unwrap().capacity_per_key
+    }
+    pub fn max_entries(&self) -> usize {
+        self.entries.lock().unwrap().max_entries
+    }
+    /// Limits the number of values of all keys together. The least recently used
+    /// values are evicted if there are more.
+    pub fn set_max_entries(&self, max_entries: usize) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.max_entries = max_entries;
+        entries.evict();
+    }
+    /// The number of values of all keys together.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().by_age.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.values.clear();
+        entries.by_age.clear();
+    }
+    /// Records a value as the most recently used one of the key and node.
+    pub fn record(&self, key: HistoryKey, node: CommandNodeId, value: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.capacity_per_key == 0 {
+            return;
+        }
+        entries.tick += 1;
+        let tick = entries.tick;
+        let HistoryEntries {
+            capacity_per_key,
+            values,
+            by_age,
+            ..
+        } = &mut *entries;
+        let list = values.entry((key, node)).or_default();
+        if let Some(index) = list.iter().position(|(recorded, _)| recorded == value) {
+            let (_, used) = list.remove(index).unwrap();
+            by_age.remove(&used);
+        }
+        list.push_front((value.to_owned(), tick));
+        by_age.insert(tick, (key, node));
+        if list.len() > *capacity_per_key {
+            let (_, used) = list.pop_back().unwrap();
+            by_age.remove(&used);
+        }
+        entries.evict();
+    }
+    /// The values of the key and node, most recently used first.
+    pub fn recent(&self, key: HistoryKey, node: CommandNodeId) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .values
+            .get(&(key, node))
+            .map(|list| list.iter().map(|(value, _)| value.clone()).collect())
+            .unwrap_or_default()
+    }
+    /// Wraps a provider, so that the values in the history which start with the
+    /// remaining input are suggested before its suggestions.
+    pub fn with_history<P>(&self, inner: P) -> HistorySuggestions<S, P> {
+        HistorySuggestions {
+            history: self.clone(),
+            inner,
+        }
+    }
+}
+
+impl HistoryEntries {
+    /// Evicts the least recently used values until at most `max_entries` are left.
+    fn evict(&mut self) {
+        while self.by_age.len() > self.max_entries {
+            let (_, list_key) = self.by_age.pop_first().unwrap();
+            let list = self.values.get_mut(&list_key).unwrap();
+            // The oldest value of all is the oldest one of its list as well
+            list.pop_back();
+            if list.is_empty() {
+                self.values.remove(&list_key);
+            }
+        }
+    }
+}
+
+/// A provider suggesting recently used values before the suggestions of another
+/// provider, see [`SuggestionHistory::with_history`].
+///
+/// The values of the history come first, most recently used first and with the
+/// [`RECENT_TOOLTIP`]. Suggestions of the inner provider with the same text are left
+/// out.
+pub struct HistorySuggestions<S, P> {
+    history: SuggestionHistory<S>,
+    inner: P,
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A, P> SuggestionProvider<S, A> for HistorySuggestions<S, P>
+where
+    S: CommandSource,
+    P: SuggestionProvider<S, A>,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        self.get_suggestions_with_state(None, context, builder)
+            .await
+    }
+    fn build_state(&self) -> Option<ProviderState> {
+        self.inner.build_state()
+    }
+    async fn get_suggestions_with_state<'i, 't, 'm>(
+        &self,
+        state: Option<&ProviderState>,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let input = builder.input();
+        let range = builder.start()..input.len();
+        let limit = builder.limit();
+        let recent: Vec<Suggestion<'t, 'm>> = match builder.node() {
+            Some(node) => self
+                .history
+                .recent(self.history.key(context.source()), node)
+                .into_iter()
+                .filter(|value| {
+                    value != builder.remaining()
+                        && value
+                            .to_lowercase()
+                            .starts_with(builder.remaining_lower_case())
+                })
+                .map(|value| {
+                    Suggestion::new_text_with_tooltip(
+                        range.clone(),
+                        value,
+                        Cow::Borrowed(RECENT_TOOLTIP),
+                    )
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let suggestions = self
+            .inner
+            .get_suggestions_with_state(state, context, builder)
+            .await?;
+        if recent.is_empty() {
+            return Ok(suggestions);
+        }
+        let range = if suggestions.is_empty() {
+            range
+        } else {
+            let other = suggestions.range();
+            range.start.min(other.start)..range.end.max(other.end)
+        };
+        let transaction_id = suggestions.transaction_id();
+        let truncated = suggestions.truncated();
+        let mut texts = HashSet::new();
+        let merged = recent
+            .into_iter()
+            .chain(suggestions)
+            .map(|suggestion| suggestion.expand_owned(input, range.clone()))
+            .filter(|suggestion| texts.insert(suggestion.text().to_owned()))
+            .collect();
+        let mut merged = Suggestions::new(range, merged)
+            .with_transaction_id(transaction_id)
+            .with_truncated(truncated);
+        if let Some(limit) = limit {
+            // Recent values stay first
+            merged.truncate(limit);
+        }
+        Ok(merged)
+    }
+}
@@ -1,18 +1,107 @@
+//! Parsing, suggestion and execution of commands, ported from Mojang's
+//! [Brigadier](https://github.com/Mojang/brigadier).
+//!
+//! # Lifetimes
+//!
+//! Parsed values, errors and suggestions borrow text instead of copying it. The lifetime
+//! parameters have the same names throughout the crate:
+//!
+//! - `'i`: the input. The [`StringReader`], parsed values
+//!   ([`ArgumentType::Output`](arguments::ArgumentType::Output)), contexts and
+//!   [`CommandSyntaxError`](errors::CommandSyntaxError)s borrow it.
+//! - `'t`: the texts of suggestions. They may borrow the input or anything living
+//!   longer, e.g. a `'static` registry of names. Builders take `'i` and `'t` separately,
+//!   so that suggestions can outlive the input they were listed for.
+//! - `'m`: the tooltips of suggestions, independent of their texts.
+//! - `'c`: a call of a closure used as a
+//!   [`SuggestionProvider`](suggestion::SuggestionProvider). Closures can't be generic
+//!   over `'t` and `'m`, so their suggestions borrow the call and are converted into
+//!   owned ones, see [`SuggestionFuture`](suggestion::SuggestionFuture).
+//!
+//! [`Suggestions`](suggestion::Suggestions) and
+//! [`SuggestionsBuilder`](suggestion::SuggestionsBuilder) are covariant in all of their
+//! lifetimes, e.g. `Suggestions<'static, 'static>` can be returned where shorter ones are
+//! expected. `examples/zero_copy.rs` uses all of them in one function.
+
+pub mod ambiguity;
 pub mod arguments;
+pub mod builder;
 pub mod command;
 pub mod context;
+pub mod dispatcher;
 pub mod errors;
+#[cfg(feature = "help")]
+pub mod help;
+#[cfg(feature = "history")]
+pub mod history;
+pub mod metrics;
+#[cfg(feature = "palette")]
+pub mod palette;
+pub mod requirement;
+pub mod shutdown;
 mod string_reader;
 pub mod suggestion;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod tree;
 
 pub use string_reader::*;
 
-macro_rules! async_fn_type {
-    (($($Arg:ty),*) -> $Out:ty) => {
-        fn($($Arg),*) -> Pin<Box<dyn Future<Output = $Out>>>
-    };
+use std::borrow::Cow;
+
+/// The reference counted pointer for data shared by the tree and contexts. With the
+/// `sync` feature it is [`Arc`](std::sync::Arc), so that trees can be moved to other
+/// threads, otherwise the cheaper [`Rc`](std::rc::Rc).
+#[cfg(feature = "sync")]
+pub type Shared<T> = std::sync::Arc<T>;
+#[cfg(not(feature = "sync"))]
+pub type Shared<T> = std::rc::Rc<T>;
+
+/// The bounds of argument types, suggestion providers and the values they hold across
+/// `.await`, which must be sendable across threads with the `send-futures` feature.
+#[cfg(feature = "send-futures")]
+pub trait FutureBounds: Send + Sync {}
+#[cfg(feature = "send-futures")]
+impl<T> FutureBounds for T where T: Send + Sync + ?Sized {}
+/// The bounds of argument types, suggestion providers and the values they hold across
+/// `.await`, which must be sendable across threads with the `send-futures` feature.
+#[cfg(not(feature = "send-futures"))]
+pub trait FutureBounds {}
+#[cfg(not(feature = "send-futures"))]
+impl<T> FutureBounds for T where T: ?Sized {}
+
+pub trait CommandSource: Clone + Sync + FutureBounds {}
+
+/// A message displayed to the user, like the tooltip of a suggestion. Frontends with
+/// rich text (e.g. chat components) can implement this for their own types.
+pub trait Message {
+    /// Renders the message without any formatting.
+    fn to_plain_string(&self) -> String;
+}
+
+impl Message for str {
+    fn to_plain_string(&self) -> String {
+        self.to_owned()
+    }
+}
+
+impl Message for String {
+    fn to_plain_string(&self) -> String {
+        self.clone()
+    }
 }
-pub(crate) use async_fn_type;
 
-pub trait CommandSource: Clone + Sync {}
+impl Message for Cow<'_, str> {
+    fn to_plain_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T> Message for &T
+where
+    T: Message + ?Sized,
+{
+    fn to_plain_string(&self) -> String {
+        (**self).to_plain_string()
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use crate::{
+    dispatcher::TypedForm,
+    tree::{CommandNodeId, LiteralMatchMode},
+};
+
+/// Counts how often each literal node was matched in each [`LiteralMatchMode`].
+#[derive(Debug, Clone, Default)]
+pub struct LiteralMatchCounters {
+    counters: HashMap<(CommandNodeId, LiteralMatchMode), u64>,
+}
+
+impl LiteralMatchCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Counts every literal of the typed form.
+    pub fn record(&mut self, typed_form: &TypedForm) {
+        for literal in &typed_form.literals {
+            *self.counters.entry((literal.node, literal.mode)).or_default() += 1;
+        }
+    }
+    pub fn count(&self, node: CommandNodeId, mode: LiteralMatchMode) -> u64 {
+        self.counters.get(&(node, mode)).copied().unwrap_or(0)
+    }
+    /// Iterates over all non-zero counters.
+    pub fn iter(&self) -> impl Iterator<Item = (CommandNodeId, LiteralMatchMode, u64)> + '_ {
+        self.counters
+            .iter()
+            .map(|(&(node, mode), &count)| (node, mode, count))
+    }
+}
@@ -0,0 +1,199 @@
+//! A flat, searchable index of executable commands for command palettes.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use crate::{
+    dispatcher::CommandDispatcher,
+    tree::{CommandNodeId, Tree},
+    CommandSource,
+};
+
+/// An executable command in a [`PaletteIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub node: CommandNodeId,
+    /// The usage of the nodes from the root to the command, e.g. `gamemode <mode>`.
+    pub usage: String,
+    /// The names of the nodes from the root to the command.
+    pub path: Vec<String>,
+    /// The keywords of the command and its ancestors, starting with its own.
+    pub keywords: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// The executable commands a source can use, ordered by their usage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaletteIndex {
+    entries: Vec<PaletteEntry>,
+}
+
+impl PaletteIndex {
+    pub(crate) fn build<S, A>(tree: &Tree<S, A>, root: CommandNodeId, source: &S) -> Self
+    where
+        S: CommandSource,
+    {
+        let mut entries = Vec::new();
+        let mut visited = HashSet::from([root]);
+        let mut stack: Vec<_> = tree
+            .sorted_children(root)
+            .into_iter()
+            .rev()
+            .map(|child_id| (child_id, Vec::new()))
+            .collect();
+        while let Some((node_id, mut path)) = stack.pop() {
+            let node = tree.get(node_id).unwrap();
+            if !node.can_use(source) || !visited.insert(node_id) {
+                continue;
+            }
+            path.push(node_id);
+            if node.command().is_some() {
+                entries.push(Self::entry(tree, &path));
+            }
+            for child_id in tree.sorted_children(node_id).into_iter().rev() {
+                stack.push((child_id, path.clone()));
+            }
+        }
+        entries.sort_by(|a, b| a.usage.cmp(&b.usage));
+        Self { entries }
+    }
+    fn entry<S, A>(tree: &Tree<S, A>, path: &[CommandNodeId]) -> PaletteEntry
+    where
+        S: CommandSource,
+    {
+        let node_id = *path.last().unwrap();
+        let mut usage = String::new();
+        for (i, &id) in path.iter().enumerate() {
+            if i > 0 {
+                usage.push(' ');
+            }
+            tree.write_usage_text(id, &mut usage);
+        }
+        let mut keywords: Vec<String> = Vec::new();
+        for &id in path.iter().rev() {
+            for keyword in tree.get(id).unwrap().keywords() {
+                if !keywords.iter().any(|k| **k == **keyword) {
+                    keywords.push(keyword.to_string());
+                }
+            }
+        }
+        PaletteEntry {
+            node: node_id,
+            usage,
+            path: path
+                .iter()
+                .map(|&id| tree.name(id).unwrap().to_owned())
+                .collect(),
+            keywords,
+            description: tree
+                .get(node_id)
+                .unwrap()
+                .description()
+                .map(str::to_owned),
+        }
+    }
+    #[inline]
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+    /// Finds the entries matching the query, best first. At most `limit` entries are
+    /// returned.
+    ///
+    /// The query matches a text if its characters, ignoring case and whitespace, appear
+    /// in the text in the same order. Each entry is matched against its path and each of
+    /// its keywords, the best of these scores is the score of the entry. Characters are
+    /// matched as early as possible and score:
+    /// - 1 point for every matched character,
+    /// - 3 more points if it starts a word, i.e. follows no letter or digit,
+    /// - 2 more points if it directly follows the previously matched character.
+    ///
+    /// Entries with equal scores keep the order of the index.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(u32, &PaletteEntry)> {
+        let query: Vec<char> = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+        let mut results: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path.join(" ");
+                std::iter::once(path.as_str())
+                    .chain(entry.keywords.iter().map(String::as_str))
+                    .filter_map(|text| fuzzy_score(&query, text))
+                    .max()
+                    .map(|score| (score, entry))
+            })
+            .collect();
+        // Stable, so ties keep the order of the index
+        results.sort_by_key(|&(score, _)| Reverse(score));
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Scores a subsequence match of the lowercase query in the text, see
+/// [`PaletteIndex::search`].
+fn fuzzy_score(query: &[char], text: &str) -> Option<u32> {
+    let mut score = 0;
+    let mut remaining = query.iter().peekable();
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(&&wanted) = remaining.peek() else {
+            break;
+        };
+        let matched = c == wanted;
+        if matched {
+            remaining.next();
+            score += 1;
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            if previous_matched {
+                score += 2;
+            }
+        }
+        previous = Some(c);
+        previous_matched = matched;
+    }
+    remaining.peek().is_none().then_some(score)
+}
+
+/// Caches palette indices per permission fingerprint, until the tree changes.
+///
+/// The fingerprint identifies everything the requirements of the tree depend on, e.g.
+/// a permission level, so that sources with the same fingerprint share an index.
+#[derive(Debug, Clone, Default)]
+pub struct PaletteCache {
+    generation: u64,
+    indices: HashMap<u64, PaletteIndex>,
+}
+
+impl PaletteCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the cached index for the fingerprint or builds it for the source.
+    pub fn get_or_build<S, A>(
+        &mut self,
+        dispatcher: &CommandDispatcher<S, A>,
+        source: &S,
+        fingerprint: u64,
+    ) -> &PaletteIndex
+    where
+        S: CommandSource,
+    {
+        let generation = dispatcher.tree().generation();
+        if self.generation != generation {
+            self.generation = generation;
+            self.indices.clear();
+        }
+        self.indices
+            .entry(fingerprint)
+            .or_insert_with(|| dispatcher.palette_index(source))
+    }
+}
@@ -0,0 +1,95 @@
+//! Requirements restrict nodes to some sources, e.g. to operators. Sources which don't
+//! meet the requirement of a node can neither parse nor see it.
+//!
+//! Closures are requirements and can be combined:
+//!
+//! ```
+//! # use brigadier::requirement::CommandRequirement;
+//! fn permission(name: &'static str) -> impl CommandRequirement<Vec<&'static str>> {
+//!     move |permissions: &Vec<&'static str>| permissions.contains(&name)
+//! }
+//! let moderator = permission("admin").or(permission("mod"));
+//! assert!(moderator.test(&vec!["mod"]));
+//! assert!(!moderator.test(&vec!["user"]));
+//! ```
+
+use crate::Shared;
+
+/// The bounds of requirements, which must be sendable across threads with the `sync`
+/// feature.
+#[cfg(feature = "sync")]
+pub trait RequirementBounds: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T> RequirementBounds for T where T: Send + Sync {}
+/// The bounds of requirements, which must be sendable across threads with the `sync`
+/// feature.
+#[cfg(not(feature = "sync"))]
+pub trait RequirementBounds {}
+#[cfg(not(feature = "sync"))]
+impl<T> RequirementBounds for T {}
+
+/// Decides whether a source may use a node, see
+/// [`requires`](crate::builder::ArgumentBuilder::requires).
+pub trait CommandRequirement<S>: RequirementBounds {
+    fn test(&self, source: &S) -> bool;
+
+    /// Requires both requirements to be met.
+    fn and<R>(self, other: R) -> And<Self, R>
+    where
+        Self: Sized,
+        R: CommandRequirement<S>,
+    {
+        And(self, other)
+    }
+    /// Requires either requirement to be met.
+    fn or<R>(self, other: R) -> Or<Self, R>
+    where
+        Self: Sized,
+        R: CommandRequirement<S>,
+    {
+        Or(self, other)
+    }
+}
+
+impl<S, F> CommandRequirement<S> for F
+where
+    F: Fn(&S) -> bool + RequirementBounds,
+{
+    #[inline]
+    fn test(&self, source: &S) -> bool {
+        self(source)
+    }
+}
+
+/// Met if both requirements are met, see [`CommandRequirement::and`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<S, A, B> CommandRequirement<S> for And<A, B>
+where
+    A: CommandRequirement<S>,
+    B: CommandRequirement<S>,
+{
+    #[inline]
+    fn test(&self, source: &S) -> bool {
+        self.0.test(source) && self.1.test(source)
+    }
+}
+
+/// Met if either requirement is met, see [`CommandRequirement::or`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<S, A, B> CommandRequirement<S> for Or<A, B>
+where
+    A: CommandRequirement<S>,
+    B: CommandRequirement<S>,
+{
+    #[inline]
+    fn test(&self, source: &S) -> bool {
+        self.0.test(source) || self.1.test(source)
+    }
+}
+
+/// A requirement stored in the tree.
+pub type SharedRequirement<S> = Shared<dyn CommandRequirement<S>>;
@@ -0,0 +1,105 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// Stops a [`CommandDispatcher`](crate::dispatcher::CommandDispatcher) from accepting
+/// new commands and observes the executions which are still running.
+///
+/// Controllers of the same dispatcher share their state and can be sent to other
+/// threads, e.g. to the task which unloads a plugin.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownController {
+    state: Arc<ShutdownState>,
+}
+
+#[derive(Debug, Default)]
+struct ShutdownState {
+    shutting_down: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Tasks waiting for `in_flight` to become zero.
+    idle_wakers: Mutex<Vec<Waker>>,
+}
+
+impl ShutdownController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Rejects all following parse and execute calls with
+    /// [`DispatcherShuttingDown`](crate::errors::CommandErrorType::DispatcherShuttingDown).
+    /// Executions which already started are not affected.
+    pub fn begin_shutdown(&self) {
+        self.state.shutting_down.store(true, Ordering::SeqCst);
+    }
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.state.shutting_down.load(Ordering::SeqCst)
+    }
+    /// The number of commands which are currently executing.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.state.in_flight.load(Ordering::SeqCst)
+    }
+    /// Counts an execution as in flight until the returned guard is dropped. The
+    /// dispatcher does this for its own executions, frontends which run commands
+    /// elsewhere can use it to be awaited by [`wait_idle`](Self::wait_idle) as well.
+    pub fn track(&self) -> InFlightGuard {
+        self.state.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard {
+            state: Arc::clone(&self.state),
+        }
+    }
+    /// Resolves once no execution is in flight. Works with any executor.
+    pub fn wait_idle(&self) -> WaitIdle {
+        WaitIdle {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+/// Keeps an execution counted as in flight, see [`ShutdownController::track`].
+#[derive(Debug)]
+#[must_use = "the execution is only counted while the guard is alive"]
+pub struct InFlightGuard {
+    state: Arc<ShutdownState>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.state.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let wakers = std::mem::take(&mut *self.state.idle_wakers.lock().unwrap());
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// Future returned by [`ShutdownController::wait_idle`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitIdle {
+    state: Arc<ShutdownState>,
+}
+
+impl Future for WaitIdle {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.in_flight.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.state.idle_wakers.lock().unwrap();
+        // Checked again while holding the lock, a guard may have been dropped since
+        if self.state.in_flight.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
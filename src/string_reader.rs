@@ -1,10 +1,15 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    ops::{Deref, DerefMut},
+};
 
 use nom::bytes::complete::take_while;
 
 use crate::{errors::{CommandErrorType, CommandSyntaxError}, context::StringReaderContext};
 
-const SYNTAX_ESCAPE: char = '\\';
+pub(crate) const SYNTAX_ESCAPE: char = '\\';
+const MACRO_PLACEHOLDER_OPEN: &str = "$(";
+const MACRO_PLACEHOLDER_CLOSE: char = ')';
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct StringReader<'i> {
@@ -27,7 +32,38 @@ macro_rules! impl_read_number {
                 }
                 Err(_) => Err(CommandSyntaxError::with_context(
                     CommandErrorType::$err_enum(number),
+                    self.token_context(remaining),
+                )),
+            }
+        }
+    };
+}
+
+/// Like `impl_read_number`, but rejects a leading minus sign with
+/// [`CommandErrorType::ReaderExpectedNonNegative`].
+macro_rules! impl_read_unsigned_number {
+    ($fnname:ident, $num:ty, $err_expected:ident, $err_invalid:ident) => {
+        pub fn $fnname(&mut self) -> Result<$num, CommandSyntaxError<'i>> {
+            if self.peek() == Some('-') {
+                return Err(CommandSyntaxError::with_context(
+                    CommandErrorType::ReaderExpectedNonNegative,
                     self.context(),
+                ));
+            }
+            let (remaining, number) =
+                take_while::<_, _, ()>(is_allowed_number)(self.remaining).unwrap();
+            if number.is_empty() {
+                return Err(CommandSyntaxError::new(CommandErrorType::$err_expected));
+            }
+            // Fails on overflow as well
+            match number.parse() {
+                Ok(number) => {
+                    self.remaining = remaining;
+                    Ok(number)
+                }
+                Err(_) => Err(CommandSyntaxError::with_context(
+                    CommandErrorType::$err_invalid(number),
+                    self.token_context(remaining),
                 )),
             }
         }
@@ -62,43 +98,85 @@ impl<'i> StringReader<'i> {
         self.remaining = &self.input[cursor..];
     }
 
+    #[inline]
+    pub fn can_read(&self) -> bool {
+        !self.remaining.is_empty()
+    }
+
+    /// Returns whether at least `length` more bytes can be read.
+    #[inline]
+    pub fn can_read_length(&self, length: usize) -> bool {
+        self.remaining.len() >= length
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<char> {
+        self.remaining.chars().next()
+    }
+
     pub fn context(&self) -> StringReaderContext<'i> {
+        StringReaderContext::new(self.input, self.cursor())
+    }
+
+    /// Returns a context at `start` spanning the input up to the cursor, e.g. the token
+    /// that failed to parse.
+    pub fn context_span(&self, start: usize) -> StringReaderContext<'i> {
+        StringReaderContext {
+            input: self.input,
+            cursor: start,
+            span: Some(start..self.cursor()),
+        }
+    }
+
+    /// Returns a context spanning the token which ends where `remaining` begins.
+    fn token_context(&self, remaining: &'i str) -> StringReaderContext<'i> {
+        let end = self.input.len() - remaining.len();
         StringReaderContext {
             input: self.input,
-            cursor: self.input.len() - self.remaining.len(),
+            cursor: self.cursor(),
+            span: Some(self.cursor()..end),
         }
     }
 
+    /// Skips the next character, which may be longer than a byte.
     #[inline]
     pub fn skip(&mut self) {
-        self.remaining = &self.remaining[1..];
+        let mut chars = self.remaining.chars();
+        chars.next();
+        self.remaining = chars.as_str();
     }
 
+    /// # Safety
+    /// The remaining input must not be empty and must start with a single-byte character.
     #[inline]
     pub unsafe fn skip_unchecked(&mut self) {
-        self.remaining = &self.remaining.get_unchecked(1..);
+        self.remaining = self.remaining.get_unchecked(1..);
     }
 
     impl_read_number!(read_int, i32, ReaderInvalidInt);
     impl_read_number!(read_long, i64, ReaderInvalidInt);
     impl_read_number!(read_float, f32, ReaderInvalidInt);
     impl_read_number!(read_double, f64, ReaderInvalidInt);
+    impl_read_unsigned_number!(read_uint, u32, ReaderExpectedInt, ReaderInvalidInt);
+    impl_read_unsigned_number!(read_ulong, u64, ReaderExpectedLong, ReaderInvalidLong);
 
     /// Reads a string (quoted or unquoted) with either the value `true` or `false` (case sensitive).
     pub fn read_boolean(&mut self) -> Result<bool, CommandSyntaxError<'i>> {
-        let start = self.remaining;
-        let value = self.read_string()?;
-        if value == Cow::Borrowed("true") {
-            return Ok(true);
-        }
-        if value == Cow::Borrowed("false") {
-            return Ok(false);
-        }
-        self.remaining = start;
-        Err(CommandSyntaxError::with_context(
-            CommandErrorType::ReaderInvalidBool(value),
-            self.context(),
-        ))
+        let mut transaction = self.transaction();
+        let value = transaction.read_string()?;
+        let value = if value == "true" {
+            true
+        } else if value == "false" {
+            false
+        } else {
+            // Dropping the transaction restores the cursor
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::ReaderInvalidBool(value),
+                transaction.context_span(transaction.start()),
+            ));
+        };
+        transaction.commit();
+        Ok(value)
     }
 
     /// Reads a simple, unquoted string without any escape sequences.
@@ -112,7 +190,7 @@ impl<'i> StringReader<'i> {
     /// Reads a string surrounded by single or double quotes. Supports escape esquences
     /// `\\` and `\"` or `\'` (depends on the starting quote).
     pub fn read_quoted_string(&mut self) -> Result<Cow<'i, str>, CommandSyntaxError<'i>> {
-        if self.remaining.len() == 0 {
+        if self.remaining.is_empty() {
             return Ok(Cow::Borrowed(""));
         }
         let quote = self.remaining.chars().next().unwrap();
@@ -131,7 +209,7 @@ impl<'i> StringReader<'i> {
 
     /// Reads a string that is either quoted or unquoted.
     pub fn read_string(&mut self) -> Result<Cow<'i, str>, CommandSyntaxError<'i>> {
-        if self.remaining.len() == 0 {
+        if self.remaining.is_empty() {
             return Ok(Cow::Borrowed(""));
         }
         let quote = self.remaining.chars().next().unwrap();
@@ -150,19 +228,18 @@ impl<'i> StringReader<'i> {
         &mut self,
         terminator: char,
     ) -> Result<Cow<'i, str>, CommandSyntaxError<'i>> {
-        // HACK loop as block because labels on blocks are unstable
-        'read: loop {
+        'read: {
             let len;
             let mut chars = self.remaining.char_indices();
-            'borrowed: loop {
+            'borrowed: {
                 // No need to allocate when nothing is escaped
-                while let Some((idx, c)) = chars.next() {
+                for (idx, c) in chars.by_ref() {
                     if c == SYNTAX_ESCAPE {
                         len = idx;
                         break 'borrowed;
                     } else if c == terminator {
                         let result = &self.remaining[..idx];
-                        self.remaining = &self.remaining[idx + 1..];
+                        self.remaining = &self.remaining[idx + c.len_utf8()..];
                         return Ok(Cow::Borrowed(result));
                     }
                 }
@@ -170,29 +247,30 @@ impl<'i> StringReader<'i> {
             }
             // Owned
             let mut result = String::from(&self.remaining[..len]);
-            let mut escaped = true;
-            while let Some((idx, c)) = chars.next() {
-                if escaped {
+            // The index of the escape character before the current one
+            let mut escaped = Some(len);
+            for (idx, c) in chars {
+                if let Some(escape_idx) = escaped {
                     if c == terminator || c == SYNTAX_ESCAPE {
                         result.push(c);
-                        escaped = false;
+                        escaped = None;
                     } else {
-                        self.remaining = &self.remaining[idx..];
+                        // The error points at the escape character
+                        self.remaining = &self.remaining[escape_idx..];
                         return Err(CommandSyntaxError::with_context(
                             CommandErrorType::ReaderInvalidEscape(c),
                             self.context(),
                         ));
                     }
                 } else if c == SYNTAX_ESCAPE {
-                    escaped = true;
+                    escaped = Some(idx);
                 } else if c == terminator {
-                    self.remaining = &self.remaining[idx + 1..];
+                    self.remaining = &self.remaining[idx + c.len_utf8()..];
                     return Ok(Cow::Owned(result));
                 } else {
                     result.push(c);
                 }
             }
-            break;
         }
         self.remaining = "";
         Err(CommandSyntaxError::with_context(
@@ -201,24 +279,127 @@ impl<'i> StringReader<'i> {
         ))
     }
 
+    /// Returns whether the remaining input starts with a macro placeholder like `$(name)`.
+    #[inline]
+    pub fn can_read_macro_placeholder(&self) -> bool {
+        self.remaining.starts_with(MACRO_PLACEHOLDER_OPEN)
+    }
+
+    /// Reads a macro placeholder like `$(name)` and returns its name. Names consist of
+    /// ASCII letters, digits and underscores.
+    pub fn read_macro_placeholder(&mut self) -> Result<&'i str, CommandSyntaxError<'i>> {
+        let Some(placeholder) = self.remaining.strip_prefix(MACRO_PLACEHOLDER_OPEN) else {
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::ReaderExpectedSymbol(MACRO_PLACEHOLDER_OPEN.into()),
+                self.context(),
+            ));
+        };
+        let start = self.remaining;
+        let (rest, name) =
+            take_while::<_, _, ()>(is_allowed_in_macro_name)(placeholder).unwrap();
+        let Some(rest) = rest.strip_prefix(MACRO_PLACEHOLDER_CLOSE) else {
+            self.remaining = rest;
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::ReaderExpectedEndOfMacro,
+                self.context(),
+            ));
+        };
+        if name.is_empty() {
+            return Err(CommandSyntaxError::with_context(
+                CommandErrorType::ReaderInvalidMacroName(&start[..start.len() - rest.len()]),
+                self.context(),
+            ));
+        }
+        self.remaining = rest;
+        Ok(name)
+    }
+
     pub fn skip_whitespace(&mut self) {
         let (remaining, _) = take_while::<_, _, ()>(is_java_space)(self.remaining).unwrap();
         self.remaining = remaining;
     }
+
+    /// Starts an attempt to parse something. Unless the attempt is
+    /// [committed](ReaderTransaction::commit), the cursor is restored when the
+    /// transaction is dropped, e.g. by returning early with `?`.
+    pub fn transaction(&mut self) -> ReaderTransaction<'_, 'i> {
+        ReaderTransaction {
+            start: self.cursor(),
+            reader: self,
+            committed: false,
+        }
+    }
+
+    /// Runs the parser in a [transaction](Self::transaction), which is committed if it
+    /// succeeds.
+    pub fn try_parse<T, E>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut transaction = self.transaction();
+        let result = parse(&mut transaction)?;
+        transaction.commit();
+        Ok(result)
+    }
+}
+
+/// Restores the cursor of a reader when dropped unless it was committed, see
+/// [`StringReader::transaction`].
+#[derive(Debug)]
+pub struct ReaderTransaction<'r, 'i> {
+    reader: &'r mut StringReader<'i>,
+    start: usize,
+    committed: bool,
+}
+
+impl ReaderTransaction<'_, '_> {
+    /// The cursor the reader is restored to.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Keeps the cursor where it is.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl<'i> Deref for ReaderTransaction<'_, 'i> {
+    type Target = StringReader<'i>;
+
+    fn deref(&self) -> &StringReader<'i> {
+        self.reader
+    }
+}
+
+impl DerefMut for ReaderTransaction<'_, '_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.reader
+    }
+}
+
+impl Drop for ReaderTransaction<'_, '_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.reader.set_cursor(self.start);
+        }
+    }
 }
 
 fn is_allowed_number(c: char) -> bool {
-    c >= '0' && c <= '9' || c == '.' || c == '-'
+    c.is_ascii_digit() || c == '.' || c == '-'
 }
 
 fn is_allowed_in_unquoted_string(c: char) -> bool {
-    match c {
-        '0'..='9' | 'A'..='Z' | 'a'..='z' | '_' | '-' | '.' | '+' => true,
-        _ => false,
-    }
+    matches!(c, '0'..='9' | 'A'..='Z' | 'a'..='z' | '_' | '-' | '.' | '+')
+}
+
+fn is_allowed_in_macro_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
 }
 
-fn is_quoted_string_start(c: char) -> bool {
+pub(crate) fn is_quoted_string_start(c: char) -> bool {
     c == '"' || c == '\''
 }
 
@@ -1,31 +1,362 @@
-use std::{borrow::Cow, collections::HashSet, future::Future, ops::Range, pin::Pin};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::HashSet,
+    future::Future,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::Range,
+    pin::Pin,
+};
 
 use crate::{
-    async_fn_type, context::CommandContext, context::StringRange, errors::CommandSyntaxError,
+    arguments::ArgumentType,
+    context::{CommandContext, StringRange},
+    errors::CommandSyntaxError,
+    tree::CommandNodeId,
+    CommandSource, FutureBounds, Shared,
 };
 
+/// A shared [`SuggestionProvider`], which can be sent across threads with the `sync`
+/// feature.
+#[cfg(feature = "sync")]
+pub type SharedSuggestionProvider<S, A> = Shared<dyn SuggestionProvider<S, A> + Send + Sync>;
+#[cfg(not(feature = "sync"))]
+pub type SharedSuggestionProvider<S, A> = Shared<dyn SuggestionProvider<S, A>>;
+
+/// How the suggestions of an argument's custom provider relate to those of its argument
+/// type, see
+/// [`suggestion_mode`](crate::builder::RequiredArgumentBuilder::suggestion_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SuggestionMode {
+    /// Only the provider suggests values, like in Brigadier.
+    #[default]
+    Replace,
+    /// The suggestions of the provider and the argument type are merged.
+    Merge,
+}
+
+/// What happens to suggestions of an argument whose range is not within the argument,
+/// i.e. between its start and the end of the input. Applying them would replace text
+/// of previous arguments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SuggestionRangeCheck {
+    /// Drops the suggestions.
+    #[default]
+    Lenient,
+    /// Fails with
+    /// [`DispatcherSuggestionOutOfRange`](crate::errors::CommandErrorType::DispatcherSuggestionOutOfRange),
+    /// to find misbehaving providers during development.
+    Strict,
+}
+
+/// Provides suggestions for an argument instead of its argument type.
+///
 /// `'t`: Lifetime of borrowed suggestions text\
 /// `'m`: Lifetime of borrowed tooltips
-pub type SuggestionProvider<'i, 't, 'm, S> = async_fn_type!((CommandContext<S>, SuggestionsBuilder<'i, 't, 'm>) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>);
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+pub trait SuggestionProvider<S, A>: FutureBounds
+where
+    S: CommandSource,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>;
+    /// Builds state which is reused by every request until the tree changes, see
+    /// [`StatefulSuggestionProvider`]. Called lazily by the tree or ahead of time by
+    /// [`warm_suggestions`](crate::dispatcher::CommandDispatcher::warm_suggestions).
+    fn build_state(&self) -> Option<ProviderState> {
+        None
+    }
+    /// Lists the suggestions with the state returned by
+    /// [`build_state`](Self::build_state).
+    async fn get_suggestions_with_state<'i, 't, 'm>(
+        &self,
+        _state: Option<&ProviderState>,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        self.get_suggestions(context, builder).await
+    }
+}
+
+/// State of a [`SuggestionProvider`], cached by the tree alongside the node.
+#[cfg(feature = "sync")]
+pub type ProviderState = Shared<dyn Any + Send + Sync>;
+#[cfg(not(feature = "sync"))]
+pub type ProviderState = Shared<dyn Any>;
+
+/// The bounds of [`StatefulSuggestionProvider::State`], which must be sendable across
+/// threads with the `sync` feature.
+#[cfg(feature = "sync")]
+pub trait ProviderStateData: Any + Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T> ProviderStateData for T where T: Any + Send + Sync {}
+/// The bounds of [`StatefulSuggestionProvider::State`], which must be sendable across
+/// threads with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub trait ProviderStateData: Any {}
+#[cfg(not(feature = "sync"))]
+impl<T> ProviderStateData for T where T: Any {}
+
+/// A provider which precomputes something expensive, like an index of thousands of
+/// names, once instead of on every keystroke. Wrap it in [`Stateful`] to use it as a
+/// [`SuggestionProvider`].
+///
+/// The state is built lazily by the first request and rebuilt after the tree was
+/// modified, see [`Tree::generation`](crate::tree::Tree::generation).
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+pub trait StatefulSuggestionProvider<S, A>: FutureBounds
+where
+    S: CommandSource,
+{
+    type State: ProviderStateData;
+
+    fn build_state(&self) -> Self::State;
+    async fn suggest_with_state<'i, 't, 'm>(
+        &self,
+        state: &Self::State,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>;
+}
+
+/// Uses a [`StatefulSuggestionProvider`] as a [`SuggestionProvider`]. Without a cached
+/// state, e.g. when called directly, the state is built for the request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stateful<P>(pub P);
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A, P> SuggestionProvider<S, A> for Stateful<P>
+where
+    S: CommandSource,
+    P: StatefulSuggestionProvider<S, A>,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let state = self.0.build_state();
+        self.0.suggest_with_state(&state, context, builder).await
+    }
+    fn build_state(&self) -> Option<ProviderState> {
+        Some(Shared::new(self.0.build_state()))
+    }
+    async fn get_suggestions_with_state<'i, 't, 'm>(
+        &self,
+        state: Option<&ProviderState>,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        match state.and_then(|state| state.downcast_ref::<P::State>()) {
+            Some(state) => self.0.suggest_with_state(state, context, builder).await,
+            None => self.get_suggestions(context, builder).await,
+        }
+    }
+}
+
+/// The future returned by closures used as a [`SuggestionProvider`].
+#[cfg(not(feature = "send-futures"))]
+pub type SuggestionFuture<'c, 'i> =
+    Pin<Box<dyn Future<Output = Result<Suggestions<'c, 'c>, CommandSyntaxError<'i>>> + 'c>>;
+/// The future returned by closures used as a [`SuggestionProvider`].
+#[cfg(feature = "send-futures")]
+pub type SuggestionFuture<'c, 'i> =
+    Pin<Box<dyn Future<Output = Result<Suggestions<'c, 'c>, CommandSyntaxError<'i>>> + Send + 'c>>;
+
+/// Functions returning a boxed future are providers as well. Their suggestions may only
+/// borrow from the call, so they are converted into owned ones.
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A, F> SuggestionProvider<S, A> for F
+where
+    S: CommandSource,
+    F: for<'c, 'i> Fn(
+            &'c CommandContext<'i, S, A>,
+            SuggestionsBuilder<'i, 'c, 'c>,
+        ) -> SuggestionFuture<'c, 'i>
+        + FutureBounds,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        Ok(self(context, builder).await?.into_owned())
+    }
+}
+
+/// Suggests the texts which start with the remaining input, ignoring case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StaticSuggestions(pub Vec<Cow<'static, str>>);
+
+impl StaticSuggestions {
+    pub fn new(texts: impl IntoIterator<Item = impl Into<Cow<'static, str>>>) -> Self {
+        Self(texts.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A> SuggestionProvider<S, A> for StaticSuggestions
+where
+    S: CommandSource,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        _context: &CommandContext<'i, S, A>,
+        mut builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        for text in &self.0 {
+            builder.suggest_text_matching(text.clone());
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Suggests the texts which start with the remaining input, ignoring case, like
+/// [`StaticSuggestions`]. Finds them with a binary search in a sorted index instead of
+/// checking every text, which pays off for long lists. Use it wrapped in [`Stateful`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SortedSliceProvider(pub Vec<String>);
+
+impl SortedSliceProvider {
+    pub fn new(texts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self(texts.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A> StatefulSuggestionProvider<S, A> for SortedSliceProvider
+where
+    S: CommandSource,
+{
+    /// The lowercase texts with their index, sorted.
+    type State = Vec<(String, usize)>;
+
+    fn build_state(&self) -> Self::State {
+        let mut index: Vec<(String, usize)> = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (text.to_lowercase(), i))
+            .collect();
+        index.sort_unstable();
+        index
+    }
+    async fn suggest_with_state<'i, 't, 'm>(
+        &self,
+        state: &Self::State,
+        _context: &CommandContext<'i, S, A>,
+        mut builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let prefix = builder.remaining_lower_case();
+        let start = state.partition_point(|(text, _)| text.as_str() < prefix);
+        let mut matches: Vec<usize> = state[start..]
+            .iter()
+            .take_while(|(text, _)| text.starts_with(prefix))
+            .map(|&(_, i)| i)
+            .collect();
+        // Keep the order of the texts, like the naive filter
+        matches.sort_unstable();
+        for i in matches {
+            builder.suggest_text(self.0[i].clone());
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Suggests nothing, e.g. to hide the suggestions of an argument type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoSuggestions;
 
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<S, A> SuggestionProvider<S, A> for NoSuggestions
+where
+    S: CommandSource,
+{
+    async fn get_suggestions<'i, 't, 'm>(
+        &self,
+        _context: &CommandContext<'i, S, A>,
+        _builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        Ok(Suggestions::empty())
+    }
+}
+
+/// `T`: Type of the tooltips, see [`Message`](crate::Message)
+///
+/// With the `serde` feature, suggestions can be serialized, e.g. to send them to a
+/// language server client. Deserialized suggestions always own their data.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Suggestions<'t, 'm> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestions<'t, 'm, T = Cow<'m, str>> {
     range: StringRange,
-    suggestions: Vec<Suggestion<'t, 'm>>,
+    suggestions: Vec<Suggestion<'t, 'm, T>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    transaction_id: Option<u64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    truncated: bool,
 }
 
 impl Suggestions<'static, 'static> {
     pub const EMPTY: Self = Suggestions::new(0..0, Vec::new());
 }
 
-impl<'t, 'm> Suggestions<'t, 'm> {
-    pub const fn new(range: StringRange, suggestions: Vec<Suggestion<'t, 'm>>) -> Self {
-        Self { range, suggestions }
+impl<'t, 'm, T> Suggestions<'t, 'm, T> {
+    pub const fn new(range: StringRange, suggestions: Vec<Suggestion<'t, 'm, T>>) -> Self {
+        Self {
+            range,
+            suggestions,
+            transaction_id: None,
+            truncated: false,
+        }
     }
-    /// Creates deduplicated suggestions expanded into the command.
-    pub fn create(command: &str, suggestions: Vec<Suggestion<'t, 'm>>) -> Self {
+    /// Same as [`Suggestions::EMPTY`], but for any tooltip type.
+    pub const fn empty() -> Self {
+        Self::new(0..0, Vec::new())
+    }
+    /// Creates deduplicated suggestions expanded into the command. Suggestions are
+    /// duplicates if they have the same text, only the first one is kept.
+    pub fn create(command: &str, suggestions: Vec<Suggestion<'t, 'm, T>>) -> Self {
         if suggestions.is_empty() {
-            return Suggestions::EMPTY;
+            return Self::empty();
         }
         let mut start = usize::MAX;
         let mut end = usize::MIN;
@@ -35,64 +366,305 @@ impl<'t, 'm> Suggestions<'t, 'm> {
         }
         let range = start..end;
         let mut texts = HashSet::with_capacity(suggestions.len());
+        let mut sorted = Vec::with_capacity(suggestions.len());
         for suggestion in suggestions {
-            texts.insert(suggestion.expand_owned(command, range.clone()));
+            let suggestion = suggestion.expand_owned(command, range.clone());
+            if texts.insert(suggestion.text.clone()) {
+                sorted.push(suggestion);
+            }
         }
-        let mut sorted: Vec<_> = texts.into_iter().collect();
-        sorted.sort_by(Suggestion::cmp_ignore_case);
+        sorted.sort_by(Suggestion::cmp_sorted);
         Self::new(range, sorted)
     }
+    /// Merges the suggestions for the same command, e.g. of several nodes, like
+    /// [`Suggestions::create`]. The result is truncated if any of them was.
+    pub fn merge(command: &str, suggestions: impl IntoIterator<Item = Self>) -> Self {
+        let mut truncated = false;
+        let mut transaction_id = None;
+        let mut merged = Vec::new();
+        for suggestions in suggestions {
+            truncated |= suggestions.truncated;
+            transaction_id = transaction_id.or(suggestions.transaction_id);
+            merged.extend(suggestions.suggestions);
+        }
+        Self::create(command, merged)
+            .with_transaction_id(transaction_id)
+            .with_truncated(truncated)
+    }
     pub fn is_empty(&self) -> bool {
         self.suggestions.is_empty()
     }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.suggestions.len()
+    }
+    #[inline]
+    pub fn range(&self) -> StringRange {
+        self.range.clone()
+    }
+    /// The id of the request these suggestions answer, see
+    /// [`SuggestionsBuilder::set_transaction_id`].
+    #[inline]
+    pub fn transaction_id(&self) -> Option<u64> {
+        self.transaction_id
+    }
+    pub fn with_transaction_id(mut self, transaction_id: Option<u64>) -> Self {
+        self.transaction_id = transaction_id;
+        self
+    }
+    /// Whether suggestions were dropped because of a limit, see
+    /// [`SuggestionsBuilder::with_limit`]. Frontends can show that more are available.
+    #[inline]
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+    pub fn with_truncated(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self
+    }
+    /// Keeps the first `limit` suggestions, marking the suggestions as truncated if any
+    /// were dropped. Suggestions are sorted, so these are the first ones in order.
+    pub fn truncate(&mut self, limit: usize) {
+        if self.suggestions.len() > limit {
+            self.suggestions.truncate(limit);
+            self.truncated = true;
+        }
+    }
+    /// Removes and returns the suggestions whose range is not within `span`. The range
+    /// of the suggestions shrinks to the remaining ones.
+    pub fn remove_outside(&mut self, span: StringRange) -> Vec<Suggestion<'t, 'm, T>> {
+        let (inside, outside) = std::mem::take(&mut self.suggestions).into_iter().partition(
+            |suggestion: &Suggestion<'t, 'm, T>| {
+                span.start <= suggestion.range.start && suggestion.range.end <= span.end
+            },
+        );
+        self.suggestions = inside;
+        if !outside.is_empty() {
+            self.range = self
+                .suggestions
+                .iter()
+                .map(|suggestion| suggestion.range.clone())
+                .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+                .unwrap_or(0..0);
+        }
+        outside
+    }
+    /// Moves the suggestions from the input they were computed for onto an edited
+    /// version of it, e.g. if the user kept typing while waiting for them. Edits after
+    /// the range of the suggestions (including appended text) keep it, edits before it
+    /// shift it. Returns `None` if the edit overlaps the range, the suggestions are
+    /// stale then.
+    pub fn rebase(&self, old_input: &str, new_input: &str) -> Option<Self>
+    where
+        T: Clone,
+    {
+        let edit = Edit::between(old_input, new_input);
+        Some(Self {
+            range: edit.rebase(self.range.clone())?,
+            suggestions: self
+                .suggestions
+                .iter()
+                .map(|suggestion| {
+                    let mut suggestion = suggestion.clone();
+                    suggestion.range = edit.rebase(suggestion.range)?;
+                    Some(suggestion)
+                })
+                .collect::<Option<_>>()?,
+            transaction_id: self.transaction_id,
+            truncated: self.truncated,
+        })
+    }
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Suggestion<'t, 'm, T>> {
+        self.suggestions.iter()
+    }
+    /// Iterates over the text of every suggestion.
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.suggestions.iter().map(Suggestion::text)
+    }
+    /// Returns the text to display as ghost text behind the cursor, if exactly one
+    /// suggestion extends the token typed so far. See [`Suggestion::completion_suffix`].
+    pub fn ghost_text(&self, input: &str) -> Option<(usize, String)> {
+        let mut extensions = self
+            .suggestions
+            .iter()
+            .filter_map(|suggestion| suggestion.completion_suffix(input));
+        match (extensions.next(), extensions.next()) {
+            (Some((offset, suffix)), None) if !suffix.is_empty() => {
+                Some((offset, suffix.to_owned()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Suggestions<'_, '_> {
+    /// Converts all borrowed texts and tooltips into owned data, so that the
+    /// suggestions can outlive the input they were created from.
+    pub fn into_owned(self) -> Suggestions<'static, 'static> {
+        Suggestions {
+            range: self.range,
+            suggestions: self
+                .suggestions
+                .into_iter()
+                .map(Suggestion::into_owned)
+                .collect(),
+            transaction_id: self.transaction_id,
+            truncated: self.truncated,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
-pub struct Suggestion<'t, 'm> {
+impl<'s, 't, 'm, T> IntoIterator for &'s Suggestions<'t, 'm, T> {
+    type Item = &'s Suggestion<'t, 'm, T>;
+    type IntoIter = std::slice::Iter<'s, Suggestion<'t, 'm, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'t, 'm, T> IntoIterator for Suggestions<'t, 'm, T> {
+    type Item = Suggestion<'t, 'm, T>;
+    type IntoIter = std::vec::IntoIter<Suggestion<'t, 'm, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.suggestions.into_iter()
+    }
+}
+
+/// Equality and hashing only consider the range, text and number, never the tooltip.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion<'t, 'm, T = Cow<'m, str>> {
     range: StringRange,
     text: Cow<'t, str>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     int: Option<i32>,
-    pub tooltip: Option<Cow<'m, str>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub tooltip: Option<T>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _tooltip_lifetime: PhantomData<&'m ()>,
+}
+impl<T> Default for Suggestion<'_, '_, T> {
+    fn default() -> Self {
+        Self {
+            range: 0..0,
+            text: Cow::Borrowed(""),
+            int: None,
+            tooltip: None,
+            _tooltip_lifetime: PhantomData,
+        }
+    }
+}
+impl<T> PartialEq for Suggestion<'_, '_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.range == other.range && self.text == other.text && self.int == other.int
+    }
+}
+impl<T> Eq for Suggestion<'_, '_, T> {}
+impl<T> Hash for Suggestion<'_, '_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.range.hash(state);
+        self.text.hash(state);
+        self.int.hash(state);
+    }
 }
-impl std::cmp::PartialOrd for Suggestion<'_, '_> {
+impl<T> std::cmp::PartialOrd for Suggestion<'_, '_, T> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
-impl std::cmp::Ord for Suggestion<'_, '_> {
+/// Numbers are ordered before texts, so that the order is total even if both are
+/// mixed. Numbers are compared by value and texts lexicographically. Ties are broken
+/// by the range, so that only equal suggestions compare as equal.
+impl<T> std::cmp::Ord for Suggestion<'_, '_, T> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (&self.int, &other.int) {
-            (Some(a), Some(b)) => a.cmp(b),
-            _ => self.text.cmp(&other.text),
-        }
+        let by_kind = match (self.int, other.int) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        };
+        by_kind
+            .then_with(|| self.text.cmp(&other.text))
+            .then_with(|| self.range.start.cmp(&other.range.start))
+            .then_with(|| self.range.end.cmp(&other.range.end))
     }
 }
 
-impl Suggestion<'_, '_> {
+impl<T> Suggestion<'_, '_, T> {
     pub fn range(&self) -> StringRange {
         self.range.clone()
     }
     pub fn int(&self) -> Option<i32> {
         self.int
     }
-    // TODO: Could be optimized
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// If the text of this suggestion starts with the token it replaces, returns the
+    /// byte offset at the end of that token and the remaining text to insert there.
+    pub fn completion_suffix(&self, input: &str) -> Option<(usize, &str)> {
+        let token = input.get(self.range.clone())?;
+        let suffix = self.text.strip_prefix(token)?;
+        Some((self.range.end, suffix))
+    }
+    /// Like [`Suggestion::completion_suffix`], but the token may differ in case from
+    /// the suggestion.
+    pub fn completion_suffix_ignore_case(
+        &self,
+        input: &str,
+    ) -> Option<CaseInsensitiveCompletion<'_>> {
+        let token = input.get(self.range.clone())?;
+        let prefix_len = prefix_len_ignore_case(&self.text, token)?;
+        let prefix = &self.text[..prefix_len];
+        Some(CaseInsensitiveCompletion {
+            offset: self.range.end,
+            suffix: &self.text[prefix_len..],
+            replacement: (prefix != token).then_some(prefix),
+        })
+    }
+    /// Compares the texts ignoring case. Unlike [`Ord`], different suggestions may
+    /// compare as equal.
     pub fn cmp_ignore_case(&self, other: &Self) -> std::cmp::Ordering {
-        self.text.to_lowercase().cmp(&other.text.to_lowercase())
+        let a = self.text.chars().flat_map(char::to_lowercase);
+        let b = other.text.chars().flat_map(char::to_lowercase);
+        a.cmp(b)
+    }
+    /// The order of built suggestions: ignoring case first, so that the order is
+    /// total.
+    fn cmp_sorted(&self, other: &Self) -> std::cmp::Ordering {
+        self.cmp_ignore_case(other).then_with(|| self.cmp(other))
     }
 }
 
-impl<'t, 'm> Suggestion<'t, 'm> {
-    pub fn text(&'t self) -> &'t str {
-        &self.text
+impl Suggestion<'_, '_> {
+    /// Converts the borrowed text and tooltip into owned data.
+    pub fn into_owned(self) -> Suggestion<'static, 'static> {
+        Suggestion {
+            range: self.range,
+            text: Cow::Owned(self.text.into_owned()),
+            int: self.int,
+            tooltip: self.tooltip.map(|tooltip| Cow::Owned(tooltip.into_owned())),
+            _tooltip_lifetime: PhantomData,
+        }
     }
-    pub fn expand<'s>(&'s self, command: &str, range: StringRange) -> Cow<'s, Self> {
+}
+
+impl<'t, 'm, T> Suggestion<'t, 'm, T> {
+    pub fn expand<'s>(&'s self, command: &str, range: StringRange) -> Cow<'s, Self>
+    where
+        T: Clone,
+    {
         if range == self.range {
             return Cow::Borrowed(self);
         }
+        let range = char_range(command, range);
         let Range {
             start: self_start,
             end: self_end,
-        } = self.range;
+        } = char_range(command, self.range.clone());
         let mut result = String::with_capacity(
             self_start.saturating_sub(range.start)
                 + self.text.len()
@@ -112,14 +684,15 @@ impl<'t, 'm> Suggestion<'t, 'm> {
             ..Default::default()
         })
     }
-    pub fn expand_owned<'s>(self, command: &str, range: StringRange) -> Self {
+    pub fn expand_owned(self, command: &str, range: StringRange) -> Self {
         if range == self.range {
             return self;
         }
+        let range = char_range(command, range);
         let Range {
             start: self_start,
             end: self_end,
-        } = self.range;
+        } = char_range(command, self.range.clone());
         let mut result = String::with_capacity(
             self_start.saturating_sub(range.start)
                 + self.text.len()
@@ -135,7 +708,7 @@ impl<'t, 'm> Suggestion<'t, 'm> {
         Self {
             range,
             text: result.into(),
-            tooltip: self.tooltip.clone(),
+            tooltip: self.tooltip,
             ..Default::default()
         }
     }
@@ -149,7 +722,7 @@ impl<'t, 'm> Suggestion<'t, 'm> {
     pub fn new_text_with_tooltip(
         range: StringRange,
         text: impl Into<Cow<'t, str>>,
-        tooltip: impl Into<Cow<'m, str>>,
+        tooltip: impl Into<T>,
     ) -> Self {
         Self {
             range,
@@ -166,16 +739,13 @@ impl<'t, 'm> Suggestion<'t, 'm> {
             ..Default::default()
         }
     }
-    pub fn new_int_with_tooltip(
-        range: StringRange,
-        int: i32,
-        tooltip: impl Into<Cow<'m, str>>,
-    ) -> Self {
+    pub fn new_int_with_tooltip(range: StringRange, int: i32, tooltip: impl Into<T>) -> Self {
         Self {
             range,
             text: int.to_string().into(),
             int: Some(int),
             tooltip: Some(tooltip.into()),
+            ..Default::default()
         }
     }
     /// Applies this suggestion to a string, "patching" the suggestion into it.
@@ -183,7 +753,7 @@ impl<'t, 'm> Suggestion<'t, 'm> {
         let Range {
             start: range_start,
             end: range_end,
-        } = self.range;
+        } = char_range(input, self.range.clone());
         let input_len = input.len();
         if range_start == 0 && range_end == input_len {
             return (&self.text[..]).into();
@@ -202,16 +772,44 @@ impl<'t, 'm> Suggestion<'t, 'm> {
     }
 }
 
-pub struct SuggestionsBuilder<'i, 't, 'm> {
+/// Result of [`Suggestion::completion_suffix_ignore_case`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaseInsensitiveCompletion<'s> {
+    /// Byte offset at the end of the typed token.
+    pub offset: usize,
+    /// Text to insert at `offset`.
+    pub suffix: &'s str,
+    /// The canonical casing of the typed token, if it was typed in another case.
+    /// It replaces the range of the suggestion up to `offset`.
+    pub replacement: Option<&'s str>,
+}
+
+/// Returns the length in bytes of the prefix of `text` that matches `prefix`,
+/// ignoring case.
+fn prefix_len_ignore_case(text: &str, prefix: &str) -> Option<usize> {
+    let mut text_chars = text.char_indices();
+    for expected in prefix.chars() {
+        let (_, actual) = text_chars.next()?;
+        if !actual.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(text_chars.next().map_or(text.len(), |(index, _)| index))
+}
+
+pub struct SuggestionsBuilder<'i, 't, 'm, T = Cow<'m, str>> {
     start: usize,
     input: &'i str,
-    input_lower_case: &'i str,
-    remaining: &'i str,
-    remaining_lower_case: &'i str,
-    result: Vec<Suggestion<'t, 'm>>,
+    input_lower_case: Cow<'i, str>,
+    start_lower_case: usize,
+    result: Vec<Suggestion<'t, 'm, T>>,
+    transaction_id: Option<u64>,
+    node: Option<CommandNodeId>,
+    limit: Option<usize>,
+    truncated: bool,
 }
 
-impl<'i> SuggestionsBuilder<'i, '_, '_> {
+impl<'i, T> SuggestionsBuilder<'i, '_, '_, T> {
     #[inline]
     pub fn input(&self) -> &'i str {
         self.input
@@ -221,49 +819,152 @@ impl<'i> SuggestionsBuilder<'i, '_, '_> {
         self.start
     }
     pub fn remaining(&self) -> &'i str {
-        self.remaining
+        &self.input[self.start..]
+    }
+    /// The argument node the suggestions are listed for, see
+    /// [`SuggestionsBuilder::set_node`].
+    #[inline]
+    pub fn node(&self) -> Option<CommandNodeId> {
+        self.node
     }
+    /// The maximum number of suggestions to build, see [`SuggestionsBuilder::with_limit`].
     #[inline]
-    pub fn remaining_lower_case(&self) -> &'i str {
-        self.remaining_lower_case
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+    #[inline]
+    pub fn remaining_lower_case(&self) -> &str {
+        &self.input_lower_case[self.start_lower_case..]
     }
 }
 
-impl<'i, 't, 'm> SuggestionsBuilder<'i, 't, 'm> {
-    #[inline]
+impl<'i, 't, 'm, T> SuggestionsBuilder<'i, 't, 'm, T> {
+    /// Creates a builder for suggestions starting at the byte offset `start`, which
+    /// falls back to the previous char boundary if needed.
+    ///
+    /// `input_lower_case` has to keep the byte offsets of `input`, which is not the
+    /// case for every character (e.g. `'İ'`). Use
+    /// [`SuggestionsBuilder::new_with_lowercase`] if that is not guaranteed.
     pub fn new(input: &'i str, input_lower_case: &'i str, start: usize) -> Self {
+        let start = floor_char_boundary(input, start);
         Self {
             start,
             input,
-            input_lower_case,
-            remaining: &input[start..],
-            remaining_lower_case: &input_lower_case[start..],
+            input_lower_case: Cow::Borrowed(input_lower_case),
+            start_lower_case: floor_char_boundary(input_lower_case, start),
             result: Vec::new(),
+            transaction_id: None,
+            node: None,
+            limit: None,
+            truncated: false,
         }
     }
-    pub fn build(self) -> Suggestions<'t, 'm> {
+    /// Like [`SuggestionsBuilder::new`], but computes the lower case form of the input.
+    pub fn new_with_lowercase(input: &'i str, start: usize) -> Self {
+        let start = floor_char_boundary(input, start);
+        let mut input_lower_case = input[..start].to_lowercase();
+        let start_lower_case = input_lower_case.len();
+        input_lower_case.push_str(&input[start..].to_lowercase());
+        Self {
+            start,
+            input,
+            input_lower_case: Cow::Owned(input_lower_case),
+            start_lower_case,
+            result: Vec::new(),
+            transaction_id: None,
+            node: None,
+            limit: None,
+            truncated: false,
+        }
+    }
+    /// Sets an id of the request, which is passed on to the built suggestions so that
+    /// clients can match responses to their requests.
+    pub fn set_transaction_id(&mut self, transaction_id: Option<u64>) -> &mut Self {
+        self.transaction_id = transaction_id;
+        self
+    }
+    /// Sets the argument node the suggestions are listed for, which
+    /// [`Tree::list_suggestions`](crate::tree::Tree::list_suggestions) does before
+    /// calling a provider.
+    pub fn set_node(&mut self, node: Option<CommandNodeId>) -> &mut Self {
+        self.node = node;
+        self
+    }
+    /// Limits the number of suggestions, e.g. when completing against thousands of ids
+    /// which the client would truncate anyway. Only the suggestions which come first
+    /// in the [built order](Suggestions::create) are kept, the others are dropped
+    /// while building and the result is marked as [truncated](Suggestions::truncated).
+    pub fn with_limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self.truncate_result();
+        self
+    }
+    pub fn build(self) -> Suggestions<'t, 'm, T> {
         Suggestions::create(self.input, self.result)
+            .with_transaction_id(self.transaction_id)
+            .with_truncated(self.truncated)
+    }
+    /// Stores the suggestion. With a limit, the stored suggestions are kept sorted
+    /// and the last one is dropped when there are too many.
+    fn push(&mut self, suggestion: Suggestion<'t, 'm, T>) {
+        let Some(limit) = self.limit else {
+            self.result.push(suggestion);
+            return;
+        };
+        match self
+            .result
+            .binary_search_by(|stored| stored.cmp_sorted(&suggestion))
+        {
+            // Equal suggestions are only built once
+            Ok(_) => {}
+            Err(index) if index < limit => {
+                self.result.insert(index, suggestion);
+                if self.result.len() > limit {
+                    self.result.pop();
+                    self.truncated = true;
+                }
+            }
+            Err(_) => self.truncated = true,
+        }
+    }
+    fn truncate_result(&mut self) {
+        if let Some(limit) = self.limit {
+            self.result.sort_by(Suggestion::cmp_sorted);
+            self.result.dedup_by(|a, b| a == b);
+            if self.result.len() > limit {
+                self.result.truncate(limit);
+                self.truncated = true;
+            }
+        }
     }
     pub fn suggest_text(&mut self, text: impl Into<Cow<'t, str>>) -> &mut Self {
         let text: Cow<'t, str> = text.into();
-        if text == self.remaining {
+        if text == self.remaining() {
             self
         } else {
-            self.result
-                .push(Suggestion::new_text(self.start..self.input.len(), text));
+            self.push(Suggestion::new_text(self.start..self.input.len(), text));
+            self
+        }
+    }
+    /// Suggests the text if it starts with the remaining input, ignoring case.
+    pub fn suggest_text_matching(&mut self, text: impl Into<Cow<'t, str>>) -> &mut Self {
+        let text: Cow<'t, str> = text.into();
+        if text.to_lowercase().starts_with(self.remaining_lower_case()) {
+            self.suggest_text(text)
+        } else {
             self
         }
     }
     pub fn suggest_text_with_tooltip(
         &mut self,
         text: impl Into<Cow<'t, str>>,
-        tooltip: impl Into<Cow<'m, str>>,
+        tooltip: impl Into<T>,
     ) -> &mut Self {
         let text: Cow<'t, str> = text.into();
-        if text == self.remaining {
+        if text == self.remaining() {
             self
         } else {
-            self.result.push(Suggestion::new_text_with_tooltip(
+            self.push(Suggestion::new_text_with_tooltip(
                 self.start..self.input.len(),
                 text,
                 tooltip.into(),
@@ -272,30 +973,124 @@ impl<'i, 't, 'm> SuggestionsBuilder<'i, 't, 'm> {
         }
     }
     pub fn suggest_int(&mut self, int: i32) -> &mut Self {
-        self.result
-            .push(Suggestion::new_int(self.start..self.input.len(), int));
+        self.push(Suggestion::new_int(self.start..self.input.len(), int));
         self
     }
-    pub fn suggest_int_with_tooltip(
-        &mut self,
-        int: i32,
-        tooltip: impl Into<Cow<'m, str>>,
-    ) -> &mut Self {
-        self.result.push(Suggestion::new_int_with_tooltip(
+    pub fn suggest_int_with_tooltip(&mut self, int: i32, tooltip: impl Into<T>) -> &mut Self {
+        self.push(Suggestion::new_int_with_tooltip(
             self.start..self.input.len(),
             int,
             tooltip.into(),
         ));
         self
     }
-    pub fn add(mut self, other: &Self) -> Self {
+    pub fn add_all(
+        &mut self,
+        suggestions: impl IntoIterator<Item = Suggestion<'t, 'm, T>>,
+    ) -> &mut Self {
+        for suggestion in suggestions {
+            self.push(suggestion);
+        }
+        self
+    }
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
         self.result.extend_from_slice(&other.result[..]);
+        self.truncated |= other.truncated;
+        self.truncate_result();
         self
     }
     pub fn create_offset(&self, start: usize) -> Self {
-        Self::new(self.input, self.input_lower_case, start)
+        let mut builder = match self.input_lower_case {
+            Cow::Borrowed(input_lower_case) => Self::new(self.input, input_lower_case, start),
+            Cow::Owned(_) => Self::new_with_lowercase(self.input, start),
+        };
+        builder.transaction_id = self.transaction_id;
+        builder.node = self.node;
+        builder.limit = self.limit;
+        builder
     }
     pub fn restart(&self) -> Self {
         self.create_offset(self.start)
     }
 }
+
+/// The region in which two versions of an input differ, found by stripping their
+/// common prefix and suffix.
+struct Edit {
+    start: usize,
+    /// End of the replaced region in the old input
+    old_end: usize,
+    /// End of the replacement in the new input
+    new_end: usize,
+}
+
+impl Edit {
+    fn between(old: &str, new: &str) -> Self {
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|&((_, a), b)| a != b)
+            .map_or(old.len().min(new.len()), |((index, _), _)| index);
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old[prefix..]
+            .chars()
+            .rev()
+            .zip(new[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .scan(0, |length, c| {
+                *length += c;
+                Some(*length)
+            })
+            .take_while(|&length| length <= max_suffix)
+            .last()
+            .unwrap_or(0);
+        Self {
+            start: prefix,
+            old_end: old.len() - suffix,
+            new_end: new.len() - suffix,
+        }
+    }
+    /// Moves a range of the old input onto the new one, if the edit doesn't touch it.
+    fn rebase(&self, range: StringRange) -> Option<StringRange> {
+        if self.start >= range.end {
+            Some(range)
+        } else if self.old_end <= range.start {
+            let shift = |index: usize| index - self.old_end + self.new_end;
+            Some(shift(range.start)..shift(range.end))
+        } else {
+            None
+        }
+    }
+}
+
+/// Widens the range to the nearest char boundaries and clamps it to the string, so that
+/// ranges from other sources (e.g. a client cursor) can't split a character.
+fn char_range(string: &str, range: StringRange) -> StringRange {
+    let start = floor_char_boundary(string, range.start);
+    let end = ceil_char_boundary(string, range.end);
+    start..end.max(start)
+}
+
+fn ceil_char_boundary(string: &str, index: usize) -> usize {
+    if index >= string.len() {
+        return string.len();
+    }
+    (index..string.len())
+        .find(|&index| string.is_char_boundary(index))
+        .unwrap_or(string.len())
+}
+
+fn floor_char_boundary(string: &str, index: usize) -> usize {
+    if index >= string.len() {
+        return string.len();
+    }
+    (0..=index)
+        .rev()
+        .find(|&index| string.is_char_boundary(index))
+        .unwrap_or(0)
+}
@@ -0,0 +1,163 @@
+//! Helpers for testing argument types outside of a dispatcher.
+//!
+//! A custom argument type can check that its examples parse and that its suggestions
+//! are accepted by its parser:
+//!
+//! ```
+//! # use brigadier::{arguments::BoolArgumentType, testing::*};
+//! assert_examples_parse(&BoolArgumentType);
+//! assert_roundtrip_suggestions(&BoolArgumentType, "");
+//! assert_roundtrip_suggestions(&BoolArgumentType, "t");
+//! ```
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use crate::{
+    arguments::{ArgCapabilities, ArgumentType},
+    context::{CommandContext, CommandContextBuilder, ParsedArgument, StringRange},
+    suggestion::{Suggestions, SuggestionsBuilder},
+    tree::CommandNodeId,
+    CommandSource, Shared, StringReader,
+};
+
+/// A source without any data, for argument types which don't depend on the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MockCommandSource;
+
+impl CommandSource for MockCommandSource {}
+
+/// Builds a [`CommandContext`] without a tree, e.g. to pass to
+/// [`ArgumentType::list_suggestions`].
+pub struct MockContext<'i, A, S = MockCommandSource>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    builder: CommandContextBuilder<'i, S, A>,
+}
+
+impl<'i, A> MockContext<'i, A>
+where
+    A: ArgumentType<'i, MockCommandSource>,
+{
+    pub fn new() -> Self {
+        Self::with_source(MockCommandSource)
+    }
+}
+
+impl<'i, A> Default for MockContext<'i, A>
+where
+    A: ArgumentType<'i, MockCommandSource>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'i, A, S> MockContext<'i, A, S>
+where
+    S: CommandSource,
+    A: ArgumentType<'i, S>,
+{
+    pub fn with_source(source: S) -> Self {
+        Self {
+            builder: CommandContextBuilder::new(source, CommandNodeId::default(), 0),
+        }
+    }
+    /// Adds a parsed argument, as if it was parsed from `range`.
+    pub fn argument(
+        &mut self,
+        name: impl Into<Shared<str>>,
+        range: StringRange,
+        result: A::Output,
+    ) -> &mut Self {
+        self.builder
+            .with_argument(name, ParsedArgument { range, result });
+        self
+    }
+    pub fn build(&self, input: &'i str) -> CommandContext<'i, S, A> {
+        self.builder.clone().build(input)
+    }
+}
+
+/// Asserts that every example of the argument type parses and consumes the whole
+/// example. Types without [`ArgCapabilities::EXAMPLES`] are skipped.
+#[track_caller]
+pub fn assert_examples_parse<A>(argument_type: &A)
+where
+    A: for<'i> ArgumentType<'i, MockCommandSource>,
+{
+    if !ArgumentType::<MockCommandSource>::capabilities(argument_type)
+        .contains(ArgCapabilities::EXAMPLES)
+    {
+        return;
+    }
+    for example in ArgumentType::<MockCommandSource>::examples(argument_type) {
+        let mut reader = StringReader::new(example);
+        if let Err(error) = argument_type.parse(&mut reader) {
+            panic!("Example {example:?} doesn't parse: {error}");
+        }
+        assert!(
+            !reader.can_read(),
+            "Example {example:?} is only parsed up to {:?}",
+            &example[..reader.cursor()]
+        );
+    }
+}
+
+/// Lists the suggestions of the argument type for the input and asserts that the
+/// input with each of them applied parses and is consumed completely. Returns the
+/// suggestions for further checks.
+#[track_caller]
+pub fn assert_roundtrip_suggestions<A>(
+    argument_type: &A,
+    input: &str,
+) -> Suggestions<'static, 'static>
+where
+    A: for<'i> ArgumentType<'i, MockCommandSource>,
+{
+    let context = MockContext::<A>::new().build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, 0);
+    let suggestions = block_on(argument_type.list_suggestions(&context, builder)).into_owned();
+    for suggestion in &suggestions {
+        let applied = suggestion.apply(input);
+        let mut reader = StringReader::new(&applied);
+        if let Err(error) = argument_type.parse(&mut reader) {
+            panic!("Suggestion {applied:?} for {input:?} doesn't parse: {error}");
+        }
+        assert!(
+            !reader.can_read(),
+            "Suggestion {applied:?} for {input:?} is only parsed up to {:?}",
+            &applied[..reader.cursor()]
+        );
+    }
+    suggestions
+}
+
+/// Wakes the thread which is blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs the future on the current thread, since suggestions are listed asynchronously.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
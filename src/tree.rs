@@ -1,53 +1,410 @@
 use std::{
-    collections::{HashMap, HashSet},
-    rc::Rc,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Write as _},
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
 };
 
+use indexmap::{IndexMap, IndexSet};
 use slotmap::{SecondaryMap, SlotMap};
 
-use crate::{command::Command, context::CommandContext, suggestion::SuggestionProvider, CommandSource};
+use crate::{
+    ambiguity::{AmbiguityChecker, AmbiguityFinding, CheckProgress},
+    arguments::ArgumentType,
+    command::NodeCommand,
+    context::{CommandContext, CommandContextBuilder, ParsedArgument},
+    dispatcher::ARGUMENT_SEPARATOR,
+    errors::{CommandErrorType, CommandSyntaxError},
+    requirement::SharedRequirement,
+    suggestion::{
+        ProviderState, SharedSuggestionProvider, SuggestionMode, SuggestionRangeCheck, Suggestions,
+        SuggestionsBuilder,
+    },
+    CommandSource, Shared, StringReader,
+};
+
+pub mod matcher;
+#[cfg(feature = "packet")]
+pub mod protocol;
+#[cfg(feature = "serde")]
+pub mod serialize;
+#[cfg(feature = "testing")]
+pub mod snapshot;
+pub mod validation;
+
+use matcher::LiteralMatcher;
+
+const USAGE_OPTIONAL_OPEN: char = '[';
+const USAGE_OPTIONAL_CLOSE: char = ']';
+const USAGE_REQUIRED_OPEN: char = '(';
+const USAGE_REQUIRED_CLOSE: char = ')';
+const USAGE_OR: char = '|';
+/// Nodes with at least this many literal children look them up using a [`LiteralMatcher`].
+const LITERAL_MATCHER_THRESHOLD: usize = 16;
 
 slotmap::new_key_type! {
     pub struct CommandNodeId;
 }
 type NodeId = CommandNodeId;
 
-pub struct Tree<'i, S>
+pub struct Tree<S, A>
 where
     S: CommandSource,
 {
-    strings: HashSet<Rc<str>>,
-    nodes: SlotMap<NodeId, CommandNodeComponent<'i, S>>,
+    strings: HashSet<Shared<str>>,
+    nodes: SlotMap<NodeId, CommandNodeComponent<S, A>>,
     literals: SecondaryMap<NodeId, LiteralCommandNodeComponent>,
-    arguments: SecondaryMap<NodeId, ArgumentCommandNodeComponent<S>>,
+    arguments: SecondaryMap<NodeId, ArgumentCommandNodeComponent<S, A>>,
+    generation: u64,
+    /// Matchers built lazily for nodes with many literal children, valid for the
+    /// generation they were built in. Locked instead of borrowed, so that the tree can be
+    /// shared between threads with the `send-futures` feature.
+    literal_matchers: Mutex<HashMap<NodeId, Shared<LiteralMatcher>>>,
+    literal_matchers_generation: AtomicU64,
+    /// States of the custom suggestion providers of argument nodes, valid for the
+    /// generation they were built in.
+    provider_states: Mutex<HashMap<NodeId, Option<ProviderState>>>,
+    provider_states_generation: AtomicU64,
+    suggestion_range_check: SuggestionRangeCheck,
+}
+
+impl<S, A> Default for Tree<S, A>
+where
+    S: CommandSource,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Dumps the nodes below every root, see [`Tree::write_tree`].
+impl<S, A> fmt::Debug for Tree<S, A>
+where
+    S: CommandSource,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        for root in self.roots() {
+            self.tree_lines(root, &mut out);
+        }
+        f.write_str(&out)
+    }
 }
 
-impl<'i, S> Tree<'i, S>
+impl<S, A> Tree<S, A>
 where
     S: CommandSource,
 {
+    pub fn new() -> Self {
+        Self {
+            strings: HashSet::new(),
+            nodes: SlotMap::with_key(),
+            literals: SecondaryMap::new(),
+            arguments: SecondaryMap::new(),
+            generation: 0,
+            literal_matchers: Mutex::new(HashMap::new()),
+            literal_matchers_generation: AtomicU64::new(0),
+            provider_states: Mutex::new(HashMap::new()),
+            provider_states_generation: AtomicU64::new(0),
+            suggestion_range_check: SuggestionRangeCheck::default(),
+        }
+    }
     #[inline]
-    pub fn add_node(&mut self, node: impl TreeNode<'i, S>) -> NodeId {
+    pub fn add_node(&mut self, node: impl TreeNode<S, A>) -> NodeId {
+        self.generation += 1;
         node.add_to_tree(self)
     }
-    fn get_shared_str(&mut self, string: &str) -> Rc<str> {
+    #[inline]
+    pub fn get(&self, node_id: NodeId) -> Option<&CommandNodeComponent<S, A>> {
+        self.nodes.get(node_id)
+    }
+    /// Iterates over every node of the tree in no particular order, including nodes
+    /// which aren't attached to any parent.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &CommandNodeComponent<S, A>)> + '_ {
+        self.nodes.iter()
+    }
+    /// Increases every time the tree is modified.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    #[inline]
+    pub fn suggestion_range_check(&self) -> SuggestionRangeCheck {
+        self.suggestion_range_check
+    }
+    /// Sets how suggestions outside of their argument are handled, see
+    /// [`list_suggestions`](Self::list_suggestions).
+    pub fn set_suggestion_range_check(&mut self, check: SuggestionRangeCheck) {
+        self.suggestion_range_check = check;
+    }
+    /// Returns the literal of a literal node or the name of an argument node.
+    pub fn name(&self, node_id: NodeId) -> Option<&str> {
+        match self.nodes.get(node_id)?.node_type {
+            CommandNodeType::Root => Some(""),
+            CommandNodeType::Literal => Some(&self.literals[node_id].literal),
+            CommandNodeType::Argument => Some(&self.arguments[node_id].name),
+        }
+    }
+    /// Returns the argument type of an argument node.
+    pub fn argument_type(&self, node_id: NodeId) -> Option<&A> {
+        self.arguments
+            .get(node_id)
+            .map(|argument| &argument.argument_type)
+    }
+    /// Returns the provider an argument node suggests with instead of its argument
+    /// type, if any.
+    pub fn custom_suggestions(&self, node_id: NodeId) -> Option<&SharedSuggestionProvider<S, A>> {
+        self.arguments.get(node_id)?.custom_suggestions.as_ref()
+    }
+    /// Returns the component of an argument node.
+    #[inline]
+    pub fn as_argument(&self, node_id: NodeId) -> Option<&ArgumentCommandNodeComponent<S, A>> {
+        self.arguments.get(node_id)
+    }
+    /// Returns the component of a literal node.
+    #[inline]
+    pub fn as_literal(&self, node_id: NodeId) -> Option<&LiteralCommandNodeComponent> {
+        self.literals.get(node_id)
+    }
+    /// Returns the input an optional argument node is parsed from if the input ends
+    /// before it, see
+    /// [`optional_with_default`](crate::builder::RequiredArgumentBuilder::optional_with_default).
+    pub fn argument_default(&self, node_id: NodeId) -> Option<&'static str> {
+        self.arguments.get(node_id)?.default
+    }
+    pub(crate) fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.node_type == CommandNodeType::Root)
+            .map(|(node_id, _)| node_id)
+    }
+    /// Returns the children of a node ordered by name. A literal comes before an
+    /// argument with the same name.
+    pub(crate) fn sorted_children(&self, node_id: NodeId) -> Vec<NodeId> {
+        let node = &self.nodes[node_id];
+        let mut children: Vec<_> = node
+            .literals
+            .iter()
+            .map(|(name, &child_id)| (name, false, child_id))
+            .chain(
+                node.arguments
+                    .iter()
+                    .map(|(name, &child_id)| (name, true, child_id)),
+            )
+            .collect();
+        children.sort_unstable_by_key(|&(name, is_argument, _)| (name, is_argument));
+        children
+            .into_iter()
+            .map(|(_, _, child_id)| child_id)
+            .collect()
+    }
+    /// Numbers the nodes reachable from the root in breadth-first order, following
+    /// the nodes `next` returns for each node. Returns the nodes in that order and the
+    /// index of each node.
+    #[cfg(any(feature = "packet", feature = "serde"))]
+    pub(crate) fn breadth_first_indices<I>(
+        &self,
+        root: NodeId,
+        mut next: impl FnMut(NodeId) -> I,
+    ) -> (Vec<NodeId>, HashMap<NodeId, usize>)
+    where
+        I: IntoIterator<Item = NodeId>,
+    {
+        // Redirects may point anywhere, so nodes are numbered before any is converted
+        let mut indices = HashMap::new();
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([root]);
+        while let Some(node_id) = queue.pop_front() {
+            if indices.contains_key(&node_id) {
+                continue;
+            }
+            indices.insert(node_id, order.len());
+            order.push(node_id);
+            queue.extend(next(node_id));
+        }
+        (order, indices)
+    }
+    /// Returns the children which can parse the next token of the reader: the literals
+    /// matching it first, then all arguments, each ordered by name. Literals take
+    /// precedence when a literal and an argument both accept the token.
+    pub(crate) fn get_relevant_nodes(
+        &self,
+        node_id: NodeId,
+        reader: &StringReader,
+        matching: LiteralMatching,
+    ) -> Vec<NodeId> {
+        let token = next_token(reader.remaining());
+        let mut arguments: Vec<_> = self.nodes[node_id].arguments.iter().collect();
+        arguments.sort_unstable_by_key(|(name, _)| *name);
+        let mut relevant = self.matching_literals(node_id, token, matching);
+        relevant.extend(arguments.into_iter().map(|(_, &child_id)| child_id));
+        relevant
+    }
+    /// Returns the literal children of a node which match the token, ordered by name.
+    /// Exact matches are looked up by name, other matches in the
+    /// [`LiteralMatcher`] of nodes with many literals, so that not every literal has to
+    /// be compared with the token.
+    pub fn matching_literals(
+        &self,
+        node_id: NodeId,
+        token: &str,
+        matching: LiteralMatching,
+    ) -> Vec<NodeId> {
+        let node = &self.nodes[node_id];
+        if matching == LiteralMatching::default() {
+            return node.find_literal(token).into_iter().collect();
+        }
+        let mut literals = match self.literal_matcher(node_id) {
+            // Every match starts with the token ignoring case
+            Some(matcher) if matching.unique_prefix => matcher.completions(token),
+            Some(matcher) => matcher.exact(token).to_vec(),
+            None => node.literals().collect(),
+        };
+        literals.retain(|&child_id| {
+            self.match_literal(node_id, &self.literals[child_id], token, matching)
+                .is_some()
+        });
+        literals.sort_unstable_by_key(|&child_id| &self.literals[child_id].literal);
+        literals
+    }
+    /// Returns the literal children of a node whose names start with `prefix` ignoring
+    /// case, ordered by their lowercase names.
+    pub fn literal_completions(&self, node_id: NodeId, prefix: &str) -> Vec<NodeId> {
+        if let Some(matcher) = self.literal_matcher(node_id) {
+            return matcher.completions(prefix);
+        }
+        let mut completions: Vec<_> = self.nodes[node_id]
+            .literals
+            .values()
+            .map(|&child_id| (&self.literals[child_id], child_id))
+            .filter(|(literal, _)| literal.has_prefix(prefix, true))
+            .collect();
+        completions.sort_by_key(|(literal, _)| &literal.literal_lower_case);
+        completions
+            .into_iter()
+            .map(|(_, child_id)| child_id)
+            .collect()
+    }
+    /// Returns the matcher for the literal children of a node if it has enough of them to
+    /// be worth it, building it if the tree changed since.
+    fn literal_matcher(&self, node_id: NodeId) -> Option<Shared<LiteralMatcher>> {
+        let node = &self.nodes[node_id];
+        if node.literals.len() < LITERAL_MATCHER_THRESHOLD {
+            return None;
+        }
+        let mut matchers = self.literal_matchers.lock().unwrap();
+        if self
+            .literal_matchers_generation
+            .swap(self.generation, Ordering::Relaxed)
+            != self.generation
+        {
+            matchers.clear();
+        }
+        let matcher = matchers.entry(node_id).or_insert_with(|| {
+            let mut matcher = LiteralMatcher::new();
+            for &child_id in node.literals.values() {
+                matcher.insert(&self.literals[child_id].literal_lower_case, child_id);
+            }
+            Shared::new(matcher)
+        });
+        Some(Shared::clone(matcher))
+    }
+    /// Returns the state of the custom suggestion provider of an argument node, building
+    /// it if the tree changed since.
+    fn provider_state(
+        &self,
+        node_id: NodeId,
+        provider: &SharedSuggestionProvider<S, A>,
+    ) -> Option<ProviderState> {
+        let mut states = self.provider_states.lock().unwrap();
+        if self
+            .provider_states_generation
+            .swap(self.generation, Ordering::Relaxed)
+            != self.generation
+        {
+            states.clear();
+        }
+        states
+            .entry(node_id)
+            .or_insert_with(|| provider.build_state())
+            .clone()
+    }
+    /// Builds the states of all custom suggestion providers now instead of during the
+    /// first request, e.g. while the server is idle.
+    pub fn warm_suggestions(&self) {
+        for (node_id, argument) in &self.arguments {
+            if let Some(provider) = &argument.custom_suggestions {
+                self.provider_state(node_id, provider);
+            }
+        }
+    }
+    pub(crate) fn insert_literal(
+        &mut self,
+        literal: &str,
+        component: CommandNodeComponent<S, A>,
+    ) -> NodeId {
+        let literal = self.get_shared_str(literal);
+        // Most literals are lowercase already and share their name
+        let literal_lower_case = if is_lower_case(&literal) {
+            Shared::clone(&literal)
+        } else {
+            self.get_shared_str(&literal.to_lowercase())
+        };
+        let node_id = self.nodes.insert(component);
+        self.literals.insert(
+            node_id,
+            LiteralCommandNodeComponent {
+                literal,
+                literal_lower_case,
+            },
+        );
+        node_id
+    }
+    pub(crate) fn insert_argument(
+        &mut self,
+        name: &str,
+        argument_type: A,
+        custom_suggestions: Option<SharedSuggestionProvider<S, A>>,
+        suggestion_mode: SuggestionMode,
+        default: Option<&'static str>,
+        component: CommandNodeComponent<S, A>,
+    ) -> NodeId {
+        let name = self.get_shared_str(name);
+        let node_id = self.nodes.insert(component);
+        self.arguments.insert(
+            node_id,
+            ArgumentCommandNodeComponent {
+                name,
+                argument_type,
+                custom_suggestions,
+                suggestion_mode,
+                default,
+            },
+        );
+        node_id
+    }
+    fn get_shared_str(&mut self, string: &str) -> Shared<str> {
         // TODO: https://github.com/rust-lang/rust/issues/60896
-        //Rc::clone(self.strings.get_or_insert_with(string, Rc::new))
+        //Shared::clone(self.strings.get_or_insert_with(string, Shared::new))
         if let Some(s) = self.strings.get(string) {
             s.clone()
         } else {
-            let s = Rc::from(string);
-            self.strings.insert(Rc::clone(&s));
+            let s = Shared::from(string);
+            self.strings.insert(Shared::clone(&s));
             s
         }
     }
     /// Deallocates all unused shared strings and returns the amount
     /// of deallocated strings.
+    #[allow(dead_code)]
     fn collect_garbage(&mut self) -> usize {
         let mut flagged = Vec::with_capacity(self.strings.len());
         for s in &self.strings {
-            if Rc::strong_count(s) == 1 {
-                flagged.push(Rc::clone(s));
+            if Shared::strong_count(s) == 1 {
+                flagged.push(Shared::clone(s));
             }
         }
         for s in &flagged {
@@ -55,164 +412,1282 @@ where
         }
         flagged.len()
     }
-    pub fn add_child(&mut self, parent_id: NodeId, child_id: NodeId) -> Result<(), ()> {
+    /// Adds a literal to the parent which redirects to the target. Like vanilla aliases,
+    /// it copies the command and requirement of the target, so that it can be executed
+    /// without arguments as well. Returns the id of the attached alias.
+    pub fn add_alias(&mut self, parent_id: NodeId, alias: &str, target_id: NodeId) -> NodeId {
+        let target = &self.nodes[target_id];
+        let component = CommandNodeComponent::new(
+            CommandNodeType::Literal,
+            target.requirement.clone(),
+            Some(target_id),
+            None,
+            false,
+            target.command.clone(),
+        );
+        let alias_id = self.insert_literal(alias, component);
+        self.add_child(parent_id, alias_id).unwrap()
+    }
+    /// Removes a node, detaches it from all parents and clears all redirects to it.
+    /// Its children are only detached from it and stay in the tree, since they may be
+    /// shared with other parents, but lose their [parent](Self::parent). Returns whether
+    /// the node existed.
+    pub fn remove_node(&mut self, node_id: NodeId) -> bool {
+        if self.nodes.remove(node_id).is_none() {
+            return false;
+        }
+        self.generation += 1;
+        self.literals.remove(node_id);
+        self.arguments.remove(node_id);
+        for node in self.nodes.values_mut() {
+            if node.parent == Some(node_id) {
+                node.parent = None;
+            }
+            node.literals.retain(|_, &mut child_id| child_id != node_id);
+            node.arguments
+                .retain(|_, &mut child_id| child_id != node_id);
+            if node.redirect == Some(node_id) {
+                node.redirect = None;
+                node.redirect_modifier = None;
+                node.forks = false;
+            }
+        }
+        true
+    }
+    /// Adds a child to the parent node. If the parent already has a child with the same
+    /// name, the new child is merged into the existing one. Returns the id of the child
+    /// which is now attached to the parent.
+    #[allow(clippy::result_unit_err)]
+    pub fn add_child(&mut self, parent_id: NodeId, child_id: NodeId) -> Result<NodeId, ()> {
+        self.generation += 1;
+        // Merging re-attaches the children of the merged node, which may merge again.
+        // A queue instead of recursion keeps deep trees from overflowing the stack.
+        let mut queue = VecDeque::new();
+        let attached = self.attach_child(parent_id, child_id, &mut queue)?;
+        while let Some((parent_id, child_id)) = queue.pop_front() {
+            self.attach_child(parent_id, child_id, &mut queue).unwrap();
+        }
+        Ok(attached)
+    }
+    /// Attaches a child like [`add_child`](Self::add_child), but queues the children of
+    /// a merged node instead of attaching them.
+    fn attach_child(
+        &mut self,
+        parent_id: NodeId,
+        child_id: NodeId,
+        queue: &mut VecDeque<(NodeId, NodeId)>,
+    ) -> Result<NodeId, ()> {
         if let Some([parent, child]) = self.nodes.get_disjoint_mut([parent_id, child_id]) {
             let child_name = match child.node_type {
                 CommandNodeType::Root => return Err(()),
                 CommandNodeType::Argument => {
-                    Rc::clone(&unsafe { self.arguments.get_unchecked(child_id) }.name)
+                    Shared::clone(&unsafe { self.arguments.get_unchecked(child_id) }.name)
                 }
                 CommandNodeType::Literal => {
-                    Rc::clone(&unsafe { self.literals.get_unchecked(child_id) }.literal)
+                    Shared::clone(&unsafe { self.literals.get_unchecked(child_id) }.literal)
                 }
             };
-            match parent.children.get(&child_name) {
+            // Literals and arguments may share a name, only nodes of the same type merge
+            let siblings = match child.node_type {
+                CommandNodeType::Root => unsafe { std::hint::unreachable_unchecked() },
+                CommandNodeType::Argument => &mut parent.arguments,
+                CommandNodeType::Literal => &mut parent.literals,
+            };
+            match siblings.get(&child_name) {
+                // Adding a child again would merge its whole subtree into itself
+                Some(&e_child_id) if e_child_id == child_id => return Ok(child_id),
                 Some(&e_child_id) => {
                     // We've found something to merge onto
-                    let grandchildren: Vec<_> = child.children.values().cloned().collect();
-                    if let Some(command) = child.command {
-                        let e_child = self.nodes.get_mut(e_child_id).unwrap();
-                        e_child.command = Some(command);
+                    let grandchildren: Vec<_> = child.children().map(|(_, id)| id).collect();
+                    let command = child.command.clone();
+                    let description = child.description.clone();
+                    let keywords = child.keywords.clone();
+                    let sensitive = child.sensitive;
+                    let e_child = self.nodes.get_mut(e_child_id).unwrap();
+                    e_child.sensitive |= sensitive;
+                    if command.is_some() {
+                        e_child.command = command;
+                    }
+                    if description.is_some() {
+                        e_child.description = description;
+                    }
+                    for keyword in keywords {
+                        if !e_child.keywords.contains(&keyword) {
+                            e_child.keywords.push(keyword);
+                        }
                     }
                     for grandchild_id in grandchildren {
-                        self.add_child(e_child_id, grandchild_id).unwrap()
+                        // The merged child is discarded, so its children are re-parented
+                        let grandchild = &mut self.nodes[grandchild_id];
+                        if grandchild.parent == Some(child_id) {
+                            grandchild.parent = None;
+                        }
+                        queue.push_back((e_child_id, grandchild_id));
                     }
+                    return Ok(e_child_id);
                 }
                 None => {
-                    parent.children.insert(Rc::clone(&child_name), child_id);
-                    match child.node_type {
-                        CommandNodeType::Root => unsafe { std::hint::unreachable_unchecked() },
-                        CommandNodeType::Argument => {
-                            parent.arguments.insert(child_name, child_id);
-                        }
-                        CommandNodeType::Literal => {
-                            parent.literals.insert(child_name, child_id);
+                    child.parent.get_or_insert(parent_id);
+                    siblings.insert(child_name, child_id);
+                }
+            }
+            return Ok(child_id);
+        }
+        Err(())
+    }
+    /// Whether two nodes are structurally equal, like the `equals` of Brigadier's
+    /// `CommandNode`: they have the same type and name, both or neither are executable,
+    /// they redirect to the same node and their children match by name recursively.
+    ///
+    /// Commands, modifiers and requirements are closures, which are only compared by
+    /// presence, not by value.
+    pub fn nodes_match(&self, a: NodeId, b: NodeId) -> bool {
+        let mut stack = vec![(a, b)];
+        let mut visited = HashSet::new();
+        while let Some((a, b)) = stack.pop() {
+            if a == b || !visited.insert((a, b)) {
+                continue;
+            }
+            let (Some(node_a), Some(node_b)) = (self.nodes.get(a), self.nodes.get(b)) else {
+                return false;
+            };
+            let same_name = match node_a.node_type {
+                CommandNodeType::Root => node_b.node_type == CommandNodeType::Root,
+                CommandNodeType::Literal => self.literals.get(b).is_some_and(|literal| {
+                    literal.literal == self.literals[a].literal
+                }),
+                CommandNodeType::Argument => self.arguments.get(b).is_some_and(|argument| {
+                    argument.name == self.arguments[a].name
+                }),
+            };
+            if !same_name
+                || node_a.command.is_some() != node_b.command.is_some()
+                || node_a.redirect_modifier.is_some() != node_b.redirect_modifier.is_some()
+                || node_a.requirement.is_some() != node_b.requirement.is_some()
+                || node_a.redirect != node_b.redirect
+                || node_a.forks != node_b.forks
+                || node_a.literals.len() != node_b.literals.len()
+                || node_a.arguments.len() != node_b.arguments.len()
+            {
+                return false;
+            }
+            for (children_a, children_b) in [
+                (&node_a.literals, &node_b.literals),
+                (&node_a.arguments, &node_b.arguments),
+            ] {
+                for (name, &child_a) in children_a {
+                    let Some(&child_b) = children_b.get(name) else {
+                        return false;
+                    };
+                    stack.push((child_a, child_b));
+                }
+            }
+        }
+        true
+    }
+    /// Returns the parent a node was first attached to. Nodes can be shared by several
+    /// parents, the others are not tracked.
+    #[inline]
+    pub fn parent(&self, node_id: NodeId) -> Option<NodeId> {
+        self.nodes.get(node_id)?.parent
+    }
+    /// Iterates over all nodes below a node in preorder, children ordered by name. Only
+    /// child edges are followed, not redirects, and every node is visited once.
+    pub fn descendants(&self, node_id: NodeId) -> Descendants<'_, S, A> {
+        let mut descendants = Descendants {
+            tree: self,
+            stack: Vec::new(),
+            visited: HashSet::from([node_id]),
+        };
+        descendants.push_children(node_id);
+        descendants
+    }
+    /// Calls the visitor for the node and then for its [descendants](Self::descendants)
+    /// in the same order.
+    pub fn walk_from(
+        &self,
+        node_id: NodeId,
+        mut visitor: impl FnMut(NodeId, &CommandNodeComponent<S, A>),
+    ) {
+        for node_id in std::iter::once(node_id).chain(self.descendants(node_id)) {
+            visitor(node_id, &self.nodes[node_id]);
+        }
+    }
+    /// Iterates over the descendants of a node which are executable or have no
+    /// children.
+    pub fn leaves(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.descendants(node_id).filter(|&id| {
+            let node = &self.nodes[id];
+            node.command.is_some() || !node.has_children()
+        })
+    }
+    /// Finds the paths from a root through which the target node can be reached. A path
+    /// lists the nodes in the order they are typed, so redirects (e.g. aliases) show up
+    /// as the node that redirects instead of the redirect target. A path ends with the
+    /// target or with a node redirecting to it. At most `max_paths` paths are returned.
+    pub fn reachable_from_root_paths(&self, target: NodeId, max_paths: usize) -> ReachablePaths {
+        // Only the first parent is stored, so the reverse edges are collected on demand
+        let mut parents: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut redirected_from: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for (node_id, node) in &self.nodes {
+            for (_, child_id) in node.children() {
+                parents.entry(child_id).or_default().push(node_id);
+            }
+            if let Some(redirect) = node.redirect {
+                redirected_from.entry(redirect).or_default().push(node_id);
+            }
+        }
+        let mut search = PathSearch {
+            tree: self,
+            parents: &parents,
+            redirected_from: &redirected_from,
+            max_paths,
+            path: Vec::new(),
+            found: HashSet::new(),
+            truncated: false,
+        };
+        if self.nodes.contains_key(target) {
+            search.visit(target);
+            for &alias in redirected_from.get(&target).into_iter().flatten() {
+                search.visit(alias);
+            }
+        }
+        let mut paths: Vec<_> = search.found.into_iter().collect();
+        paths.sort_unstable();
+        ReachablePaths {
+            paths,
+            truncated: search.truncated,
+        }
+    }
+    /// Returns the usage of the node as it's shown to users, like `<name>` for arguments.
+    pub fn usage_text(&self, node_id: NodeId) -> String {
+        let mut usage = String::new();
+        self.write_usage_text(node_id, &mut usage);
+        usage
+    }
+    /// Returns the usage of the node followed by where it redirects to: `-> target` for
+    /// redirects, `...` for redirects to the root and an additional `...` for forks,
+    /// e.g. `as <targets> -> execute ...`.
+    pub fn usage_text_with_redirect(&self, node_id: NodeId) -> String {
+        let mut usage = self.usage_text(node_id);
+        let node = &self.nodes[node_id];
+        // Removing a node clears redirects to it, but the target may be in another tree
+        let Some(redirect) = node.redirect.filter(|&id| self.nodes.contains_key(id)) else {
+            return usage;
+        };
+        usage.push(ARGUMENT_SEPARATOR);
+        if self.nodes[redirect].node_type == CommandNodeType::Root {
+            usage.push_str("...");
+            return usage;
+        }
+        usage.push_str("-> ");
+        self.write_usage_text(redirect, &mut usage);
+        if node.forks {
+            usage.push(ARGUMENT_SEPARATOR);
+            usage.push_str("...");
+        }
+        usage
+    }
+    pub(crate) fn write_usage_text(&self, node_id: NodeId, out: &mut String) {
+        match self.nodes[node_id].node_type {
+            CommandNodeType::Root => {}
+            CommandNodeType::Literal => out.push_str(&self.literals[node_id].literal),
+            CommandNodeType::Argument => {
+                let argument = &self.arguments[node_id];
+                if argument.default.is_some() {
+                    out.push(USAGE_OPTIONAL_OPEN);
+                }
+                out.push('<');
+                out.push_str(&argument.name);
+                out.push('>');
+                if argument.default.is_some() {
+                    out.push(USAGE_OPTIONAL_CLOSE);
+                }
+            }
+        }
+    }
+    /// Returns the usage of every executable command and redirect below the node. If
+    /// `restricted`, nodes the source can't use are skipped.
+    pub fn get_all_usage(&self, node_id: NodeId, source: &S, restricted: bool) -> Vec<String> {
+        let mut result = Vec::new();
+        self.collect_all_usage(node_id, source, &mut result, String::new(), restricted);
+        result
+    }
+    fn collect_all_usage(
+        &self,
+        node_id: NodeId,
+        source: &S,
+        result: &mut Vec<String>,
+        prefix: String,
+        restricted: bool,
+    ) {
+        let node = &self.nodes[node_id];
+        if restricted && !node.can_use(source) {
+            return;
+        }
+        if node.command.is_some() {
+            result.push(prefix.clone());
+        }
+        if let Some(redirect) = node.redirect {
+            let mut usage = prefix;
+            if usage.is_empty() {
+                self.write_usage_text(node_id, &mut usage);
+            }
+            usage.push(ARGUMENT_SEPARATOR);
+            if self.nodes[redirect].node_type == CommandNodeType::Root {
+                usage.push_str("...");
+            } else {
+                usage.push_str("-> ");
+                self.write_usage_text(redirect, &mut usage);
+            }
+            result.push(usage);
+        } else {
+            for child_id in self.sorted_children(node_id) {
+                let mut usage = prefix.clone();
+                if !usage.is_empty() {
+                    usage.push(ARGUMENT_SEPARATOR);
+                }
+                self.write_usage_text(child_id, &mut usage);
+                self.collect_all_usage(child_id, source, result, usage, restricted);
+            }
+        }
+    }
+    /// Returns a compact usage for each child of the node, which the source can use.
+    /// Optional parts are written as `[...]` and alternatives as `(a|b)`.
+    pub fn get_smart_usage(&self, node_id: NodeId, source: &S) -> IndexMap<NodeId, String> {
+        let optional = self.nodes[node_id].command.is_some();
+        self.sorted_children(node_id)
+            .into_iter()
+            .filter_map(|child_id| {
+                let usage = self.smart_usage(child_id, source, optional, false)?;
+                Some((child_id, usage))
+            })
+            .collect()
+    }
+    fn smart_usage(
+        &self,
+        node_id: NodeId,
+        source: &S,
+        optional: bool,
+        deep: bool,
+    ) -> Option<String> {
+        let node = &self.nodes[node_id];
+        if !node.can_use(source) {
+            return None;
+        }
+        // Optional arguments are already written in brackets
+        let optional = optional && self.argument_default(node_id).is_none();
+        let mut usage = String::new();
+        if optional {
+            usage.push(USAGE_OPTIONAL_OPEN);
+        }
+        self.write_usage_text(node_id, &mut usage);
+        if optional {
+            usage.push(USAGE_OPTIONAL_CLOSE);
+        }
+        if deep {
+            return Some(usage);
+        }
+        let child_optional = node.command.is_some();
+        if let Some(redirect) = node.redirect {
+            usage.push(ARGUMENT_SEPARATOR);
+            if self.nodes[redirect].node_type == CommandNodeType::Root {
+                usage.push_str("...");
+            } else {
+                usage.push_str("-> ");
+                self.write_usage_text(redirect, &mut usage);
+            }
+            return Some(usage);
+        }
+        let children: Vec<_> = self
+            .sorted_children(node_id)
+            .into_iter()
+            .filter(|&child_id| self.nodes[child_id].can_use(source))
+            .collect();
+        match children[..] {
+            [] => {}
+            [child_id] => {
+                if let Some(child_usage) =
+                    self.smart_usage(child_id, source, child_optional, child_optional)
+                {
+                    usage.push(ARGUMENT_SEPARATOR);
+                    usage.push_str(&child_usage);
+                }
+            }
+            _ => {
+                let mut child_usages = IndexSet::new();
+                for &child_id in &children {
+                    if let Some(child_usage) =
+                        self.smart_usage(child_id, source, child_optional, true)
+                    {
+                        child_usages.insert(child_usage);
+                    }
+                }
+                if child_usages.len() == 1 {
+                    usage.push(ARGUMENT_SEPARATOR);
+                    let child_usage = &child_usages[0];
+                    if child_optional {
+                        usage.push(USAGE_OPTIONAL_OPEN);
+                        usage.push_str(child_usage);
+                        usage.push(USAGE_OPTIONAL_CLOSE);
+                    } else {
+                        usage.push_str(child_usage);
+                    }
+                } else if child_usages.len() > 1 {
+                    usage.push(ARGUMENT_SEPARATOR);
+                    usage.push(if child_optional {
+                        USAGE_OPTIONAL_OPEN
+                    } else {
+                        USAGE_REQUIRED_OPEN
+                    });
+                    for (i, &child_id) in children.iter().enumerate() {
+                        if i > 0 {
+                            usage.push(USAGE_OR);
                         }
+                        self.write_usage_text(child_id, &mut usage);
                     }
+                    usage.push(if child_optional {
+                        USAGE_OPTIONAL_CLOSE
+                    } else {
+                        USAGE_REQUIRED_CLOSE
+                    });
                 }
             }
-            return Ok(());
         }
-        Err(())
+        Some(usage)
+    }
+    /// Writes the tree as a Graphviz digraph for debugging. Children are connected with
+    /// solid edges, redirects with dashed ones. Executable nodes are marked with `*`.
+    pub fn write_dot(&self, w: &mut impl io::Write) -> io::Result<()> {
+        writeln!(w, "digraph {{")?;
+        for (node_id, node) in &self.nodes {
+            let shape = match node.node_type {
+                CommandNodeType::Root => "doublecircle",
+                CommandNodeType::Literal => "box",
+                CommandNodeType::Argument => "ellipse",
+            };
+            let mut label = match node.node_type {
+                CommandNodeType::Root => "<root>".to_owned(),
+                _ => self.usage_text(node_id),
+            };
+            if node.command.is_some() {
+                label.push_str(" *");
+            }
+            writeln!(
+                w,
+                "  {} [label=\"{}\", shape={shape}];",
+                dot_id(node_id),
+                label.replace('\\', "\\\\").replace('"', "\\\"")
+            )?;
+            for child_id in self.sorted_children(node_id) {
+                writeln!(w, "  {} -> {};", dot_id(node_id), dot_id(child_id))?;
+            }
+            if let Some(redirect) = node.redirect {
+                writeln!(
+                    w,
+                    "  {} -> {} [style=dashed];",
+                    dot_id(node_id),
+                    dot_id(redirect)
+                )?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+    /// Writes the node and its descendants as an indented tree for debugging, one line
+    /// per node with its id and usage. Executable nodes are marked with `*` and
+    /// redirects with `-> #id`. Nodes reached a second time, e.g. through a cycle, are
+    /// marked with `^` and not expanded again.
+    pub fn write_tree(&self, w: &mut impl io::Write, node_id: NodeId) -> io::Result<()> {
+        let mut out = String::new();
+        self.tree_lines(node_id, &mut out);
+        w.write_all(out.as_bytes())
+    }
+    fn tree_lines(&self, node_id: NodeId, out: &mut String) {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(node_id, 0)];
+        while let Some((node_id, depth)) = stack.pop() {
+            let node = &self.nodes[node_id];
+            let indent = "  ".repeat(depth);
+            let usage = match node.node_type {
+                CommandNodeType::Root => "<root>".to_owned(),
+                _ => self.usage_text(node_id),
+            };
+            write!(out, "{indent}{} {usage}", tree_id(node_id)).unwrap();
+            if node.command.is_some() {
+                out.push_str(" *");
+            }
+            if let Some(redirect) = node.redirect {
+                write!(out, " -> {}", tree_id(redirect)).unwrap();
+            }
+            if !visited.insert(node_id) {
+                out.push_str(" ^\n");
+                continue;
+            }
+            out.push('\n');
+            let children = self.sorted_children(node_id);
+            stack.extend(children.into_iter().rev().map(|child_id| (child_id, depth + 1)));
+        }
     }
-    pub fn find_ambiguities<F>()
+    /// Finds children that accept the examples of their siblings. See
+    /// [`AmbiguityChecker`] to spread the work over multiple calls.
+    pub fn find_ambiguities(&self) -> Vec<AmbiguityFinding>
     where
-        F: FnMut(NodeId, NodeId, NodeId, HashSet<Rc<str>>),
+        A: for<'i> ArgumentType<'i, S>,
     {
-        todo!()
+        match AmbiguityChecker::new(self).check_some(self, usize::MAX) {
+            CheckProgress::Finished(findings) => findings,
+            _ => unreachable!("the tree cannot change during the check"),
+        }
     }
-    unsafe fn unchecked_name_of(&mut self, node_id: NodeId, node_type: CommandNodeType) -> Rc<str> {
+    /// Returns example inputs of a node which should parse successfully.
+    pub(crate) fn examples(&self, node_id: NodeId) -> Vec<String>
+    where
+        A: for<'i> ArgumentType<'i, S>,
+    {
+        match self.nodes[node_id].node_type {
+            CommandNodeType::Root => Vec::new(),
+            CommandNodeType::Literal => vec![self.literals[node_id].literal.to_string()],
+            CommandNodeType::Argument => self.arguments[node_id]
+                .argument_type
+                .examples()
+                .iter()
+                .map(|&example| example.to_owned())
+                .collect(),
+        }
+    }
+    /// Whether the node would consume the whole first token of the input.
+    pub(crate) fn is_valid_input<'i>(&self, node_id: NodeId, input: &'i str) -> bool
+    where
+        A: ArgumentType<'i, S>,
+    {
+        match self.nodes[node_id].node_type {
+            CommandNodeType::Root => false,
+            CommandNodeType::Literal => next_token(input) == &*self.literals[node_id].literal,
+            CommandNodeType::Argument => {
+                let mut reader = StringReader::new(input);
+                self.arguments[node_id]
+                    .argument_type
+                    .parse(&mut reader)
+                    .is_ok()
+                    && matches!(reader.peek(), None | Some(ARGUMENT_SEPARATOR))
+            }
+        }
+    }
+    #[allow(dead_code)]
+    unsafe fn unchecked_name_of(
+        &mut self,
+        node_id: NodeId,
+        node_type: CommandNodeType,
+    ) -> Shared<str> {
         match node_type {
             CommandNodeType::Root => self.get_shared_str(""),
-            CommandNodeType::Literal => Rc::clone(&self.literals.get_unchecked(node_id).literal),
-            CommandNodeType::Argument => Rc::clone(&self.arguments.get_unchecked(node_id).name),
+            CommandNodeType::Literal => {
+                Shared::clone(&self.literals.get_unchecked(node_id).literal)
+            }
+            CommandNodeType::Argument => Shared::clone(&self.arguments.get_unchecked(node_id).name),
         }
     }
+    /// Parses the node at the reader's position and records it in the context.
+    pub(crate) fn parse_node<'i>(
+        &self,
+        parent_id: NodeId,
+        node_id: NodeId,
+        reader: &mut StringReader<'i>,
+        context: &mut CommandContextBuilder<'i, S, A>,
+        matching: LiteralMatching,
+    ) -> Result<(), CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let node = &self.nodes[node_id];
+        let start = reader.cursor();
+        match node.node_type {
+            CommandNodeType::Root => return Ok(()),
+            CommandNodeType::Literal => {
+                let literal = &self.literals[node_id];
+                let token = next_token(reader.remaining());
+                let Some(mut mode) = self.match_literal(parent_id, literal, token, matching) else {
+                    return Err(self.literal_incorrect(node_id, reader));
+                };
+                reader.set_cursor(start + token.len());
+                if self.is_alias(node) {
+                    mode = LiteralMatchMode::Alias;
+                }
+                if let Some(typed_literals) = &mut context.typed_literals {
+                    typed_literals.push(TypedLiteral {
+                        node: node_id,
+                        typed: token,
+                        mode,
+                    });
+                }
+            }
+            CommandNodeType::Argument => {
+                let argument = &self.arguments[node_id];
+                let result = argument.argument_type.parse(reader)?;
+                context.with_argument(
+                    Shared::clone(&argument.name),
+                    ParsedArgument {
+                        range: start..reader.cursor(),
+                        result,
+                    },
+                );
+            }
+        }
+        context.with_node(node_id, node, start..reader.cursor());
+        Ok(())
+    }
+    /// Parses the defaults of the optional arguments after the node, for input which
+    /// ends at it. The context runs the command of the last of them, unless the node
+    /// is executable itself.
+    pub(crate) fn parse_defaults<'i>(
+        &self,
+        node_id: NodeId,
+        context: &mut CommandContextBuilder<'i, S, A>,
+    ) -> Result<(), CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        let end = context.range.end;
+        let mut node_id = node_id;
+        while self.nodes[node_id].command.is_none() {
+            let Some((child_id, argument, default)) =
+                self.sorted_children(node_id).into_iter().find_map(|child_id| {
+                    let argument = self.arguments.get(child_id)?;
+                    let usable = self.nodes[child_id].can_use(&context.source);
+                    Some((child_id, argument, argument.default.filter(|_| usable)?))
+                })
+            else {
+                break;
+            };
+            let result = argument.argument_type.parse(&mut StringReader::new(default))?;
+            context.with_argument(
+                Shared::clone(&argument.name),
+                ParsedArgument {
+                    range: end..end,
+                    result,
+                },
+            );
+            context.with_command(self.nodes[child_id].command.clone());
+            node_id = child_id;
+        }
+        Ok(())
+    }
+    /// The error of a literal which doesn't match the input at the reader.
+    pub(crate) fn literal_incorrect<'i>(
+        &self,
+        node_id: NodeId,
+        reader: &StringReader<'i>,
+    ) -> CommandSyntaxError<'i> {
+        CommandSyntaxError::with_context(
+            CommandErrorType::LiteralIncorrect {
+                expected: Shared::clone(&self.literals[node_id].literal),
+            },
+            reader.context(),
+        )
+    }
+    /// Lists the suggestions of a node for the remaining input of the builder.
+    ///
+    /// Suggestions of arguments must lie between the start of the builder and the end
+    /// of the input, others are handled according to the
+    /// [`suggestion_range_check`](Self::suggestion_range_check).
+    pub async fn list_suggestions<'i, 't, 'm>(
+        &self,
+        node_id: NodeId,
+        context: &CommandContext<'i, S, A>,
+        mut builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        match self.nodes[node_id].node_type {
+            CommandNodeType::Root => Ok(Suggestions::empty()),
+            CommandNodeType::Literal => {
+                let literal = &self.literals[node_id];
+                if literal
+                    .literal_lower_case
+                    .starts_with(builder.remaining_lower_case())
+                {
+                    builder.suggest_text(literal.literal.to_string());
+                }
+                Ok(builder.build())
+            }
+            CommandNodeType::Argument => {
+                let argument = &self.arguments[node_id];
+                let allowed = builder.start()..builder.input().len();
+                builder.set_node(Some(node_id));
+                let mut suggestions = match &argument.custom_suggestions {
+                    Some(provider) => {
+                        let input = builder.input();
+                        let type_builder = (argument.suggestion_mode == SuggestionMode::Merge)
+                            .then(|| builder.restart());
+                        let offset = argument.argument_type.suggestion_offset(builder.remaining());
+                        if offset > 0 {
+                            builder = builder.create_offset(builder.start() + offset);
+                        }
+                        let state = self.provider_state(node_id, provider);
+                        let provided = provider
+                            .get_suggestions_with_state(state.as_ref(), context, builder)
+                            .await?;
+                        match type_builder {
+                            Some(type_builder) => {
+                                let own = argument
+                                    .argument_type
+                                    .list_suggestions(context, type_builder)
+                                    .await;
+                                Suggestions::merge(input, [provided, own])
+                            }
+                            None => provided,
+                        }
+                    }
+                    None => {
+                        argument
+                            .argument_type
+                            .list_suggestions(context, builder)
+                            .await
+                    }
+                };
+                let outside = suggestions.remove_outside(allowed.clone());
+                match (self.suggestion_range_check, outside.first()) {
+                    (SuggestionRangeCheck::Strict, Some(suggestion)) => {
+                        Err(CommandSyntaxError::new(
+                            CommandErrorType::DispatcherSuggestionOutOfRange {
+                                range: suggestion.range(),
+                                allowed,
+                            },
+                        ))
+                    }
+                    _ => Ok(suggestions),
+                }
+            }
+        }
+    }
+    fn match_literal(
+        &self,
+        parent_id: NodeId,
+        literal: &LiteralCommandNodeComponent,
+        token: &str,
+        matching: LiteralMatching,
+    ) -> Option<LiteralMatchMode> {
+        if token == &*literal.literal {
+            return Some(LiteralMatchMode::Exact);
+        }
+        if matching.case_insensitive && literal.matches_ignore_case(token) {
+            return Some(LiteralMatchMode::CaseInsensitive);
+        }
+        if matching.unique_prefix
+            && !token.is_empty()
+            && literal.has_prefix(token, matching.case_insensitive)
+        {
+            // Every other literal sibling must not share the prefix
+            let unique = match self.literal_matcher(parent_id) {
+                Some(matcher) if matching.case_insensitive => {
+                    matcher.unique_prefix(token).is_some()
+                }
+                Some(matcher) => {
+                    matcher
+                        .completions(token)
+                        .into_iter()
+                        .filter(|&id| self.literals[id].has_prefix(token, false))
+                        .count()
+                        == 1
+                }
+                None => {
+                    self.nodes[parent_id]
+                        .literals
+                        .values()
+                        .filter(|&&id| {
+                            self.literals[id].has_prefix(token, matching.case_insensitive)
+                        })
+                        .count()
+                        == 1
+                }
+            };
+            if unique {
+                return Some(LiteralMatchMode::UniquePrefix);
+            }
+        }
+        None
+    }
+    /// An alias is a literal that only redirects to another literal.
+    pub(crate) fn is_alias(&self, node: &CommandNodeComponent<S, A>) -> bool {
+        match node.redirect {
+            Some(target) => {
+                node.redirect_modifier.is_none()
+                    && !node.has_children()
+                    && self.literals.contains_key(target)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Result of [`Tree::reachable_from_root_paths`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReachablePaths {
+    /// Distinct paths, each starting at a child of a root.
+    pub paths: Vec<Vec<NodeId>>,
+    /// Whether more paths exist than were requested.
+    pub truncated: bool,
 }
 
-pub struct CommandNodeComponent<'i, S>
+/// Iterator returned by [`Tree::descendants`].
+pub struct Descendants<'t, S, A>
 where
     S: CommandSource,
 {
-    node_type: CommandNodeType,
-    children: HashMap<Rc<str>, NodeId>,
-    literals: HashMap<Rc<str>, NodeId>,
-    arguments: HashMap<Rc<str>, NodeId>,
-    requirement: fn(S) -> bool,
-    redirect: Option<NodeId>,
-    redirect_modifier: Option<RedirectModifier<'i, S>>,
-    forks: bool,
-    command: Option<Command<'i, S>>,
+    tree: &'t Tree<S, A>,
+    stack: Vec<NodeId>,
+    visited: HashSet<NodeId>,
 }
 
-pub type RedirectModifier<'i, S> = fn(&CommandContext<'i, S>) -> Vec<S>;
-
-#[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum CommandNodeType {
-    Root = 0,
-    Argument = 1,
-    Literal = 2,
+impl<S, A> Descendants<'_, S, A>
+where
+    S: CommandSource,
+{
+    fn push_children(&mut self, node_id: NodeId) {
+        let children = self.tree.sorted_children(node_id);
+        self.stack.extend(children.into_iter().rev());
+    }
 }
 
-impl CommandNodeType {
-    pub fn is_valid_input(self) -> bool {
-        match self {
-            Self::Root => false,
-            _ => false,
+impl<S, A> Iterator for Descendants<'_, S, A>
+where
+    S: CommandSource,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node_id = self.stack.pop()?;
+            if self.visited.insert(node_id) {
+                self.push_children(node_id);
+                return Some(node_id);
+            }
         }
     }
 }
 
-pub struct ArgumentCommandNodeComponent<S> {
-    name: Rc<str>,
-    custom_suggestions: S,
+/// Depth-first search over the reverse edges of a tree.
+struct PathSearch<'a, S, A>
+where
+    S: CommandSource,
+{
+    tree: &'a Tree<S, A>,
+    parents: &'a HashMap<NodeId, Vec<NodeId>>,
+    redirected_from: &'a HashMap<NodeId, Vec<NodeId>>,
+    max_paths: usize,
+    /// The current path in reverse order
+    path: Vec<NodeId>,
+    found: HashSet<Vec<NodeId>>,
+    truncated: bool,
 }
 
-pub struct LiteralCommandNodeComponent {
-    literal: Rc<str>,
-    literal_lower_case: Rc<str>,
+impl<S, A> PathSearch<'_, S, A>
+where
+    S: CommandSource,
+{
+    fn visit(&mut self, node_id: NodeId) {
+        if self.truncated || self.path.contains(&node_id) {
+            return;
+        }
+        self.path.push(node_id);
+        for &parent_id in self.parents.get(&node_id).into_iter().flatten() {
+            if self.tree.nodes[parent_id].node_type == CommandNodeType::Root {
+                let path: Vec<_> = self.path.iter().rev().copied().collect();
+                if !self.found.contains(&path) {
+                    if self.found.len() == self.max_paths {
+                        self.truncated = true;
+                        break;
+                    }
+                    self.found.insert(path);
+                }
+            } else {
+                self.visit(parent_id);
+            }
+            // Nodes redirecting to the parent are followed by this node as well
+            for &redirect_id in self.redirected_from.get(&parent_id).into_iter().flatten() {
+                self.visit(redirect_id);
+            }
+        }
+        self.path.pop();
+    }
 }
 
-pub trait TreeNode<'i, S>
+pub struct CommandNodeComponent<S, A>
 where
     S: CommandSource,
 {
-    fn add_to_tree(self, tree: &mut Tree<'i, S>) -> NodeId;
+    node_type: CommandNodeType,
+    literals: HashMap<Shared<str>, NodeId>,
+    arguments: HashMap<Shared<str>, NodeId>,
+    /// Nodes without a requirement can be used by every source.
+    requirement: Option<SharedRequirement<S>>,
+    pub(crate) redirect: Option<NodeId>,
+    pub(crate) redirect_modifier: Option<SourceModifier<S, A>>,
+    pub(crate) forks: bool,
+    pub(crate) command: Option<NodeCommand<S, A>>,
+    pub(crate) description: Option<Shared<str>>,
+    pub(crate) keywords: Vec<Shared<str>>,
+    pub(crate) sensitive: bool,
+    /// The parent the node was first attached to.
+    parent: Option<NodeId>,
 }
 
-pub struct RootCommandNode;
+impl<S, A> fmt::Debug for CommandNodeComponent<S, A>
+where
+    S: CommandSource,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandNodeComponent")
+            .field("node_type", &self.node_type)
+            .field("executable", &self.command.is_some())
+            .field("redirect", &self.redirect)
+            .field("children", &(self.literals.len() + self.arguments.len()))
+            .finish_non_exhaustive()
+    }
+}
 
-impl<'i, S> TreeNode<'i, S> for RootCommandNode
+impl<S, A> CommandNodeComponent<S, A>
 where
     S: CommandSource,
 {
-    fn add_to_tree(self, tree: &mut Tree<'i, S>) -> NodeId {
-        tree.nodes.insert(CommandNodeComponent {
-            node_type: CommandNodeType::Root,
-            children: HashMap::new(),
+    pub(crate) fn new(
+        node_type: CommandNodeType,
+        requirement: Option<SharedRequirement<S>>,
+        redirect: Option<NodeId>,
+        redirect_modifier: Option<SourceModifier<S, A>>,
+        forks: bool,
+        command: Option<NodeCommand<S, A>>,
+    ) -> Self {
+        Self {
+            node_type,
             literals: HashMap::new(),
             arguments: HashMap::new(),
-            requirement: tautology_predicate,
-            redirect: None,
-            redirect_modifier: Some(|ctx| vec![ctx.source.clone()]),
-            forks: false,
-            command: None,
-        })
+            requirement,
+            redirect,
+            redirect_modifier,
+            forks,
+            command,
+            description: None,
+            keywords: Vec::new(),
+            sensitive: false,
+            parent: None,
+        }
+    }
+    #[inline]
+    pub fn node_type(&self) -> CommandNodeType {
+        self.node_type
+    }
+    #[inline]
+    pub fn is_root(&self) -> bool {
+        self.node_type == CommandNodeType::Root
+    }
+    #[inline]
+    pub fn can_use(&self, source: &S) -> bool {
+        self.requirement
+            .as_ref()
+            .is_none_or(|requirement| requirement.test(source))
+    }
+    /// The literal children of the node.
+    pub(crate) fn literals(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.literals.values().copied()
+    }
+    /// The literal child whose name is exactly `token`.
+    #[inline]
+    pub fn find_literal(&self, token: &str) -> Option<NodeId> {
+        self.literals.get(token).copied()
+    }
+    /// The literal and argument children of the node with their names.
+    pub(crate) fn children(&self) -> impl Iterator<Item = (&Shared<str>, NodeId)> + '_ {
+        self.literals
+            .iter()
+            .chain(&self.arguments)
+            .map(|(name, &child_id)| (name, child_id))
+    }
+    /// The child with the given name. A literal takes precedence over an argument with
+    /// the same name.
+    pub(crate) fn child(&self, name: &str) -> Option<NodeId> {
+        self.literals
+            .get(name)
+            .or_else(|| self.arguments.get(name))
+            .copied()
+    }
+    #[inline]
+    pub(crate) fn has_children(&self) -> bool {
+        !self.literals.is_empty() || !self.arguments.is_empty()
+    }
+    /// The command which is run when the input ends at this node.
+    #[inline]
+    pub fn command(&self) -> Option<NodeCommand<S, A>> {
+        self.command.clone()
+    }
+    /// Whether the input can end at this node.
+    #[inline]
+    pub fn is_executable(&self) -> bool {
+        self.command.is_some()
+    }
+    /// The node the rest of the input is parsed by instead of the children.
+    #[inline]
+    pub fn redirect(&self) -> Option<NodeId> {
+        self.redirect
+    }
+    /// Whether the redirect runs the rest of the command for each source its modifier
+    /// returns, ignoring failures, see
+    /// [`fork`](crate::builder::ArgumentBuilder::fork).
+    #[inline]
+    pub fn is_fork(&self) -> bool {
+        self.forks
     }
+    /// A description of the node for users, e.g. shown when hovering over it.
+    #[inline]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    /// Search keywords of the node, without the ones inherited from its ancestors.
+    #[inline]
+    pub fn keywords(&self) -> &[Shared<str>] {
+        &self.keywords
+    }
+    /// Whether the input of the node must not be recorded, see
+    /// `SuggestionHistory` (`history` feature).
+    #[inline]
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+    /// Whether the node was given a requirement, see
+    /// [`requires`](crate::builder::ArgumentBuilder::requires).
+    #[inline]
+    pub fn is_restricted(&self) -> bool {
+        self.requirement.is_some()
+    }
+}
+
+/// Returns the sources the rest of a redirected command runs with, e.g. every entity
+/// selected by `/execute as @e`. Closures may capture state.
+#[cfg(feature = "sync")]
+pub type RedirectModifier<S, A> = Shared<
+    dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<Vec<S>, CommandSyntaxError<'i>>
+        + Send
+        + Sync,
+>;
+/// Returns the sources the rest of a redirected command runs with, e.g. every entity
+/// selected by `/execute as @e`. Closures may capture state.
+#[cfg(not(feature = "sync"))]
+pub type RedirectModifier<S, A> =
+    Shared<dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<Vec<S>, CommandSyntaxError<'i>>>;
+/// Replaces the source for the rest of a redirected command, like
+/// `|context| Ok(context.source().as_player())`. Closures may capture state.
+#[cfg(feature = "sync")]
+pub type SingleRedirectModifier<S, A> = Shared<
+    dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<S, CommandSyntaxError<'i>> + Send + Sync,
+>;
+/// Replaces the source for the rest of a redirected command, like
+/// `|context| Ok(context.source().as_player())`. Closures may capture state.
+#[cfg(not(feature = "sync"))]
+pub type SingleRedirectModifier<S, A> =
+    Shared<dyn for<'i> Fn(&CommandContext<'i, S, A>) -> Result<S, CommandSyntaxError<'i>>>;
+
+/// The modifier of a redirect, which decides the sources of the rest of the command.
+pub enum SourceModifier<S, A>
+where
+    S: CommandSource,
+{
+    Single(SingleRedirectModifier<S, A>),
+    Multiple(RedirectModifier<S, A>),
+}
+
+impl<S, A> SourceModifier<S, A>
+where
+    S: CommandSource,
+{
+    /// Returns the sources the rest of the command runs with.
+    pub fn apply<'i>(
+        &self,
+        context: &CommandContext<'i, S, A>,
+    ) -> Result<Vec<S>, CommandSyntaxError<'i>>
+    where
+        A: ArgumentType<'i, S>,
+    {
+        match self {
+            Self::Single(modifier) => modifier(context).map(|source| vec![source]),
+            Self::Multiple(modifier) => modifier(context),
+        }
+    }
+}
+
+impl<S, A> Clone for SourceModifier<S, A>
+where
+    S: CommandSource,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Single(modifier) => Self::Single(Shared::clone(modifier)),
+            Self::Multiple(modifier) => Self::Multiple(Shared::clone(modifier)),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandNodeType {
+    Root = 0,
+    Argument = 1,
+    Literal = 2,
+}
+
+pub struct ArgumentCommandNodeComponent<S, A>
+where
+    S: CommandSource,
+{
+    name: Shared<str>,
+    argument_type: A,
+    custom_suggestions: Option<SharedSuggestionProvider<S, A>>,
+    suggestion_mode: SuggestionMode,
+    default: Option<&'static str>,
 }
 
-pub struct ArgumentCommandNode<'a, 'i, 't, 'm, S>
+impl<S, A> ArgumentCommandNodeComponent<S, A>
 where
     S: CommandSource,
 {
-    name: &'a str,
-    argument_type: ArgumentType,
-    command: Option<Command<'i, S>>,
-    requirement: fn(S) -> bool,
-    redirect: Option<NodeId>,
-    modifier: Option<RedirectModifier<'i, S>>,
-    forks: bool,
-    custom_suggestions: Option<SuggestionProvider<'i, 't, 'm, S>>,
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    #[inline]
+    pub fn argument_type(&self) -> &A {
+        &self.argument_type
+    }
+    #[inline]
+    pub fn custom_suggestions(&self) -> Option<&SharedSuggestionProvider<S, A>> {
+        self.custom_suggestions.as_ref()
+    }
+    #[inline]
+    pub fn suggestion_mode(&self) -> SuggestionMode {
+        self.suggestion_mode
+    }
+    /// See [`Tree::argument_default`].
+    #[inline]
+    pub fn default(&self) -> Option<&'static str> {
+        self.default
+    }
 }
 
-pub enum ArgumentType {
+pub struct LiteralCommandNodeComponent {
+    literal: Shared<str>,
+    literal_lower_case: Shared<str>,
+}
 
+impl LiteralCommandNodeComponent {
+    #[inline]
+    pub fn literal(&self) -> &str {
+        &self.literal
+    }
+    fn matches_ignore_case(&self, token: &str) -> bool {
+        let mut literal = self.literal_lower_case.chars();
+        token
+            .chars()
+            .flat_map(char::to_lowercase)
+            .all(|c| literal.next() == Some(c))
+            && literal.next().is_none()
+    }
+    fn has_prefix(&self, token: &str, case_insensitive: bool) -> bool {
+        if case_insensitive {
+            let mut literal = self.literal_lower_case.chars();
+            token
+                .chars()
+                .flat_map(char::to_lowercase)
+                .all(|c| literal.next() == Some(c))
+        } else {
+            self.literal.starts_with(token)
+        }
+    }
 }
 
-pub struct LiteralCommandNode<'a, 'i, S>
+/// How literals are matched against the input. By default, literals must be typed
+/// exactly like vanilla Minecraft does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiteralMatching {
+    /// Accept literals typed in any case.
+    pub case_insensitive: bool,
+    /// Accept a prefix of a literal if no sibling literal shares it, like `tele` for
+    /// `teleport`.
+    pub unique_prefix: bool,
+}
+
+/// The way a literal was matched against the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiteralMatchMode {
+    Exact,
+    CaseInsensitive,
+    UniquePrefix,
+    /// The literal redirects to another literal. This takes precedence over the
+    /// other modes.
+    Alias,
+}
+
+/// A literal as it was typed by the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedLiteral<'i> {
+    pub node: NodeId,
+    pub typed: &'i str,
+    pub mode: LiteralMatchMode,
+}
+
+/// The index of the node in the slot map, which is unique among the nodes of a tree.
+fn tree_id(node_id: NodeId) -> String {
+    format!("#{}", node_id.0.as_ffi() as u32)
+}
+
+fn dot_id(node_id: NodeId) -> String {
+    format!("n{}", node_id.0.as_ffi())
+}
+
+/// Returns the input up to the next argument separator.
+fn next_token(input: &str) -> &str {
+    match input.find(ARGUMENT_SEPARATOR) {
+        Some(end) => &input[..end],
+        None => input,
+    }
+}
+
+/// Whether [`str::to_lowercase`] would return the string unchanged.
+fn is_lower_case(string: &str) -> bool {
+    string
+        .chars()
+        .all(|c| c.to_lowercase().eq(std::iter::once(c)))
+}
+
+pub trait TreeNode<S, A>
 where
     S: CommandSource,
 {
-    literal: &'a str,
-    command: Option<Command<'i, S>>,
-    requirement: fn(S) -> bool,
-    redirect: Option<NodeId>,
-    modifier: Option<RedirectModifier<'i, S>>,
-    forks: bool,
+    fn add_to_tree(self, tree: &mut Tree<S, A>) -> NodeId;
+    /// Adds the node as a child of the parent and returns the id of the attached node,
+    /// see [`Tree::add_child`].
+    fn add_to_parent(self, tree: &mut Tree<S, A>, parent_id: NodeId) -> NodeId
+    where
+        Self: Sized,
+    {
+        let node_id = tree.add_node(self);
+        tree.add_child(parent_id, node_id).unwrap()
+    }
 }
 
-/// A predicate that always returns `true` for any argument.
-fn tautology_predicate<T>(_: T) -> bool {
-    true
+pub struct RootCommandNode;
+
+impl<S, A> TreeNode<S, A> for RootCommandNode
+where
+    S: CommandSource,
+{
+    fn add_to_tree(self, tree: &mut Tree<S, A>) -> NodeId {
+        tree.nodes.insert(CommandNodeComponent::new(
+            CommandNodeType::Root,
+            None,
+            None,
+            None,
+            false,
+            None,
+        ))
+    }
 }
@@ -0,0 +1,112 @@
+//! A lowercase trie over the literal children of a node, answering exact, unique prefix
+//! and completion queries without scanning every sibling.
+
+use super::CommandNodeId;
+
+/// A trie over literal names, keyed by their lowercase form.
+///
+/// All queries ignore case, callers which match case-sensitively filter the returned
+/// nodes against the original literals.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Edges sorted by their character.
+    edges: Vec<(char, usize)>,
+    /// Literals ending at this node.
+    literals: Vec<CommandNodeId>,
+    /// Number of literals ending at this node or below.
+    count: usize,
+}
+
+impl Default for LiteralMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiteralMatcher {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+    /// Adds a literal. Different literals may share the same lowercase key.
+    pub fn insert(&mut self, literal: &str, node_id: CommandNodeId) {
+        let mut index = 0;
+        self.nodes[index].count += 1;
+        for c in literal.chars().flat_map(char::to_lowercase) {
+            index = match self.nodes[index]
+                .edges
+                .binary_search_by_key(&c, |&(c, _)| c)
+            {
+                Ok(edge) => self.nodes[index].edges[edge].1,
+                Err(edge) => {
+                    let next = self.nodes.len();
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[index].edges.insert(edge, (c, next));
+                    next
+                }
+            };
+            self.nodes[index].count += 1;
+        }
+        self.nodes[index].literals.push(node_id);
+    }
+    /// The number of literals in the matcher.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes[0].count
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Literals which are equal to `token` ignoring case.
+    pub fn exact(&self, token: &str) -> &[CommandNodeId] {
+        match self.find(token) {
+            Some(index) => &self.nodes[index].literals,
+            None => &[],
+        }
+    }
+    /// The literal starting with `prefix` ignoring case, if there is exactly one.
+    pub fn unique_prefix(&self, prefix: &str) -> Option<CommandNodeId> {
+        let mut index = self.find(prefix)?;
+        if self.nodes[index].count != 1 {
+            return None;
+        }
+        loop {
+            let node = &self.nodes[index];
+            if let Some(&node_id) = node.literals.first() {
+                return Some(node_id);
+            }
+            index = node.edges[0].1;
+        }
+    }
+    /// All literals starting with `prefix` ignoring case, ordered by their lowercase
+    /// names.
+    pub fn completions(&self, prefix: &str) -> Vec<CommandNodeId> {
+        let Some(index) = self.find(prefix) else {
+            return Vec::new();
+        };
+        let mut result = Vec::with_capacity(self.nodes[index].count);
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            result.extend_from_slice(&node.literals);
+            stack.extend(node.edges.iter().rev().map(|&(_, next)| next));
+        }
+        result
+    }
+    fn find(&self, key: &str) -> Option<usize> {
+        let mut index = 0;
+        for c in key.chars().flat_map(char::to_lowercase) {
+            let edges = &self.nodes[index].edges;
+            let edge = edges.binary_search_by_key(&c, |&(c, _)| c).ok()?;
+            index = edges[edge].1;
+        }
+        Some(index)
+    }
+}
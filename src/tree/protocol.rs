@@ -0,0 +1,160 @@
+//! Conversion of a [`Tree`] into the nodes of Minecraft's commands packet, which
+//! clients use to parse and suggest commands themselves.
+
+use super::{CommandNodeId, CommandNodeType, Tree};
+use crate::{
+    arguments::{ArgCapabilities, ArgumentType},
+    CommandSource,
+};
+
+/// The node type bits of [`ProtocolNode::flags`].
+pub const NODE_TYPE_MASK: u8 = 0x03;
+pub const NODE_TYPE_ROOT: u8 = 0x00;
+pub const NODE_TYPE_LITERAL: u8 = 0x01;
+pub const NODE_TYPE_ARGUMENT: u8 = 0x02;
+/// Set if the node has a command.
+pub const FLAG_EXECUTABLE: u8 = 0x04;
+/// Set if the node redirects to [`ProtocolNode::redirect`].
+pub const FLAG_REDIRECT: u8 = 0x08;
+/// Set if the node has a [`ProtocolNode::suggestions_type`].
+pub const FLAG_SUGGESTIONS_TYPE: u8 = 0x10;
+
+/// The suggestions type of arguments with custom suggestions, which the client has to
+/// request from the server.
+pub const ASK_SERVER_SUGGESTIONS: &str = "minecraft:ask_server";
+
+/// The parser sent for argument types which clients can't parse themselves, so that
+/// they can still type a value which the server then parses.
+pub const FALLBACK_PARSER: &str = "brigadier:string";
+
+/// A node as sent in the commands packet. Nodes refer to each other by their index,
+/// the root is the first node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolNode {
+    pub flags: u8,
+    pub children: Vec<usize>,
+    pub redirect: Option<usize>,
+    /// The literal or the name of the argument, `None` for the root
+    pub name: Option<String>,
+    /// The parser of an argument node
+    pub parser: Option<ProtocolParser>,
+    pub suggestions_type: Option<String>,
+}
+
+/// The parser of an argument node, see [`ArgumentType::protocol_id`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolParser {
+    pub id: &'static str,
+    pub properties: Vec<u8>,
+}
+
+impl ProtocolNode {
+    /// The node type, one of the `NODE_TYPE_*` constants.
+    #[inline]
+    pub fn node_type(&self) -> u8 {
+        self.flags & NODE_TYPE_MASK
+    }
+    #[inline]
+    pub fn is_executable(&self) -> bool {
+        self.flags & FLAG_EXECUTABLE != 0
+    }
+}
+
+/// Converts the nodes the source can use, starting at the root, into the nodes of the
+/// commands packet.
+///
+/// Like the vanilla server, redirect targets are sent even if the source can't use
+/// them, since clients need them to resolve redirects. Argument types without
+/// [`ArgCapabilities::CLIENT_SIDE`] are sent as the [`FALLBACK_PARSER`] with
+/// suggestions asked from the server. Other types without a
+/// [`protocol_id`](ArgumentType::protocol_id) can't be parsed by clients and are left
+/// out together with their children and the redirects to them.
+pub fn to_protocol_nodes<S, A>(
+    tree: &Tree<S, A>,
+    root: CommandNodeId,
+    source: &S,
+) -> Vec<ProtocolNode>
+where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    let has_parser = |node_id: CommandNodeId| {
+        tree.argument_type(node_id)
+            .is_none_or(|argument_type| parser::<S, A>(argument_type).is_some())
+    };
+    let children = |node_id: CommandNodeId| {
+        tree.sorted_children(node_id)
+            .into_iter()
+            .filter(|&child_id| tree.nodes[child_id].can_use(source) && has_parser(child_id))
+    };
+    let redirect =
+        |node_id: CommandNodeId| tree.nodes[node_id].redirect.filter(|&id| has_parser(id));
+    let (order, indices) =
+        tree.breadth_first_indices(root, |node_id| children(node_id).chain(redirect(node_id)));
+    order
+        .into_iter()
+        .map(|node_id| {
+            let node = &tree.nodes[node_id];
+            let redirect = redirect(node_id).map(|redirect| indices[&redirect]);
+            let mut flags = match node.node_type {
+                CommandNodeType::Root => NODE_TYPE_ROOT,
+                CommandNodeType::Literal => NODE_TYPE_LITERAL,
+                CommandNodeType::Argument => NODE_TYPE_ARGUMENT,
+            };
+            if node.command.is_some() {
+                flags |= FLAG_EXECUTABLE;
+            }
+            if redirect.is_some() {
+                flags |= FLAG_REDIRECT;
+            }
+            let mut parser = None;
+            let mut suggestions_type = None;
+            if let Some(argument) = tree.arguments.get(node_id) {
+                let argument_type = &argument.argument_type;
+                parser = self::parser::<S, A>(argument_type);
+                let client_side = ArgumentType::<S>::capabilities(argument_type)
+                    .contains(ArgCapabilities::CLIENT_SIDE);
+                if argument.custom_suggestions.is_some() || !client_side {
+                    flags |= FLAG_SUGGESTIONS_TYPE;
+                    suggestions_type = Some(ASK_SERVER_SUGGESTIONS.to_owned());
+                }
+            }
+            ProtocolNode {
+                flags,
+                children: children(node_id)
+                    .map(|child_id| indices[&child_id])
+                    .collect(),
+                redirect,
+                name: (node.node_type != CommandNodeType::Root)
+                    .then(|| tree.name(node_id).unwrap().to_owned()),
+                parser,
+                suggestions_type,
+            }
+        })
+        .collect()
+}
+
+/// Returns the parser of the argument type, or `None` if clients can't parse it.
+fn parser<S, A>(argument_type: &A) -> Option<ProtocolParser>
+where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    if !ArgumentType::<S>::capabilities(argument_type).contains(ArgCapabilities::CLIENT_SIDE) {
+        // The string parser's mode: a single word or the rest of the input
+        let mode = if ArgumentType::<S>::is_greedy(argument_type) {
+            2
+        } else {
+            0
+        };
+        return Some(ProtocolParser {
+            id: FALLBACK_PARSER,
+            properties: vec![mode],
+        });
+    }
+    ArgumentType::<S>::protocol_id(argument_type).map(|id| {
+        let mut properties = Vec::new();
+        ArgumentType::<S>::write_protocol_properties(argument_type, &mut properties);
+        ProtocolParser { id, properties }
+    })
+}
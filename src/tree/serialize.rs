@@ -0,0 +1,101 @@
+//! Conversion of a [`Tree`] into serializable data, e.g. to send it to clients or to
+//! compare trees.
+
+use serde::Serialize;
+
+use super::{CommandNodeId, CommandNodeType, Tree};
+use crate::CommandSource;
+
+/// The nodes of a tree. Nodes refer to each other by their index, the root is the
+/// first node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreeDto {
+    pub nodes: Vec<NodeDto>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeDto {
+    pub kind: NodeKind,
+    /// The literal or the name of the argument, empty for the root
+    pub name: String,
+    pub executable: bool,
+    pub children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argument_type: Option<ArgumentTypeDescriptor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Root,
+    Literal,
+    Argument,
+}
+
+impl From<CommandNodeType> for NodeKind {
+    fn from(node_type: CommandNodeType) -> Self {
+        match node_type {
+            CommandNodeType::Root => Self::Root,
+            CommandNodeType::Literal => Self::Literal,
+            CommandNodeType::Argument => Self::Argument,
+        }
+    }
+}
+
+/// Describes an argument type, like the parser id and properties that Minecraft sends.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArgumentTypeDescriptor {
+    pub parser: String,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Converts the nodes reachable from the root into serializable data. Argument types
+/// describe themselves with `describe`.
+pub fn to_dto<S, A>(
+    tree: &Tree<S, A>,
+    root: CommandNodeId,
+    describe: impl Fn(&A) -> ArgumentTypeDescriptor,
+) -> TreeDto
+where
+    S: CommandSource,
+{
+    let (order, indices) = tree.breadth_first_indices(root, |node_id| {
+        let mut next = tree.sorted_children(node_id);
+        next.extend(tree.nodes[node_id].redirect);
+        next
+    });
+    let nodes = order
+        .into_iter()
+        .map(|node_id| {
+            let node = &tree.nodes[node_id];
+            NodeDto {
+                kind: node.node_type.into(),
+                name: tree.name(node_id).unwrap_or_default().to_owned(),
+                executable: node.command.is_some(),
+                children: tree
+                    .sorted_children(node_id)
+                    .iter()
+                    .map(|child_id| indices[child_id])
+                    .collect(),
+                redirect: node.redirect.map(|redirect| indices[&redirect]),
+                argument_type: tree.argument_type(node_id).map(&describe),
+            }
+        })
+        .collect();
+    TreeDto { nodes }
+}
+
+/// Converts the nodes reachable from the root into JSON, see [`to_dto`].
+pub fn to_json_value<S, A>(
+    tree: &Tree<S, A>,
+    root: CommandNodeId,
+    describe: impl Fn(&A) -> ArgumentTypeDescriptor,
+) -> serde_json::Value
+where
+    S: CommandSource,
+{
+    serde_json::to_value(to_dto(tree, root, describe)).expect("tree data is valid JSON")
+}
@@ -0,0 +1,338 @@
+//! A canonical text form of a [`Tree`], meant to be committed as a golden file so that
+//! tests notice when commands change unexpectedly.
+//!
+//! Every node is one line, indented by two spaces per level:
+//!
+//! ```text
+//! root
+//!   literal speed executes "Changes the walking speed"
+//!     argument value: double(0..=10) executes restricted
+//!   literal sp -> /speed
+//! ```
+//!
+//! A line starts with the kind and the name of the node, followed by the description
+//! of the argument type, its default, the flags `executes`, `forks`, `modifies`,
+//! `restricted` and `sensitive`, the keywords, the redirect as the path of its target
+//! and the description. Children are ordered by name, a literal before an argument
+//! with the same name. A node reachable through several parents is only expanded the
+//! first time, later lines refer to it with `= <path>`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::{CommandNodeId, CommandNodeType, Tree};
+use crate::CommandSource;
+
+impl<S, A> Tree<S, A>
+where
+    S: CommandSource,
+{
+    /// Renders the nodes reachable from the root in the [canonical form](self).
+    /// Argument types describe themselves with `describe`. The result contains no ids
+    /// or addresses and only changes when the commands do.
+    pub fn canonical_description(
+        &self,
+        root: CommandNodeId,
+        describe: impl Fn(&A) -> String,
+    ) -> String {
+        let mut paths = HashMap::new();
+        self.collect_paths(root, String::new(), &mut paths);
+        let mut out = String::new();
+        let mut expanded = HashSet::new();
+        self.describe_node(root, 0, &paths, &mut expanded, &describe, &mut out);
+        out
+    }
+    /// Finds the path of every node the first time it's reached in canonical order.
+    fn collect_paths(
+        &self,
+        node_id: CommandNodeId,
+        path: String,
+        paths: &mut HashMap<CommandNodeId, String>,
+    ) {
+        if paths.contains_key(&node_id) {
+            return;
+        }
+        paths.insert(node_id, path.clone());
+        for child_id in self.sorted_children(node_id) {
+            let name = self.name(child_id).unwrap_or_default();
+            self.collect_paths(child_id, format!("{path}/{name}"), paths);
+        }
+    }
+    fn describe_node(
+        &self,
+        node_id: CommandNodeId,
+        depth: usize,
+        paths: &HashMap<CommandNodeId, String>,
+        expanded: &mut HashSet<CommandNodeId>,
+        describe: &impl Fn(&A) -> String,
+        out: &mut String,
+    ) {
+        let node = &self.nodes[node_id];
+        let name = self.name(node_id).unwrap_or_default();
+        let indent = "  ".repeat(depth);
+        match node.node_type {
+            CommandNodeType::Root => out.push_str("root"),
+            CommandNodeType::Literal => write!(out, "{indent}literal {name}").unwrap(),
+            CommandNodeType::Argument => write!(out, "{indent}argument {name}").unwrap(),
+        }
+        if !expanded.insert(node_id) {
+            writeln!(out, " = {}", path_or_root(&paths[&node_id])).unwrap();
+            return;
+        }
+        if let Some(argument_type) = self.argument_type(node_id) {
+            write!(out, ": {}", describe(argument_type)).unwrap();
+        }
+        if let Some(default) = self.argument_default(node_id) {
+            write!(out, " default {default:?}").unwrap();
+        }
+        let flags = [
+            (node.command.is_some(), "executes"),
+            (node.forks, "forks"),
+            (node.redirect_modifier.is_some(), "modifies"),
+            (node.is_restricted(), "restricted"),
+            (node.sensitive, "sensitive"),
+        ];
+        for (_, flag) in flags.into_iter().filter(|&(set, _)| set) {
+            write!(out, " {flag}").unwrap();
+        }
+        if !node.keywords.is_empty() {
+            write!(out, " keywords({})", node.keywords.join(", ")).unwrap();
+        }
+        if let Some(redirect) = node.redirect {
+            match paths.get(&redirect) {
+                Some(path) => write!(out, " -> {}", path_or_root(path)).unwrap(),
+                None => out.push_str(" -> ?"),
+            }
+        }
+        if let Some(description) = &node.description {
+            write!(out, " {description:?}").unwrap();
+        }
+        out.push('\n');
+        for child_id in self.sorted_children(node_id) {
+            self.describe_node(child_id, depth + 1, paths, expanded, describe, out);
+        }
+    }
+}
+
+fn path_or_root(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+/// Compares the canonical description of a tree with a golden file, see
+/// [`assert_matches_snapshot`].
+///
+/// ```ignore
+/// let tree = dispatcher.tree();
+/// assert_tree_matches_snapshot!(tree, dispatcher.root(), describe, "tests/golden/tree.snap");
+/// ```
+#[macro_export]
+macro_rules! assert_tree_matches_snapshot {
+    ($tree:expr, $root:expr, $describe:expr, $path:expr $(,)?) => {
+        $crate::tree::snapshot::assert_matches_snapshot(
+            &$tree.canonical_description($root, $describe),
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path),
+        )
+    };
+}
+
+/// The environment variable which makes [`assert_matches_snapshot`] write the golden
+/// file instead of comparing with it.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "BRIGADIER_UPDATE_SNAPSHOTS";
+
+/// Compares the description with the golden file at `path`, panicking with a
+/// [`unified_diff`] if they differ. If the [`UPDATE_SNAPSHOTS_VAR`] is set, the file
+/// is written instead.
+#[track_caller]
+pub fn assert_matches_snapshot(description: &str, path: impl AsRef<std::path::Path>) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() {
+        std::fs::write(path, description)
+            .unwrap_or_else(|error| panic!("Can't write {}: {error}", path.display()));
+        return;
+    }
+    let expected = std::fs::read_to_string(path).unwrap_or_else(|error| {
+        panic!(
+            "Can't read {}: {error}\nRun with {UPDATE_SNAPSHOTS_VAR}=1 to create it",
+            path.display()
+        )
+    });
+    if expected != description {
+        panic!(
+            "The tree doesn't match {}, run with {UPDATE_SNAPSHOTS_VAR}=1 if the change is \
+             intended\n{}",
+            path.display(),
+            unified_diff(&expected, description)
+        );
+    }
+}
+
+/// Lines of context around changes in a [`unified_diff`].
+const DIFF_CONTEXT: usize = 3;
+
+/// Renders the line differences of two canonical descriptions in the unified format.
+/// Instead of a function name, the header of each hunk names the path of the first
+/// changed node.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    let edits = diff_lines(&old, &new);
+    let old_paths = line_paths(&old);
+    let new_paths = line_paths(&new);
+
+    let mut out = String::from("--- snapshot\n+++ tree\n");
+    let mut index = 0;
+    while let Some(first_change) = edits[index..]
+        .iter()
+        .position(|edit| !matches!(edit, Edit::Keep(..)))
+    {
+        let change = index + first_change;
+        let start = change.saturating_sub(DIFF_CONTEXT).max(index);
+        // A hunk ends once there are more than twice the context lines without changes
+        let mut end = change;
+        let mut unchanged = 0;
+        for (i, edit) in edits.iter().enumerate().skip(change) {
+            if matches!(edit, Edit::Keep(..)) {
+                unchanged += 1;
+                if unchanged > 2 * DIFF_CONTEXT {
+                    break;
+                }
+            } else {
+                unchanged = 0;
+                end = i;
+            }
+        }
+        let end = (end + 1 + DIFF_CONTEXT).min(edits.len());
+        let hunk = &edits[start..end];
+        let old_start = edits[..start].iter().filter(|edit| edit.in_old()).count();
+        let new_start = edits[..start].iter().filter(|edit| edit.in_new()).count();
+        let old_len = hunk.iter().filter(|edit| edit.in_old()).count();
+        let new_len = hunk.iter().filter(|edit| edit.in_new()).count();
+        let path = match edits[change] {
+            Edit::Delete(i) => &old_paths[i],
+            Edit::Insert(j) | Edit::Keep(_, j) => &new_paths[j],
+        };
+        writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@ {}",
+            hunk_start(old_start, old_len),
+            hunk_start(new_start, new_len),
+            path_or_root(path)
+        )
+        .unwrap();
+        for edit in hunk {
+            match *edit {
+                Edit::Keep(i, _) => writeln!(out, " {}", old[i]),
+                Edit::Delete(i) => writeln!(out, "-{}", old[i]),
+                Edit::Insert(j) => writeln!(out, "+{}", new[j]),
+            }
+            .unwrap();
+        }
+        index = end;
+    }
+    out
+}
+
+/// A line of a diff, with its index in the old and the new lines.
+#[derive(Debug, Clone, Copy)]
+enum Edit {
+    Keep(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+impl Edit {
+    fn in_old(self) -> bool {
+        !matches!(self, Self::Insert(_))
+    }
+    fn in_new(self) -> bool {
+        !matches!(self, Self::Delete(_))
+    }
+}
+
+/// The first line of a hunk, counted from 1. Empty ranges name the line before them.
+fn hunk_start(start: usize, len: usize) -> usize {
+    if len == 0 {
+        start
+    } else {
+        start + 1
+    }
+}
+
+/// Finds the longest common subsequence of the lines, after skipping the common
+/// prefix and suffix which are all lines but a few for typical changes.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    // lengths[i][j] is the length of the common subsequence of old_mid[i..] and
+    // new_mid[j..]
+    let width = new_mid.len() + 1;
+    let mut lengths = vec![0u32; (old_mid.len() + 1) * width];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lengths[i * width + j] = if old_mid[i] == new_mid[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Keep(i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            edits.push(Edit::Keep(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len()
+            || (i < old_mid.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            edits.push(Edit::Delete(prefix + i));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(prefix + j));
+            j += 1;
+        }
+    }
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    edits.extend((0..suffix).map(|k| Edit::Keep(old_end + k, new_end + k)));
+    edits
+}
+
+/// The path of the node on each line of a canonical description, reconstructed from
+/// the indentation.
+fn line_paths(lines: &[&str]) -> Vec<String> {
+    let mut names: Vec<&str> = Vec::new();
+    lines
+        .iter()
+        .map(|line| {
+            let text = line.trim_start_matches(' ');
+            let depth = (line.len() - text.len()) / 2;
+            let mut words = text.split(' ');
+            let name = match words.next() {
+                Some("root") | None => None,
+                Some(_) => words.next(),
+            };
+            names.truncate(depth.saturating_sub(1));
+            if let Some(name) = name {
+                // The type of arguments follows their name after a colon
+                names.push(name.trim_end_matches(':'));
+            }
+            names.iter().map(|name| format!("/{name}")).collect()
+        })
+        .collect()
+}
@@ -0,0 +1,163 @@
+//! Checks for mistakes in a tree which parsing doesn't report, like argument names
+//! that overwrite each other.
+
+use std::collections::HashSet;
+
+use super::{CommandNodeId, CommandNodeType, Tree};
+use crate::{arguments::ArgumentType, CommandSource};
+
+/// At most this many nodes are visited while walking the paths of a tree, so that
+/// large shared subtrees can't make the validation take exponential time.
+const MAX_VALIDATION_VISITS: usize = 1 << 16;
+
+/// A mistake found by [`Tree::validate`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Both arguments are on one path, the value of the second one replaces the first.
+    #[error("The argument name '{name}' is used twice on one path")]
+    DuplicateArgumentName {
+        name: String,
+        first: CommandNodeId,
+        second: CommandNodeId,
+    },
+    /// An argument without a command, children or redirect can't be executed.
+    #[error("The argument '{name}' has neither a command nor children")]
+    DeadEnd { node: CommandNodeId, name: String },
+    /// The child of an argument which consumes the rest of the input.
+    #[error("'{child_name}' can never match, because '{greedy_name}' consumes the rest of the input")]
+    UnreachableChild {
+        greedy: CommandNodeId,
+        greedy_name: String,
+        child: CommandNodeId,
+        child_name: String,
+    },
+    #[error("A node redirects to a node which was removed")]
+    DanglingRedirect { node: CommandNodeId },
+}
+
+impl ValidationError {
+    /// The nodes involved in the mistake.
+    pub fn nodes(&self) -> Vec<CommandNodeId> {
+        match *self {
+            Self::DuplicateArgumentName { first, second, .. } => vec![first, second],
+            Self::DeadEnd { node, .. } | Self::DanglingRedirect { node } => vec![node],
+            Self::UnreachableChild { greedy, child, .. } => vec![greedy, child],
+        }
+    }
+}
+
+impl<S, A> Tree<S, A>
+where
+    S: CommandSource,
+    A: for<'i> ArgumentType<'i, S>,
+{
+    /// Reports mistakes in the tree:
+    /// - argument names used twice on a path from a root, arguments after a redirect
+    ///   are in a context of their own and don't clash with the ones before,
+    /// - arguments which are neither executable nor continue the command,
+    /// - children of arguments which consume the rest of the input,
+    /// - redirects to removed nodes.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        for (node_id, node) in &self.nodes {
+            if let Some(redirect) = node.redirect {
+                if !self.nodes.contains_key(redirect) {
+                    errors.push(ValidationError::DanglingRedirect { node: node_id });
+                }
+            }
+            let Some(argument) = self.arguments.get(node_id) else {
+                continue;
+            };
+            if node.command.is_none() && !node.has_children() && node.redirect.is_none() {
+                errors.push(ValidationError::DeadEnd {
+                    node: node_id,
+                    name: argument.name.to_string(),
+                });
+            }
+            if ArgumentType::is_greedy(&argument.argument_type) {
+                for child_id in self.sorted_children(node_id) {
+                    errors.push(ValidationError::UnreachableChild {
+                        greedy: node_id,
+                        greedy_name: argument.name.to_string(),
+                        child: child_id,
+                        child_name: self.name(child_id).unwrap().to_owned(),
+                    });
+                }
+            }
+        }
+        let mut walk = PathWalk {
+            tree: self,
+            path: Vec::new(),
+            followed_redirects: HashSet::new(),
+            reported: HashSet::new(),
+            visits: 0,
+            errors: &mut errors,
+        };
+        for root in self.roots() {
+            walk.visit(root, &mut Vec::new());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Depth-first search over the paths of a tree, checking the argument names on each.
+struct PathWalk<'a, S, A>
+where
+    S: CommandSource,
+{
+    tree: &'a Tree<S, A>,
+    path: Vec<CommandNodeId>,
+    /// Every redirect target is walked once, this also ends cycles through redirects.
+    followed_redirects: HashSet<CommandNodeId>,
+    reported: HashSet<(CommandNodeId, CommandNodeId)>,
+    visits: usize,
+    errors: &'a mut Vec<ValidationError>,
+}
+
+impl<S, A> PathWalk<'_, S, A>
+where
+    S: CommandSource,
+{
+    /// `arguments` are the arguments of the current context on the path.
+    fn visit(&mut self, node_id: CommandNodeId, arguments: &mut Vec<CommandNodeId>) {
+        if self.visits >= MAX_VALIDATION_VISITS {
+            return;
+        }
+        self.visits += 1;
+        let tree = self.tree;
+        let node = &tree.nodes[node_id];
+        let is_argument = node.node_type == CommandNodeType::Argument;
+        if is_argument {
+            let name = &tree.arguments[node_id].name;
+            for &first in arguments.iter() {
+                if tree.arguments[first].name == *name && self.reported.insert((first, node_id)) {
+                    self.errors.push(ValidationError::DuplicateArgumentName {
+                        name: name.to_string(),
+                        first,
+                        second: node_id,
+                    });
+                }
+            }
+            arguments.push(node_id);
+        }
+        self.path.push(node_id);
+        if let Some(redirect) = node.redirect {
+            if tree.nodes.contains_key(redirect) && self.followed_redirects.insert(redirect) {
+                self.visit(redirect, &mut Vec::new());
+            }
+        }
+        for child_id in tree.sorted_children(node_id) {
+            if !self.path.contains(&child_id) {
+                self.visit(child_id, arguments);
+            }
+        }
+        self.path.pop();
+        if is_argument {
+            arguments.pop();
+        }
+    }
+}
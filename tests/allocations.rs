@@ -0,0 +1,82 @@
+//! Counts the allocations of building and searching a tree.
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+};
+
+use common::Source;
+
+/// Counts the allocations of the current thread, so that tests running in parallel
+/// don't disturb each other.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+const COMMANDS: usize = 1000;
+
+fn build(names: &[String]) -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    for name in names {
+        let mut value = argument("value", BoolArgumentType);
+        value.executes(|_| Ok(1));
+        let mut set = literal("set");
+        set.then(value);
+        let mut command = literal(name);
+        command.then(set);
+        dispatcher.register(command);
+    }
+    dispatcher
+}
+
+#[test]
+fn lowercase_literals_share_their_name() {
+    let lower: Vec<_> = (0..COMMANDS).map(|i| format!("command{i}")).collect();
+    let upper: Vec<_> = (0..COMMANDS).map(|i| format!("Command{i}")).collect();
+    let (_, lower) = allocations(|| build(&lower));
+    let (_, upper) = allocations(|| build(&upper));
+    // A mixed case literal allocates its lowercase name twice, when converting and
+    // interning it. A lowercase one reuses its name, the rest of the tree is the same.
+    assert!(
+        upper.saturating_sub(lower) > COMMANDS * 3 / 2,
+        "{lower} allocations for lowercase literals, {upper} for mixed case ones"
+    );
+}
+
+#[test]
+fn lookups_do_not_allocate() {
+    let names: Vec<_> = (0..COMMANDS).map(|i| format!("command{i}")).collect();
+    let dispatcher = build(&names);
+    let (node, count) = allocations(|| dispatcher.find_node(&["command500", "set", "value"]));
+    assert!(node.is_some());
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::collections::HashSet;
+
+use brigadier::{
+    ambiguity::{AmbiguityChecker, CheckProgress},
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+};
+
+use common::Source;
+
+fn dispatcher() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut set = literal("set");
+    set.then(literal("false"));
+    set.then(argument("value", BoolArgumentType));
+    set.then(literal("other"));
+    dispatcher.register(set);
+    dispatcher.register(literal("get"));
+    dispatcher
+}
+
+#[test]
+fn find_ambiguities_one_shot() {
+    let dispatcher = dispatcher();
+    let findings = dispatcher.tree().find_ambiguities();
+    let summary: Vec<_> = findings
+        .iter()
+        .map(|finding| {
+            (
+                finding.parent_path.join(" "),
+                finding.child_name.as_str(),
+                finding.sibling_name.as_str(),
+                finding.inputs.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("set".to_owned(), "false", "value", vec!["false".to_owned()]),
+            ("set".to_owned(), "value", "false", vec!["false".to_owned()]),
+        ]
+    );
+}
+
+#[test]
+fn find_ambiguities_incrementally() {
+    let dispatcher = dispatcher();
+    let tree = dispatcher.tree();
+    let mut checker = AmbiguityChecker::new(tree);
+    let mut calls = 0;
+    let findings = loop {
+        calls += 1;
+        match checker.check_some(tree, 2) {
+            CheckProgress::InProgress { done, total } => {
+                assert_eq!(done, calls * 2);
+                assert!(done < total);
+            }
+            CheckProgress::Finished(findings) => break findings,
+            CheckProgress::TreeChanged => panic!("the tree did not change"),
+        }
+    };
+    assert!(calls > 1);
+    assert_eq!(findings, tree.find_ambiguities());
+}
+
+#[test]
+fn tree_change_invalidates_checker() {
+    let mut dispatcher = dispatcher();
+    let mut checker = AmbiguityChecker::new(dispatcher.tree());
+    assert!(matches!(
+        checker.check_some(dispatcher.tree(), 1),
+        CheckProgress::InProgress { done: 1, .. }
+    ));
+    dispatcher.register(literal("new"));
+    assert_eq!(
+        checker.check_some(dispatcher.tree(), 1),
+        CheckProgress::TreeChanged
+    );
+}
+
+#[test]
+fn dispatcher_find_ambiguities() {
+    let dispatcher = dispatcher();
+    let mut found = Vec::new();
+    dispatcher.find_ambiguities(|parent, child, sibling, inputs| {
+        let tree = dispatcher.tree();
+        found.push((
+            tree.name(parent).unwrap().to_owned(),
+            tree.name(child).unwrap().to_owned(),
+            tree.name(sibling).unwrap().to_owned(),
+            inputs,
+        ));
+    });
+    let inputs = HashSet::from(["false".to_owned()]);
+    assert_eq!(
+        found,
+        vec![
+            ("set".into(), "false".into(), "value".into(), inputs.clone()),
+            ("set".into(), "value".into(), "false".into(), inputs),
+        ]
+    );
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use brigadier::{
+    arguments::{
+        any::{AnyValue, BoxArgumentType, BoxedArgumentType},
+        ArgumentType, BoolArgumentType, DoubleArgumentType,
+    },
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    suggestion::SuggestionsBuilder,
+    StringReader,
+};
+
+use common::Source;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    Red,
+    Green,
+}
+
+/// An argument type with an output unrelated to the built-in ones.
+struct ColorArgumentType;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for ColorArgumentType {
+    type Output = Color;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Color, CommandSyntaxError<'i>> {
+        let start = reader.cursor();
+        match reader.read_unquoted_string()? {
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            _ => {
+                reader.set_cursor(start);
+                Err(CommandSyntaxError::with_context(
+                    CommandErrorType::DispatcherUnknownArgument,
+                    reader.context(),
+                ))
+            }
+        }
+    }
+}
+
+fn paint<'i>(
+    context: &CommandContext<'i, Source, BoxedArgumentType<Source>>,
+) -> Result<i32, CommandSyntaxError<'i>> {
+    let color = *context.get_argument_as::<Color>("color")?;
+    let opacity = *context.get_argument_as::<f64>("opacity")?;
+    let glossy = *context.get_argument_as::<bool>("glossy")?;
+    assert_eq!(color, Color::Green);
+    Ok((opacity * 10.0) as i32 + glossy as i32)
+}
+
+/// `paint <color> <opacity> <glossy>`
+fn dispatcher() -> CommandDispatcher<Source, BoxedArgumentType<Source>> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut glossy = argument("glossy", BoolArgumentType.boxed());
+    glossy.executes(paint);
+    let mut opacity = argument("opacity", DoubleArgumentType::new(0.0..=1.0).boxed());
+    opacity.then(glossy);
+    let mut color = argument("color", ColorArgumentType.boxed());
+    color.then(opacity);
+    let mut command = literal("paint");
+    command.then(color);
+    dispatcher.register(command);
+    dispatcher
+}
+
+#[test]
+fn mixed_outputs_in_one_tree() {
+    let dispatcher = dispatcher();
+    assert_eq!(
+        dispatcher.execute_input("paint green 0.5 true", Source),
+        Ok(6)
+    );
+    // Errors of the wrapped types are kept
+    let error = dispatcher
+        .execute_input("paint green 2 true", Source)
+        .unwrap_err();
+    assert!(matches!(
+        error.error_type,
+        CommandErrorType::DoubleTooBig { .. }
+    ));
+}
+
+#[test]
+fn wrong_argument_type() {
+    let dispatcher = dispatcher();
+    let parse = dispatcher.parse("paint red 1 false", Source);
+    let context = parse.context().clone().build("paint red 1 false");
+    assert_eq!(context.get_argument_as::<Color>("color"), Ok(&Color::Red));
+    let error = context.get_argument_as::<i32>("opacity").unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherWrongArgumentType {
+            name: "opacity".to_owned(),
+            expected: "i32",
+            actual: "f64",
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "The argument 'opacity' is a f64, not a i32"
+    );
+    assert_eq!(context.get_typed::<Color>("color"), Ok(&Color::Red));
+    assert_eq!(
+        context.get_typed::<i32>("opacity").unwrap_err(),
+        context.get_argument_as::<i32>("opacity").unwrap_err()
+    );
+    let error = context.get_argument_as::<bool>("size").unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherNoSuchArgument {
+            name: "size".to_owned()
+        }
+    );
+}
+
+#[test]
+fn any_value() {
+    let value = AnyValue::new(Color::Red);
+    assert!(value.is::<Color>());
+    assert_eq!(value.downcast_ref::<Color>(), Some(&Color::Red));
+    assert_eq!(value.downcast_ref::<bool>(), None);
+    assert_eq!(value, AnyValue::new(Color::Red));
+    assert_ne!(value, AnyValue::new(Color::Green));
+    // Values of different types are never equal
+    assert_ne!(AnyValue::new(1i32), AnyValue::new(1i64));
+    assert!(format!("{value:?}").contains("Color"));
+}
+
+#[test]
+fn boxed_suggestions_and_examples() {
+    let dispatcher = dispatcher();
+    let glossy = dispatcher
+        .find_node(&["paint", "color", "opacity", "glossy"])
+        .unwrap();
+    let input = "paint red 1 t";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, 12);
+    let suggestions = pollster::block_on(
+        dispatcher
+            .tree()
+            .list_suggestions(glossy, &context, builder),
+    )
+    .unwrap();
+    let texts: Vec<_> = suggestions.iter().map(|s| s.text().to_owned()).collect();
+    assert_eq!(texts, vec!["true"]);
+    assert_eq!(
+        ArgumentType::<Source>::examples(&BoolArgumentType.boxed()),
+        ArgumentType::<Source>::examples(&BoolArgumentType)
+    );
+}
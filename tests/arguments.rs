@@ -0,0 +1,194 @@
+mod common;
+
+use brigadier::{
+    arguments::{
+        ArgCapabilities, ArgumentDescriptor, ArgumentType, BoolArgumentType, DoubleArgumentType,
+        StringArgumentType, StringType, UIntArgumentType, ULongArgumentType,
+    },
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    suggestion::{StaticSuggestions, SuggestionsBuilder},
+    Shared, StringReader,
+};
+
+use common::Source;
+
+struct ServerOnly;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for ServerOnly {
+    type Output = String;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<String, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string().map(str::to_owned)
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        ArgCapabilities::all() - ArgCapabilities::CLIENT_SIDE
+    }
+}
+
+fn capabilities<A: for<'i> ArgumentType<'i, Source>>(argument_type: &A) -> ArgCapabilities {
+    argument_type.capabilities()
+}
+
+#[test]
+fn builtin_capabilities() {
+    assert_eq!(capabilities(&BoolArgumentType), ArgCapabilities::all());
+    let double = capabilities(&DoubleArgumentType::new(..));
+    assert!(double.contains(ArgCapabilities::CLIENT_SIDE | ArgCapabilities::EXAMPLES));
+    // Some examples are out of the range
+    let bounded = capabilities(&DoubleArgumentType::new(0.0..=10.0));
+    assert!(bounded.contains(ArgCapabilities::CLIENT_SIDE));
+    assert!(!bounded.contains(ArgCapabilities::EXAMPLES));
+}
+
+fn descriptor<A>(argument_type: &A) -> Option<ArgumentDescriptor>
+where
+    A: for<'i> ArgumentType<'i, Source>,
+{
+    argument_type.descriptor()
+}
+
+#[test]
+fn builtin_descriptors() {
+    let bounds = |argument_type| descriptor(&argument_type).unwrap().bounds;
+    assert_eq!(bounds(DoubleArgumentType::new(..)), None);
+    assert_eq!(bounds(DoubleArgumentType::new(0.5..=10.0)).as_deref(), Some("0.5..=10"));
+    assert_eq!(bounds(DoubleArgumentType::new(1.5..)).as_deref(), Some("1.5.."));
+    assert_eq!(bounds(DoubleArgumentType::new(..=-2.0)).as_deref(), Some("..=-2"));
+    assert_eq!(
+        descriptor(&UIntArgumentType::new(..=100)),
+        Some(ArgumentDescriptor {
+            type_name: "uint",
+            bounds: Some(String::from("..=100")),
+        })
+    );
+    let greedy = descriptor(&StringArgumentType::greedy_string()).unwrap();
+    assert_eq!(greedy.type_name, "greedy_string");
+    assert_eq!(descriptor(&ServerOnly), None);
+}
+
+#[test]
+fn custom_type_clears_capability() {
+    let server_only = capabilities(&ServerOnly);
+    assert!(!server_only.contains(ArgCapabilities::CLIENT_SIDE));
+    assert!(server_only.contains(ArgCapabilities::EXAMPLES));
+}
+
+#[test]
+fn bool_argument_in_tree() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    dispatcher.register(argument("value", BoolArgumentType));
+    let input = "true";
+    let parse = dispatcher.parse(input, Source);
+    assert!(parse.exceptions().is_empty());
+    let context = parse.context().clone().build(input);
+    assert_eq!(context.get_argument("value"), Some(&true));
+}
+
+/// Parsed values only need to be cloneable, they are never compared.
+#[derive(Clone)]
+struct Handle(brigadier::Shared<str>);
+
+struct HandleArgumentType;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for HandleArgumentType {
+    type Output = Handle;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Handle, CommandSyntaxError<'i>> {
+        Ok(Handle(reader.read_unquoted_string()?.into()))
+    }
+}
+
+#[test]
+fn value_without_partial_eq() {
+    let mut dispatcher = CommandDispatcher::<Source, HandleArgumentType>::new();
+    dispatcher.register(argument("handle", HandleArgumentType));
+    let input = "abc";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let Handle(name) = context.get_argument("handle").unwrap();
+    assert_eq!(&**name, "abc");
+}
+
+fn parse_uint<'i>(argument_type: &UIntArgumentType, input: &'i str) -> Result<u32, CommandSyntaxError<'i>> {
+    ArgumentType::<Source>::parse(argument_type, &mut StringReader::new(input))
+}
+
+#[test]
+fn unsigned_argument_bounds() {
+    let page = UIntArgumentType::new(1..=10);
+    assert_eq!(parse_uint(&page, "10"), Ok(10));
+    let error = parse_uint(&page, "0").unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::UIntegerTooSmall { found: 0, min: 1 });
+    assert_eq!(error.context.unwrap().cursor, 0);
+    assert_eq!(
+        parse_uint(&page, "11").unwrap_err().error_type,
+        CommandErrorType::UIntegerTooBig { found: 11, max: 10 }
+    );
+    assert_eq!(
+        parse_uint(&page, "-1").unwrap_err().error_type,
+        CommandErrorType::ReaderExpectedNonNegative
+    );
+
+    let id = ULongArgumentType::new(..=u64::MAX - 1);
+    let mut reader = StringReader::new("18446744073709551615");
+    assert_eq!(
+        ArgumentType::<Source>::parse(&id, &mut reader).unwrap_err().error_type,
+        CommandErrorType::ULongTooBig { found: u64::MAX, max: u64::MAX - 1 }
+    );
+}
+
+fn parse_string(string_type: StringType, input: &str) -> (String, &str) {
+    let mut reader = StringReader::new(input);
+    let argument_type = StringArgumentType::new(string_type);
+    let value = ArgumentType::<Source>::parse(&argument_type, &mut reader).unwrap();
+    (value.into_owned(), reader.remaining())
+}
+
+#[test]
+fn string_types() {
+    let input = "\"hello world\" again";
+    let word = ("hello".to_owned(), " world");
+    assert_eq!(parse_string(StringType::SingleWord, "hello world"), word);
+    assert_eq!(parse_string(StringType::QuotablePhrase, "hello world"), word);
+    assert_eq!(
+        parse_string(StringType::QuotablePhrase, input),
+        ("hello world".to_owned(), " again")
+    );
+    assert_eq!(parse_string(StringType::GreedyPhrase, input), (input.to_owned(), ""));
+    assert!(ArgumentType::<Source>::is_greedy(&StringArgumentType::greedy_string()));
+    assert!(!ArgumentType::<Source>::is_greedy(&StringArgumentType::string()));
+}
+
+fn suggest_fruit(input: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut dispatcher = CommandDispatcher::<Source, StringArgumentType>::new();
+    let mut eat = literal("eat");
+    eat.then_build(argument("fruit", StringArgumentType::string()), |fruit| {
+        fruit.suggests(Shared::new(StaticSuggestions::new(["apple", "apricot", "banana"])))
+    });
+    dispatcher.register(eat);
+    let fruit = dispatcher.find_node(&["eat", "fruit"]).unwrap();
+    let context = dispatcher.parse(input, Source).context().clone().build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, "eat ".len());
+    let suggestions =
+        pollster::block_on(dispatcher.tree().list_suggestions(fruit, &context, builder)).unwrap();
+    suggestions.iter().map(|s| (s.range(), s.text().to_owned())).collect()
+}
+
+#[test]
+fn quoted_string_suggestions_start_after_quote() {
+    assert_eq!(
+        suggest_fruit("eat \"ap"),
+        vec![(5..7, "apple".to_owned()), (5..7, "apricot".to_owned())]
+    );
+    assert_eq!(
+        suggest_fruit("eat ap"),
+        vec![(4..6, "apple".to_owned()), (4..6, "apricot".to_owned())]
+    );
+}
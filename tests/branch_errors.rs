@@ -0,0 +1,105 @@
+mod common;
+
+use brigadier::{
+    arguments::{
+        any::{BoxArgumentType, BoxedArgumentType},
+        ArgumentType, UIntArgumentType,
+    },
+    builder::argument,
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    StringReader,
+};
+
+use common::Source;
+
+/// Reads one word, or two if `words` is 2.
+struct Words(usize);
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Words {
+    type Output = String;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<String, CommandSyntaxError<'i>> {
+        let start = reader.cursor();
+        for i in 0..self.0 {
+            if i > 0 {
+                reader.skip();
+            }
+            reader.read_unquoted_string()?;
+        }
+        Ok(reader.input()[start..reader.cursor()].to_owned())
+    }
+}
+
+/// A quoted string.
+struct Quoted;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Quoted {
+    type Output = String;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<String, CommandSyntaxError<'i>> {
+        reader.read_quoted_string().map(Into::into)
+    }
+}
+
+fn number() -> BoxedArgumentType<Source> {
+    UIntArgumentType::new(..).boxed()
+}
+
+/// `<word> <number>` and `<phrase> <number>`, where a phrase consists of two words.
+fn dispatcher() -> CommandDispatcher<Source, BoxedArgumentType<Source>> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut word = argument("word", Words(1).boxed());
+    let mut number_after_word = argument("number", number());
+    number_after_word.executes(|_| Ok(1));
+    word.then(number_after_word);
+    dispatcher.register(word);
+    let mut phrase = argument("phrase", Words(2).boxed());
+    let mut number = argument("number", number());
+    number.executes(|_| Ok(2));
+    phrase.then(number);
+    dispatcher.register(phrase);
+    dispatcher
+}
+
+#[test]
+fn furthest_branch_wins() {
+    let dispatcher = dispatcher();
+    // The word branch fails at 4, the phrase branch at 9
+    let error = dispatcher
+        .execute_input("abc defg hij", Source)
+        .unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedInt);
+    assert_eq!(error.context.unwrap().cursor, 9);
+
+    let parse = dispatcher.parse("abc defg hij", Source);
+    let phrase = dispatcher.find_node(&["phrase"]).unwrap();
+    assert_eq!(parse.context().nodes()[0].node, phrase);
+    assert_eq!(parse.exceptions().len(), 1);
+}
+
+#[test]
+fn ties_are_combined() {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut number = argument("number", number());
+    number.executes(|_| Ok(1));
+    dispatcher.register(number);
+    let mut quoted = argument("quoted", Quoted.boxed());
+    quoted.executes(|_| Ok(2));
+    dispatcher.register(quoted);
+
+    let error = dispatcher.execute_input("text", Source).unwrap_err();
+    let CommandErrorType::DispatcherMultiple(alternatives) = &error.error_type else {
+        panic!("{error}");
+    };
+    assert_eq!(alternatives.len(), 2);
+    assert_eq!(error.context.unwrap().cursor, 0);
+    assert_eq!(
+        error.error_type.to_string(),
+        "Expected integer / Expected quote to start a string"
+    );
+    // The map of every failed node is still available
+    assert_eq!(dispatcher.parse("text", Source).exceptions().len(), 2);
+}
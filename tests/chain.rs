@@ -0,0 +1,132 @@
+mod common;
+
+use std::borrow::Cow;
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    suggestion::SuggestionsBuilder,
+    StringReader,
+};
+
+use common::Source;
+
+/// A quoted or unquoted string.
+struct Text;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Text {
+    type Output = Cow<'i, str>;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Cow<'i, str>, CommandSyntaxError<'i>> {
+        reader.read_string()
+    }
+}
+
+/// `say <text>` returns the length of the text, `fail` fails.
+fn dispatcher() -> CommandDispatcher<Source, Text> {
+    let mut dispatcher = CommandDispatcher::new();
+    dispatcher.set_chain_separator(Some(";"));
+    let mut text = argument("text", Text);
+    text.executes(|context| Ok(context.get_argument("text").unwrap().len() as i32));
+    let mut say = literal("say");
+    say.then(text);
+    dispatcher.register(say);
+    let mut fail = literal("fail");
+    fail.executes(|_| {
+        Err(CommandSyntaxError::new(
+            CommandErrorType::DispatcherUnknownCommand,
+        ))
+    });
+    dispatcher.register(fail);
+    dispatcher
+}
+
+#[test]
+fn split_at_top_level_separators() {
+    let dispatcher = dispatcher();
+    let input = r#"say "a;b" ; say (c;d);say 'e\';f';;  "#;
+    let segments: Vec<_> = dispatcher
+        .split_chain(input)
+        .into_iter()
+        .map(|range| &input[range])
+        .collect();
+    assert_eq!(
+        segments,
+        vec![r#"say "a;b""#, "say (c;d)", r#"say 'e\';f'"#]
+    );
+}
+
+#[test]
+fn without_separator_the_input_is_one_command() {
+    let mut dispatcher = dispatcher();
+    dispatcher.set_chain_separator(None);
+    let segments = dispatcher.split_chain("say a; say b");
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0], 0..12);
+}
+
+#[test]
+fn execute_separator_inside_quotes() {
+    let dispatcher = dispatcher();
+    let report = dispatcher.execute_chain(r#"say "a;b"; say four"#, Source, true);
+    assert!(report.is_success());
+    let results: Vec<_> = report.segments.iter().map(|s| s.result.clone()).collect();
+    assert_eq!(results, vec![Ok(3), Ok(4)]);
+    assert_eq!(report.segments[1].range, 11..19);
+    assert_eq!(report.total(), 7);
+}
+
+#[test]
+fn errors_have_cursors_in_the_whole_input() {
+    let dispatcher = dispatcher();
+    let report = dispatcher.execute_chain("say one; nope; say", Source, false);
+    let errors: Vec<_> = report.errors().collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].context.as_ref().unwrap().cursor, 9);
+    // `say` without text ends at the end of the input
+    assert_eq!(errors[1].context.as_ref().unwrap().cursor, 18);
+}
+
+#[test]
+fn stop_on_error() {
+    let dispatcher = dispatcher();
+    let input = "say one; fail; say three";
+
+    let report = dispatcher.execute_chain(input, Source, true);
+    assert!(!report.is_success());
+    assert_eq!(report.segments.len(), 2);
+    assert!(report.segments[1].result.is_err());
+    assert_eq!(report.skipped, vec![15..24]);
+    assert_eq!(report.total(), 3);
+
+    let report = dispatcher.execute_chain(input, Source, false);
+    assert_eq!(report.segments.len(), 3);
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.total(), 8);
+}
+
+#[test]
+fn suggestions_in_second_segment() {
+    let dispatcher = dispatcher();
+    let input = "say one; sax; fail";
+    let cursor = 11;
+    let parse = dispatcher.parse_chain_at(input, cursor, Source);
+    // Only the second command was parsed, from its start up to the cursor
+    assert_eq!(parse.reader().cursor(), 9);
+    assert_eq!(parse.reader().input(), &input[..cursor]);
+    assert!(parse.context().nodes().is_empty());
+
+    let say = dispatcher.find_node(&["say"]).unwrap();
+    let context = parse.context().clone().build(parse.reader().input());
+    let builder = SuggestionsBuilder::new_with_lowercase(parse.reader().input(), 9);
+    let suggestions =
+        pollster::block_on(dispatcher.tree().list_suggestions(say, &context, builder)).unwrap();
+    let suggestions: Vec<_> = suggestions
+        .iter()
+        .map(|s| (s.range(), s.text().to_owned()))
+        .collect();
+    assert_eq!(suggestions, vec![(9..11, "say".to_owned())]);
+}
@@ -0,0 +1,475 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    command::{CommandFuture, CompensatedStep, NodeCommand, TransactionalCommand},
+    context::{CommandContext, CommandContextBuilder},
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    CommandSource,
+};
+
+#[derive(Clone, Default)]
+struct Source {
+    log: Arc<Mutex<Vec<&'static str>>>,
+    /// Makes `failing_if_marked` fail
+    marked: bool,
+}
+
+impl CommandSource for Source {}
+
+impl Source {
+    fn push(&self, entry: &'static str) {
+        self.log.lock().unwrap().push(entry);
+    }
+    fn entries(&self) -> Vec<&'static str> {
+        self.log.lock().unwrap().clone()
+    }
+}
+
+type Context<'i> = CommandContext<'i, Source, BoolArgumentType>;
+type Step = CompensatedStep<Source, BoolArgumentType>;
+
+fn error<'i>(message: &str) -> CommandSyntaxError<'i> {
+    CommandSyntaxError::new(CommandErrorType::DispatcherParseException(
+        message.to_owned(),
+    ))
+}
+
+fn context(source: &Source) -> Context<'static> {
+    let dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    CommandContextBuilder::new(source.clone(), dispatcher.root(), 0).build("")
+}
+
+fn step(result: i32, done: &'static str, undone: &'static str) -> Step {
+    Step::new(
+        move |context| {
+            context.source().push(done);
+            Ok(result)
+        },
+        move |context| {
+            context.source().push(undone);
+            Ok(())
+        },
+    )
+}
+
+fn step_1() -> Step {
+    step(1, "do 1", "undo 1")
+}
+
+fn step_2() -> Step {
+    step(2, "do 2", "undo 2")
+}
+
+fn failing_step() -> Step {
+    Step::new(|_| Err(error("step failed")), |_| unreachable!())
+}
+
+fn failing_if_marked() -> Step {
+    Step::new(
+        |context| match context.source().marked {
+            true => Err(error("marked")),
+            false => Ok(0),
+        },
+        |_| Ok(()),
+    )
+}
+
+fn failing_compensator() -> Step {
+    Step::new(
+        |context| {
+            context.source().push("do 3");
+            Ok(3)
+        },
+        |_| Err(error("undo failed")),
+    )
+}
+
+#[test]
+fn transaction_succeeds() {
+    let source = Source::default();
+    let result = context(&source).run_compensated(&[step_1(), step_2()]);
+    assert_eq!(result, Ok(3));
+    assert_eq!(source.entries(), vec!["do 1", "do 2"]);
+}
+
+#[test]
+fn transaction_fails_at_second_step() {
+    let source = Source::default();
+    let error = context(&source)
+        .run_compensated(&[step_1(), failing_step(), step_2()])
+        .unwrap_err();
+    assert_eq!(error.step, 1);
+    assert!(error.compensator_errors.is_empty());
+    assert_eq!(source.entries(), vec!["do 1", "undo 1"]);
+}
+
+#[test]
+fn transaction_fails_at_third_step() {
+    let source = Source::default();
+    let error = context(&source)
+        .run_compensated(&[step_1(), step_2(), failing_step()])
+        .unwrap_err();
+    assert_eq!(error.step, 2);
+    assert_eq!(source.entries(), vec!["do 1", "do 2", "undo 2", "undo 1"]);
+}
+
+#[test]
+fn transaction_compensator_fails() {
+    let source = Source::default();
+    let error = context(&source)
+        .run_compensated(&[step_1(), failing_compensator(), failing_step()])
+        .unwrap_err();
+    assert_eq!(error.step, 2);
+    assert_eq!(error.error, self::error("step failed"));
+    assert_eq!(
+        error.compensator_errors,
+        vec![(1, self::error("undo failed"))]
+    );
+    assert_eq!(source.entries(), vec!["do 1", "do 3", "undo 1"]);
+    assert_eq!(
+        error.to_string(),
+        "step 2 failed: Could not parse command: step failed; \
+         undoing step 1 failed: Could not parse command: undo failed"
+    );
+}
+
+#[test]
+fn transactional_command_on_node() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut swap = literal("swap");
+    swap.executes_command(TransactionalCommand::new([step_1(), step_2()]));
+    dispatcher.register(swap);
+    let mut second = literal("second");
+    second.executes_command(TransactionalCommand::new([
+        step_1(),
+        failing_step(),
+        step_2(),
+    ]));
+    dispatcher.register(second);
+    let mut third = literal("third");
+    third.executes_command(TransactionalCommand::new([
+        step_1(),
+        step_2(),
+        failing_step(),
+    ]));
+    dispatcher.register(third);
+    let mut broken = literal("broken");
+    broken.executes_command(TransactionalCommand::new([
+        step_1(),
+        failing_compensator(),
+        failing_step(),
+    ]));
+    dispatcher.register(broken);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("swap", source.clone()), Ok(3));
+    assert_eq!(source.entries(), vec!["do 1", "do 2"]);
+
+    let source = Source::default();
+    assert_eq!(
+        dispatcher.execute_input("second", source.clone()),
+        Err(error("step failed"))
+    );
+    assert_eq!(source.entries(), vec!["do 1", "undo 1"]);
+
+    let source = Source::default();
+    assert_eq!(
+        dispatcher.execute_input("third", source.clone()),
+        Err(error("step failed"))
+    );
+    assert_eq!(source.entries(), vec!["do 1", "do 2", "undo 2", "undo 1"]);
+
+    // Failed compensators are bundled with the failure
+    let source = Source::default();
+    let error = dispatcher
+        .execute_input("broken", source.clone())
+        .unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherMultiple(vec![
+            self::error("step failed"),
+            self::error("undo failed")
+        ])
+    );
+    assert_eq!(source.entries(), vec!["do 1", "do 3", "undo 1"]);
+}
+
+#[test]
+fn transaction_per_fork() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut swap = literal("swap");
+    swap.executes_command(TransactionalCommand::new([
+        step_1(),
+        failing_if_marked(),
+        step_2(),
+    ]));
+    dispatcher.register(swap);
+    let root = dispatcher.root();
+    let mut both = literal::<Source, BoolArgumentType>("both");
+    both.fork(root, |context| {
+        let marked = Source {
+            marked: true,
+            ..context.source().clone()
+        };
+        Ok(vec![context.source().clone(), marked])
+    });
+    dispatcher.register(both);
+
+    // Only the steps of the marked source are undone
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("both swap", source.clone()), Ok(1));
+    assert_eq!(source.entries(), vec!["do 1", "do 2", "do 1", "undo 1"]);
+}
+
+#[test]
+fn closures_as_commands() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut one = literal("one");
+    one.executes(|_| Ok(1));
+    dispatcher.register(one);
+    let mut log = literal::<Source, BoolArgumentType>("log");
+    log.executes(|context| {
+        context.source().push("log");
+        Ok(2)
+    });
+    dispatcher.register(log);
+    let mut toggle = literal("toggle");
+    let mut value = argument("value", BoolArgumentType);
+    value.executes(|context| match context.get_argument("value") {
+        Some(true) => Ok(3),
+        _ => Err(CommandSyntaxError::new(
+            CommandErrorType::DispatcherUnknownArgument,
+        )),
+    });
+    toggle.then(value);
+    dispatcher.register(toggle);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("one", source.clone()), Ok(1));
+    assert_eq!(dispatcher.execute_input("log", source.clone()), Ok(2));
+    assert_eq!(
+        dispatcher.execute_input("toggle true", source.clone()),
+        Ok(3)
+    );
+    assert!(dispatcher.execute_input("toggle false", source.clone()).is_err());
+    assert_eq!(source.entries(), vec!["log"]);
+}
+
+#[test]
+fn capturing_closures_as_commands() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let counter = Arc::new(Mutex::new(0));
+    let mut increment = literal("increment");
+    let count = Arc::clone(&counter);
+    increment.executes(move |_| {
+        let mut count = count.lock().unwrap();
+        *count += 1;
+        Ok(*count)
+    });
+    dispatcher.register(increment);
+    let mut reset = literal("reset");
+    let count = Arc::clone(&counter);
+    reset.executes(move |_| {
+        *count.lock().unwrap() = 0;
+        Ok(0)
+    });
+    dispatcher.register(reset);
+    let mut greet = literal::<Source, BoolArgumentType>("greet");
+    let greeting = String::from("hello");
+    greet.executes(move |context| {
+        context.source().push("greet");
+        Ok(greeting.len() as i32)
+    });
+    dispatcher.register(greet);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("increment", source.clone()), Ok(1));
+    assert_eq!(dispatcher.execute_input("increment", source.clone()), Ok(2));
+    assert_eq!(*counter.lock().unwrap(), 2);
+    assert_eq!(dispatcher.execute_input("reset", source.clone()), Ok(0));
+    assert_eq!(dispatcher.execute_input("increment", source.clone()), Ok(1));
+    assert_eq!(dispatcher.execute_input("greet", source.clone()), Ok(5));
+    assert_eq!(source.entries(), vec!["greet"]);
+}
+
+#[test]
+fn closure_errors_borrow_the_input() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut check = literal("check");
+    let max_len = 3;
+    // The closure owns its captures, while the error borrows the input of the call
+    check.executes(move |context| {
+        let input = context.input();
+        if input.len() > max_len {
+            Err(CommandSyntaxError::new(CommandErrorType::ReaderInvalidInt(
+                input,
+            )))
+        } else {
+            Ok(1)
+        }
+    });
+    dispatcher.register(check);
+
+    let error = dispatcher
+        .execute_input("check", Source::default())
+        .unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::ReaderInvalidInt("check")
+    );
+
+    // Stored commands can be run without the dispatcher
+    let node = dispatcher.find_node(&["check"]).unwrap();
+    let Some(NodeCommand::Sync(command)) = dispatcher.tree().get(node).unwrap().command() else {
+        panic!("Expected a command");
+    };
+    let input = String::from("check");
+    let context = CommandContextBuilder::new(Source::default(), dispatcher.root(), 0).build(&input);
+    assert_eq!(
+        command.run(&context).unwrap_err().error_type,
+        CommandErrorType::ReaderInvalidInt("check")
+    );
+}
+
+#[test]
+fn closures_as_redirect_modifiers() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut log = literal::<Source, BoolArgumentType>("log");
+    log.executes(|context| {
+        context.source().push("log");
+        Ok(2)
+    });
+    dispatcher.register(log);
+    let root = dispatcher.root();
+    let mut sudo = literal::<Source, BoolArgumentType>("sudo");
+    sudo.redirect_modifier(root, |context| {
+        context.source().push("sudo");
+        Ok(context.source().clone())
+    });
+    dispatcher.register(sudo);
+    let mut nobody = literal::<Source, BoolArgumentType>("nobody");
+    nobody.redirect_modifier(root, |_| Err(error("no such user")));
+    dispatcher.register(nobody);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("sudo log", source.clone()), Ok(2));
+    assert_eq!(source.entries(), vec!["sudo", "log"]);
+    assert_eq!(
+        dispatcher.execute_input("nobody log", source.clone()),
+        Err(error("no such user"))
+    );
+    assert_eq!(source.entries(), vec!["sudo", "log"]);
+}
+
+#[test]
+fn redirect_modifier_captures_source() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut log = literal::<Source, BoolArgumentType>("log");
+    log.executes(|context| {
+        context.source().push("log");
+        Ok(2)
+    });
+    dispatcher.register(log);
+    let root = dispatcher.root();
+    let guest = Source::default();
+    let mut visit = literal::<Source, BoolArgumentType>("visit");
+    let target = guest.clone();
+    visit.redirect_modifier(root, move |_| Ok(target.clone()));
+    dispatcher.register(visit);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("visit log", source.clone()), Ok(2));
+    assert!(source.entries().is_empty());
+    assert_eq!(guest.entries(), vec!["log"]);
+}
+
+#[test]
+fn fork_modifier_captures_sources() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut log = literal::<Source, BoolArgumentType>("log");
+    log.executes(|context| {
+        context.source().push("log");
+        Ok(2)
+    });
+    dispatcher.register(log);
+    let root = dispatcher.root();
+    let team = vec![Source::default(), Source::default()];
+    let mut all = literal::<Source, BoolArgumentType>("all");
+    let members = team.clone();
+    all.fork(root, move |_| Ok(members.clone()));
+    dispatcher.register(all);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("all log", source.clone()), Ok(2));
+    assert!(source.entries().is_empty());
+    for member in &team {
+        assert_eq!(member.entries(), vec!["log"]);
+    }
+}
+
+#[test]
+fn closures_as_fork_modifiers() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut log = literal::<Source, BoolArgumentType>("log");
+    log.executes(|context| {
+        context.source().push("log");
+        Ok(2)
+    });
+    dispatcher.register(log);
+    let root = dispatcher.root();
+    let mut each = literal::<Source, BoolArgumentType>("each");
+    each.fork(root, |context| Ok(vec![context.source().clone(); 3]));
+    dispatcher.register(each);
+
+    let source = Source::default();
+    assert_eq!(dispatcher.execute_input("each log", source.clone()), Ok(3));
+    assert_eq!(source.entries(), vec!["log"; 3]);
+}
+
+/// Waits for the delay before succeeding with the value of the shared counter.
+fn delayed(
+    delay: Duration,
+    counter: Arc<Mutex<i32>>,
+) -> impl for<'c, 'i> Fn(&'c Context<'i>) -> CommandFuture<'c, 'i> {
+    move |context| {
+        let counter = Arc::clone(&counter);
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            context.source().push("delayed");
+            let mut counter = counter.lock().unwrap();
+            *counter += 1;
+            Ok(*counter)
+        })
+    }
+}
+
+#[tokio::test]
+async fn execute_async_command() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let counter = Arc::new(Mutex::new(4));
+    let mut wait = literal("wait");
+    wait.executes_async(delayed(Duration::from_millis(1), Arc::clone(&counter)));
+    dispatcher.register(wait);
+    let mut now = literal("now");
+    now.executes(|_| Ok(1));
+    dispatcher.register(now);
+
+    let source = Source::default();
+    let parse = dispatcher.parse("wait", source.clone());
+    assert_eq!(dispatcher.execute_async(parse).await, Ok(5));
+    let parse = dispatcher.parse("now", source.clone());
+    assert_eq!(dispatcher.execute_async(parse).await, Ok(1));
+    assert_eq!(source.entries(), vec!["delayed"]);
+    assert_eq!(*counter.lock().unwrap(), 5);
+
+    let error = dispatcher.execute_input("wait", source.clone()).unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::DispatcherAsyncCommand);
+    assert_eq!(source.entries(), vec!["delayed"]);
+    assert_eq!(*counter.lock().unwrap(), 5);
+}
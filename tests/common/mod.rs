@@ -0,0 +1,25 @@
+//! Fixtures shared by the integration tests.
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    CommandSource,
+};
+
+/// A source without any data, for commands which don't depend on the source.
+#[derive(Clone)]
+pub struct Source;
+
+impl CommandSource for Source {}
+
+/// A dispatcher with the command `toggle <value>`, which parses but doesn't execute.
+// Not every test crate including this module uses it
+#[allow(dead_code)]
+pub fn toggle_dispatcher() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut toggle = literal("toggle");
+    toggle.then(argument("value", BoolArgumentType));
+    dispatcher.register(toggle);
+    dispatcher
+}
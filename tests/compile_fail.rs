@@ -0,0 +1,6 @@
+/// Borrow patterns which must not compile, each with the error explaining why.
+#[test]
+fn compile_fail() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/compile_fail/*.rs");
+}
@@ -0,0 +1,14 @@
+//! Suggestions whose texts borrow the input can't outlive it, see the lifetimes section
+//! in the crate documentation. Texts from a longer lived registry could.
+
+use brigadier::suggestion::SuggestionsBuilder;
+
+fn main() {
+    let suggestions = {
+        let input = String::from("warp s");
+        let mut builder: SuggestionsBuilder = SuggestionsBuilder::new_with_lowercase(&input, 5);
+        builder.suggest_text(&input[5..]);
+        builder.build()
+    };
+    println!("{}", suggestions.len());
+}
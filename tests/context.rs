@@ -0,0 +1,250 @@
+use brigadier::{
+    arguments::{ArgumentType, BoolArgumentType},
+    builder::{argument, literal},
+    context::{CommandContextBuilder, ParsedArgument, ParsedCommandNode, StringRangeExt},
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    CommandSource, StringReader,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Source(u32);
+
+impl CommandSource for Source {}
+
+#[test]
+fn build_context_from_parse() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    toggle.then(argument("value", BoolArgumentType));
+    dispatcher.register(toggle);
+
+    let input = "toggle false";
+    let parse = dispatcher.parse(input, Source(1));
+    let context = parse.context().clone().build(input);
+    assert_eq!(context.source(), &Source(1));
+    assert!(context.has_nodes());
+    assert_eq!(context.get_argument("value"), Some(&false));
+    assert_eq!(context.get_argument("missing"), None);
+    assert!(context.child().is_none());
+
+    let copy = context.clone_for(Source(2));
+    assert_eq!(copy.source(), &Source(2));
+    assert_eq!(copy.get_argument("value"), Some(&false));
+}
+
+#[test]
+fn build_context_with_child() {
+    let dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut child =
+        CommandContextBuilder::<_, BoolArgumentType>::new(Source(2), dispatcher.root(), 0);
+    child.with_argument(
+        "value",
+        ParsedArgument {
+            range: 0..4,
+            result: true,
+        },
+    );
+    let mut parent = CommandContextBuilder::new(Source(1), dispatcher.root(), 0);
+    parent.with_child(child);
+
+    let context = parent.build("true");
+    assert!(!context.has_nodes());
+    assert_eq!(context.get_argument("value"), None);
+    let child = context.child().unwrap();
+    assert_eq!(child.source(), &Source(2));
+    assert_eq!(child.get_argument("value"), Some(&true));
+}
+
+struct Token;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Token {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string()
+    }
+}
+
+#[test]
+fn find_node_and_argument_at_cursor() {
+    let mut dispatcher = CommandDispatcher::<Source, Token>::new();
+    let mut give = literal("give");
+    let mut player = argument("player", Token);
+    player.then(argument("count", Token));
+    give.then(player);
+    dispatcher.register(give);
+    let give = dispatcher.find_node(&["give"]).unwrap();
+    let player = dispatcher.find_node(&["give", "player"]).unwrap();
+    let count = dispatcher.find_node(&["give", "player", "count"]).unwrap();
+
+    let input = "give player 64 ";
+    let parse = dispatcher.parse(input, Source(1));
+    let context = parse.context().clone().build(input);
+    let expected = [
+        (0, Some(give), None),
+        (2, Some(give), None),
+        (4, Some(give), None),
+        (5, Some(player), Some("player")),
+        (8, Some(player), Some("player")),
+        (11, Some(player), Some("player")),
+        (12, Some(count), Some("count")),
+        (14, Some(count), Some("count")),
+        (15, Some(count), Some("count")),
+    ];
+    for (cursor, node, argument) in expected {
+        assert_eq!(context.node_at(cursor), node, "cursor {cursor}");
+        assert_eq!(
+            context.find_argument_at(cursor).map(|(name, _)| name),
+            argument,
+            "cursor {cursor}"
+        );
+    }
+    let (_, count) = context.find_argument_at(13).unwrap();
+    assert_eq!(count.range, 12..14);
+    assert_eq!(count.range.get_text(input), "64");
+    assert_eq!(count.result, "64");
+    assert_eq!(count.range.length(), 2);
+    assert!(!count.range.is_empty());
+
+    // The text doesn't depend on the cursor of the reader
+    let mut reader = StringReader::new(input);
+    reader.set_cursor(count.range.end);
+    assert_eq!(count.range.get_reader_text(&reader), "64");
+    assert_eq!(count.range.get_reader_text(parse.reader()), "64");
+}
+
+#[test]
+fn context_accessors() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    let mut value = argument("value", BoolArgumentType);
+    value.executes(|_| Ok(1));
+    toggle.then(value);
+    dispatcher.register(toggle);
+    let root = dispatcher.root();
+    let mut each = literal::<Source, BoolArgumentType>("each");
+    each.fork(root, |context| Ok(vec![context.source().clone(); 2]));
+    dispatcher.register(each);
+
+    let input = "each toggle true";
+    let parse = dispatcher.parse(input, Source(1));
+    let context = parse.context().clone().build(input);
+    assert_eq!(context.input(), input);
+    assert_eq!(context.range(), 0..4);
+    assert!(context.command().is_none());
+    assert!(context.redirect_modifier().is_some());
+    assert!(context.is_forked());
+
+    let child = context.child().unwrap();
+    assert_eq!(child.input(), input);
+    assert_eq!(child.range(), 5..16);
+    assert!(child.command().is_some());
+    assert!(child.redirect_modifier().is_none());
+    assert!(!child.is_forked());
+}
+
+#[test]
+fn parsed_nodes() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    toggle.then(argument("value", BoolArgumentType));
+    dispatcher.register(toggle);
+    let root = dispatcher.root();
+    let mut run = literal::<Source, BoolArgumentType>("run");
+    run.redirect(root);
+    dispatcher.register(run);
+
+    let input = "run toggle true";
+    let parse = dispatcher.parse(input, Source(1));
+    let context = parse.context().clone().build(input);
+    assert_eq!(
+        context.nodes(),
+        [ParsedCommandNode {
+            node: dispatcher.find_node(&["run"]).unwrap(),
+            range: 0..3,
+        }]
+    );
+    assert_eq!(
+        context.child().unwrap().nodes(),
+        [
+            ParsedCommandNode {
+                node: dispatcher.find_node(&["toggle"]).unwrap(),
+                range: 4..10,
+            },
+            ParsedCommandNode {
+                node: dispatcher.find_node(&["toggle", "value"]).unwrap(),
+                range: 11..15,
+            },
+        ]
+    );
+}
+
+#[test]
+fn last_child_follows_redirects() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    let mut value = argument("value", BoolArgumentType);
+    value.executes(|_| Ok(1));
+    toggle.then(value);
+    dispatcher.register(toggle);
+    let root = dispatcher.root();
+    let mut each = literal::<Source, BoolArgumentType>("each");
+    each.fork(root, |context| Ok(vec![context.source().clone(); 2]));
+    dispatcher.register(each);
+    let mut run = literal::<Source, BoolArgumentType>("run");
+    run.redirect(root);
+    dispatcher.register(run);
+
+    let input = "each run each toggle true";
+    let parse = dispatcher.parse(input, Source(1));
+    assert_eq!(parse.context().last_child().range(), 14..25);
+    let context = parse.context().clone().build(input);
+    let last = context.last_child();
+    assert_eq!(last.range(), 14..25);
+    assert!(last.command().is_some());
+    assert!(last.child().is_none());
+    assert_eq!(last.last_child().range(), last.range());
+
+    let input = "toggle true";
+    let context = dispatcher
+        .parse(input, Source(1))
+        .context()
+        .clone()
+        .build(input);
+    assert_eq!(context.last_child().range(), context.range());
+}
+
+#[test]
+fn iterate_redirect_chain() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    toggle.then_build(argument("value", BoolArgumentType), |value| {
+        value.executes(|_| Ok(1))
+    });
+    dispatcher.register(toggle);
+    let root = dispatcher.root();
+    let mut run = literal::<Source, BoolArgumentType>("run");
+    run.redirect(root);
+    dispatcher.register(run);
+    let mut each = literal::<Source, BoolArgumentType>("each");
+    each.fork(root, |context| Ok(vec![context.source().clone(); 2]));
+    dispatcher.register(each);
+
+    let input = "run each toggle true";
+    let context = dispatcher
+        .parse(input, Source(1))
+        .context()
+        .clone()
+        .build(input);
+    let ranges: Vec<_> = context
+        .iter_chain()
+        .map(|context| context.range())
+        .collect();
+    assert_eq!(ranges, vec![0..3, 4..8, 9..20]);
+    assert_eq!(context.depth(), 3);
+    assert_eq!(context.child().unwrap().depth(), 2);
+    assert_eq!(context.last_child().depth(), 1);
+    assert!(context.last_child().command().is_some());
+}
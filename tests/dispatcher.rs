@@ -0,0 +1,536 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use brigadier::{
+    arguments::{ArgumentDescriptor, BoolArgumentType, DoubleArgumentType, StringArgumentType},
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::{CommandDispatcher, Tokens, DEFAULT_MAX_DEPTH},
+    errors::{CommandErrorType, CommandSyntaxError},
+    suggestion::SuggestionsBuilder,
+    tree::CommandNodeType,
+    Shared,
+};
+
+use common::{toggle_dispatcher, Source};
+
+#[test]
+fn parse_complete_command() {
+    let dispatcher = toggle_dispatcher();
+    let parse = dispatcher.parse("toggle true", Source);
+    assert!(!parse.reader().can_read());
+    assert!(parse.exceptions().is_empty());
+    let context = parse.context();
+    assert_eq!(context.range(), 0..11);
+    let ranges: Vec<_> = context.nodes().iter().map(|n| n.range.clone()).collect();
+    assert_eq!(ranges, vec![0..6, 7..11]);
+    let value = &context.arguments()["value"];
+    assert_eq!(value.range, 7..11);
+    assert!(value.result);
+}
+
+#[test]
+fn parse_partial_command() {
+    let dispatcher = toggle_dispatcher();
+    let parse = dispatcher.parse("toggle maybe", Source);
+    assert_eq!(parse.reader().cursor(), 7);
+    assert_eq!(parse.context().nodes().len(), 1);
+    let errors: Vec<_> = parse.exceptions().values().collect();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].error_type,
+        CommandErrorType::ReaderInvalidBool(_)
+    ));
+}
+
+#[test]
+fn parse_unknown_command() {
+    let dispatcher = toggle_dispatcher();
+    let parse = dispatcher.parse("togglez", Source);
+    assert_eq!(parse.reader().cursor(), 0);
+    assert!(parse.context().nodes().is_empty());
+    let errors: Vec<_> = parse.exceptions().values().collect();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0].error_type,
+        CommandErrorType::LiteralIncorrect { .. }
+    ));
+}
+
+#[test]
+fn literal_needs_token_boundary() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut run = literal("run");
+    run.then_build(literal("stop"), |stop| stop.executes(|_| Ok(1)));
+    dispatcher.register(run);
+
+    let parse = dispatcher.parse("run stopper", Source);
+    assert_eq!(parse.context().nodes().len(), 1);
+    let errors: Vec<_> = parse.exceptions().values().collect();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        &errors[0].error_type,
+        CommandErrorType::LiteralIncorrect { expected } if &**expected == "stop"
+    ));
+    // The error points at the start of the literal
+    assert_eq!(errors[0].context.as_ref().unwrap().cursor(), 4);
+    assert_eq!(parse.reader().cursor(), 4);
+
+    assert_eq!(dispatcher.execute_input("run stop", Source), Ok(1));
+    let parse = dispatcher.parse("run stop ", Source);
+    assert_eq!(parse.context().nodes().len(), 2);
+}
+
+#[test]
+fn parse_redirect() {
+    let mut dispatcher = toggle_dispatcher();
+    let toggle = dispatcher.register(literal("toggle"));
+    let mut alias = literal("alias");
+    alias.redirect(toggle);
+    dispatcher.register(alias);
+
+    let parse = dispatcher.parse("alias true", Source);
+    assert!(!parse.reader().can_read());
+    let child = parse
+        .context()
+        .child()
+        .expect("redirect creates a child context");
+    assert_eq!(child.root(), toggle);
+    assert_eq!(child.range(), 6..10);
+    assert!(child.arguments()["value"].result);
+}
+
+#[test]
+fn list_node_suggestions() {
+    let mut dispatcher = toggle_dispatcher();
+    let toggle = dispatcher.register(literal("Toggle"));
+    let value = dispatcher
+        .tree_mut()
+        .add_node(argument("value", BoolArgumentType));
+    let root = dispatcher.root();
+
+    let input = "tog";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let tree = dispatcher.tree();
+    let suggest = |node, start| {
+        let builder = SuggestionsBuilder::new_with_lowercase(input, start);
+        pollster::block_on(tree.list_suggestions(node, &context, builder))
+            .unwrap()
+            .texts()
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(suggest(toggle, 0), vec!["Toggle"]);
+    assert_eq!(suggest(toggle, 1), Vec::<String>::new());
+    assert!(suggest(root, 0).is_empty());
+
+    let input = "toggle f";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, 7);
+    let suggestions = pollster::block_on(tree.list_suggestions(value, &context, builder)).unwrap();
+    assert_eq!(suggestions.texts().collect::<Vec<_>>(), vec!["false"]);
+    assert_eq!(suggestions.range(), 7..8);
+}
+
+#[test]
+fn hover_info() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut speed = literal("speed");
+    let mut value = argument("value", BoolArgumentType);
+    value.describe("Whether the speed is relative");
+    speed.then(value);
+    dispatcher.register(speed);
+
+    let parse = dispatcher.parse("speed true", Source);
+    let hover = parse.hover_info(dispatcher.tree(), 8).unwrap();
+    assert_eq!(hover.name, "value");
+    assert_eq!(hover.kind, CommandNodeType::Argument);
+    assert_eq!(hover.description, Some("Whether the speed is relative"));
+    assert_eq!(hover.argument_type, Some(ArgumentDescriptor::new("bool")));
+    assert_eq!(hover.range, 6..10);
+    assert_eq!(hover.text, "true");
+    assert_eq!(hover.value.as_deref(), Some("true"));
+
+    let hover = parse.hover_info(dispatcher.tree(), 0).unwrap();
+    assert_eq!(hover.name, "speed");
+    assert_eq!(hover.kind, CommandNodeType::Literal);
+    assert_eq!(hover.description, None);
+    assert_eq!(hover.argument_type, None);
+    assert_eq!(hover.value, None);
+
+    // The end of a token and the spaces after it still belong to it
+    assert_eq!(parse.hover_info(dispatcher.tree(), 5).unwrap().name, "speed");
+    assert_eq!(parse.hover_info(dispatcher.tree(), 10).unwrap().name, "value");
+}
+
+#[test]
+fn register_aliases() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut teleport = literal("teleport");
+    teleport
+        .aliases(["tp", "warp"])
+        .then(argument("value", BoolArgumentType));
+    let teleport = dispatcher.register(teleport);
+    dispatcher.register_alias("move", teleport);
+
+    let root = dispatcher.root();
+    assert_eq!(
+        dispatcher.tree().get_all_usage(root, &Source, false),
+        vec!["move -> teleport", "tp -> teleport", "warp -> teleport"]
+    );
+    for alias in ["tp", "warp", "move"] {
+        let input = format!("{alias} true");
+        let parse = dispatcher.parse(&input, Source);
+        assert!(
+            !parse.reader().can_read(),
+            "{input} should parse completely"
+        );
+        let child = parse.context().child().unwrap();
+        assert_eq!(
+            child.arguments()["value"].range,
+            alias.len() + 1..input.len()
+        );
+    }
+}
+
+#[test]
+fn parse_tokens() {
+    let mut dispatcher = CommandDispatcher::<Source, DoubleArgumentType>::new();
+    let mut speed = literal("speed");
+    speed.then(argument("value", DoubleArgumentType::new(0.0..=10.0)));
+    let speed = dispatcher.register(speed);
+    dispatcher.register_alias("sp", speed);
+
+    let tokens = Tokens::new(["speed", "2.5"]);
+    assert_eq!(tokens.joined(), "speed 2.5");
+    let context = dispatcher.parse_tokens(&tokens, Source).unwrap();
+    let value = &context.arguments()["value"];
+    assert_eq!(value.result, 2.5);
+    assert_eq!(value.range, 6..9);
+
+    let tokens = Tokens::new(["sp", "4"]);
+    let context = dispatcher.parse_tokens(&tokens, Source).unwrap();
+    assert_eq!(context.child().unwrap().arguments()["value"].result, 4.0);
+
+    let tokens = Tokens::new(["speed", "12"]);
+    let error = dispatcher.parse_tokens(&tokens, Source).err().unwrap();
+    assert_eq!(error.token, 1);
+    assert!(matches!(
+        error.error.error_type,
+        CommandErrorType::DoubleTooBig { .. }
+    ));
+    assert_eq!(error.error.context.unwrap().cursor, 0);
+
+    let tokens = Tokens::new(["speed", "1 2"]);
+    let error = dispatcher.parse_tokens(&tokens, Source).err().unwrap();
+    assert_eq!(error.token, 1);
+    assert_eq!(
+        error.error.error_type,
+        CommandErrorType::DispatcherExpectedArgumentSeparator
+    );
+    assert_eq!(error.error.context.unwrap().cursor, 1);
+
+    let tokens = Tokens::new(["speed", "1", "2"]);
+    let error = dispatcher.parse_tokens(&tokens, Source).err().unwrap();
+    assert_eq!(error.token, 2);
+    assert_eq!(
+        error.error.error_type,
+        CommandErrorType::DispatcherUnknownArgument
+    );
+}
+
+#[test]
+fn execute_tokens_with_greedy_tail() {
+    let mut dispatcher = CommandDispatcher::<Source, StringArgumentType>::new();
+    let said = Arc::new(Mutex::new(String::new()));
+    let mut say = literal("say");
+    let message = Arc::clone(&said);
+    say.then_build(
+        argument("message", StringArgumentType::greedy_string()),
+        |argument| {
+            argument.executes(move |context| {
+                *message.lock().unwrap() = context.get_argument("message").unwrap().to_string();
+                Ok(1)
+            })
+        },
+    );
+    dispatcher.register(say);
+    let mut name = literal("name");
+    name.then_build(argument("value", StringArgumentType::word()), |value| {
+        value.executes(|_| Ok(2))
+    });
+    dispatcher.register(name);
+
+    let tokens = Tokens::new(["say", "hello", "world"]);
+    assert_eq!(dispatcher.execute_tokens(&tokens, Source), Ok(1));
+    assert_eq!(*said.lock().unwrap(), "hello world");
+    // Tokens are used verbatim, quotes and spaces included
+    let tokens = Tokens::new(["say", "\"hi\"  there", "!"]);
+    assert_eq!(dispatcher.execute_tokens(&tokens, Source), Ok(1));
+    assert_eq!(*said.lock().unwrap(), "\"hi\"  there !");
+
+    let tokens = Tokens::new(["name", "alex"]);
+    assert_eq!(dispatcher.execute_tokens(&tokens, Source), Ok(2));
+    let tokens = Tokens::new(["name", "a b"]);
+    let error = dispatcher.execute_tokens(&tokens, Source).unwrap_err();
+    assert_eq!(error.token, 1);
+    assert_eq!(error.error.context.unwrap().cursor, 1);
+    // Errors of the execution are at the last token
+    let tokens = Tokens::new(["name"]);
+    let error = dispatcher.execute_tokens(&tokens, Source).unwrap_err();
+    assert_eq!(error.token, 0);
+    assert_eq!(
+        error.error.error_type,
+        CommandErrorType::DispatcherUnknownCommand
+    );
+    assert_eq!(error.error.context.unwrap().cursor, 4);
+}
+
+#[test]
+fn get_path_and_find_node() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut time = literal("time");
+    let mut set = literal("set");
+    set.then(argument("value", BoolArgumentType));
+    time.then(literal("query")).then(set);
+    dispatcher.register(time);
+
+    let value = dispatcher.find_node(&["time", "set", "value"]).unwrap();
+    assert_eq!(dispatcher.get_path(value), vec!["time", "set", "value"]);
+    let time = dispatcher.find_node(&["time"]).unwrap();
+    assert_eq!(dispatcher.get_path(time), vec!["time"]);
+    assert_eq!(dispatcher.find_node(&[]), Some(dispatcher.root()));
+    assert!(dispatcher.get_path(dispatcher.root()).is_empty());
+    assert_eq!(dispatcher.find_node(&["time", "value"]), None);
+
+    let orphan = dispatcher.tree_mut().add_node(literal("orphan"));
+    assert!(dispatcher.get_path(orphan).is_empty());
+}
+
+#[test]
+fn execute_without_command() {
+    let dispatcher = toggle_dispatcher();
+    let error = dispatcher.execute_input("toggle true", Source).unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::DispatcherUnknownCommand);
+    let error = dispatcher.execute_input("toggle maybe", Source).unwrap_err();
+    assert!(matches!(
+        error.error_type,
+        CommandErrorType::ReaderInvalidBool(_)
+    ));
+}
+
+fn failing_modifier<'i>(
+    _: &CommandContext<'i, Source, BoolArgumentType>,
+) -> Result<Vec<Source>, CommandSyntaxError<'i>> {
+    Err(CommandSyntaxError::new(
+        CommandErrorType::DispatcherParseException(String::from("no targets")),
+    ))
+}
+
+#[test]
+fn execute_fork_swallows_modifier_errors() {
+    let mut dispatcher = toggle_dispatcher();
+    let root = dispatcher.root();
+    let mut all = literal("all");
+    all.fork(root, failing_modifier);
+    dispatcher.register(all);
+    let mut each = literal("each");
+    each.forward(root, Some(Shared::new(failing_modifier)), false);
+    dispatcher.register(each);
+
+    assert_eq!(dispatcher.execute_input("all toggle true", Source), Ok(0));
+    let error = dispatcher
+        .execute_input("each toggle true", Source)
+        .unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherParseException(String::from("no targets"))
+    );
+}
+
+#[test]
+fn consumer_reports_failures() {
+    let mut dispatcher = toggle_dispatcher();
+    let root = dispatcher.root();
+    let mut all = literal("all");
+    all.fork(root, failing_modifier);
+    dispatcher.register(all);
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&results);
+    dispatcher.set_consumer(Box::new(move |context, success, result| {
+        let redirected = context.child().is_some();
+        recorded.lock().unwrap().push((redirected, success, result));
+    }));
+
+    assert_eq!(dispatcher.execute_input("all toggle true", Source), Ok(0));
+    assert!(dispatcher.execute_input("toggle true", Source).is_err());
+    assert_eq!(
+        *results.lock().unwrap(),
+        vec![(true, false, 0), (false, false, 0)]
+    );
+}
+
+#[test]
+fn executes_attaches_command() {
+    let mut dispatcher = toggle_dispatcher();
+    let mut answer = literal("answer");
+    answer.executes(|_| Ok(42));
+    let answer = dispatcher.register(answer);
+    assert!(dispatcher.tree().get(answer).unwrap().command().is_some());
+    assert_eq!(dispatcher.execute_input("answer", Source), Ok(42));
+}
+
+fn three_sources<'i>(
+    _: &CommandContext<'i, Source, BoolArgumentType>,
+) -> Result<Vec<Source>, CommandSyntaxError<'i>> {
+    Ok(vec![Source; 3])
+}
+
+#[test]
+fn execute_fork_counts_successes() {
+    let mut dispatcher = toggle_dispatcher();
+    let mut answer = literal("answer");
+    answer.executes(|_| Ok(42));
+    dispatcher.register(answer);
+    let root = dispatcher.root();
+    let mut all = literal("all");
+    all.fork(root, three_sources);
+    dispatcher.register(all);
+    let mut each = literal("each");
+    each.forward(root, Some(Shared::new(three_sources)), false);
+    dispatcher.register(each);
+
+    assert_eq!(dispatcher.execute_input("all answer", Source), Ok(3));
+    assert_eq!(dispatcher.execute_input("each answer", Source), Ok(126));
+}
+
+#[test]
+fn literals_take_precedence_over_arguments() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    let mut literal_true = literal("true");
+    literal_true.executes(|_| Ok(1));
+    toggle.then(literal_true);
+    let mut value = argument("value", BoolArgumentType);
+    value.executes(|_| Ok(2));
+    toggle.then(value);
+    dispatcher.register(toggle);
+
+    let parse = dispatcher.parse("toggle true", Source);
+    let literal_true = dispatcher.find_node(&["toggle", "true"]).unwrap();
+    assert_eq!(parse.context().nodes()[1].node, literal_true);
+    assert!(parse.context().arguments().is_empty());
+    assert_eq!(dispatcher.execute_input("toggle true", Source), Ok(1));
+    assert_eq!(dispatcher.execute_input("toggle false", Source), Ok(2));
+}
+
+/// `loop` is executable and redirects to the root, so it can be repeated indefinitely.
+fn redirect_cycle() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let root = dispatcher.root();
+    let mut repeat = literal("loop");
+    repeat.executes(|_| Ok(1)).redirect(root);
+    dispatcher.register(repeat);
+    dispatcher
+}
+
+#[test]
+fn max_depth_stops_redirect_cycles() {
+    let mut dispatcher = redirect_cycle();
+    assert_eq!(dispatcher.max_depth(), DEFAULT_MAX_DEPTH);
+    let input = vec!["loop"; 1000].join(" ");
+    let error = dispatcher.execute_input(&input, Source).unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherMaxDepthExceeded {
+            max_depth: DEFAULT_MAX_DEPTH
+        }
+    );
+    // The cursor is at the first node after the limit
+    assert_eq!(error.context.unwrap().cursor, DEFAULT_MAX_DEPTH * 5);
+
+    let input = ["loop"; 3].join(" ");
+    assert_eq!(dispatcher.execute_input(&input, Source), Ok(1));
+    dispatcher.set_max_depth(2);
+    assert_eq!(
+        dispatcher.execute_input(&input, Source).unwrap_err().error_type,
+        CommandErrorType::DispatcherMaxDepthExceeded { max_depth: 2 }
+    );
+}
+
+#[test]
+fn max_depth_of_tokens() {
+    let dispatcher = redirect_cycle();
+    let tokens = Tokens::new(vec!["loop"; 1000]);
+    let error = dispatcher.parse_tokens(&tokens, Source).err().unwrap();
+    assert_eq!(error.token, DEFAULT_MAX_DEPTH);
+    assert_eq!(
+        error.error.error_type,
+        CommandErrorType::DispatcherMaxDepthExceeded {
+            max_depth: DEFAULT_MAX_DEPTH
+        }
+    );
+}
+
+#[test]
+fn requires_hides_node() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut public = literal("public");
+    public.executes(|_| Ok(1));
+    dispatcher.register(public);
+    let mut admin = literal("admin");
+    admin.requires(|_: &Source| false).executes(|_| Ok(2));
+    dispatcher.register(admin);
+
+    assert_eq!(dispatcher.execute_input("public", Source), Ok(1));
+    // The restricted literal isn't tried at all
+    let error = dispatcher.execute_input("admin", Source).unwrap_err();
+    assert!(matches!(
+        error.error_type,
+        CommandErrorType::LiteralIncorrect { expected } if &*expected == "public"
+    ));
+    let usage = dispatcher.tree().get_smart_usage(dispatcher.root(), &Source);
+    let names: Vec<_> = usage.into_iter().map(|(_, usage)| usage).collect();
+    assert_eq!(names, vec!["public"]);
+}
+
+#[test]
+fn then_build_nests_builders() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut outer = literal("outer");
+    outer.then_build(literal("inner"), |inner| {
+        inner.then_build(argument("value", BoolArgumentType), |value| {
+            value.executes(|_| Ok(3))
+        })
+    });
+    dispatcher.register(outer);
+    assert!(dispatcher.find_node(&["outer", "inner", "value"]).is_some());
+    assert_eq!(dispatcher.execute_input("outer inner true", Source), Ok(3));
+}
+
+#[test]
+fn build_macros_nest_literals_and_arguments() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut gamerule = literal("gamerule");
+    brigadier::build_literal!(gamerule, "keepInventory", rule => {
+        rule.executes(|_| Ok(0));
+        brigadier::build_argument!(rule, "value", BoolArgumentType, value => {
+            value.executes(|_| Ok(1));
+        });
+    });
+    dispatcher.register(gamerule);
+    assert_eq!(dispatcher.execute_input("gamerule keepInventory", Source), Ok(0));
+    assert_eq!(
+        dispatcher.execute_input("gamerule keepInventory true", Source),
+        Ok(1)
+    );
+}
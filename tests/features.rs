@@ -0,0 +1,144 @@
+//! Checks that the core modules only refer to optional subsystems behind the features
+//! enabling them, see `Cargo.toml`. `scripts/check-features.sh` builds the features.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The modules of optional subsystems and the features they are enabled by.
+const OPTIONAL_MODULES: &[(&str, &str)] = &[
+    ("help", "help"),
+    ("history", "history"),
+    ("palette", "palette"),
+    ("testing", "testing"),
+    ("tree::protocol", "packet"),
+    ("tree::serialize", "serde"),
+    ("tree::snapshot", "testing"),
+];
+
+/// A `use` or `mod` item with the attributes before it.
+struct Item {
+    line: usize,
+    attributes: Vec<String>,
+    text: String,
+}
+
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            source_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// The path of the module a file defines, e.g. `tree::protocol`.
+fn module_path(src: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(src).unwrap().with_extension("");
+    let path = relative
+        .iter()
+        .map(|segment| segment.to_str().unwrap())
+        .collect::<Vec<_>>()
+        .join("::");
+    if path == "lib" {
+        String::new()
+    } else {
+        path
+    }
+}
+
+fn is_in_module(path: &str, module: &str) -> bool {
+    path == module || path.starts_with(&format!("{module}::"))
+}
+
+/// Collects the `use` and `mod` items, which may span several lines.
+fn items(source: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut attributes = Vec::new();
+    let mut current: Option<Item> = None;
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if let Some(item) = &mut current {
+            item.text.push_str(line);
+        } else if line.starts_with("#[") {
+            attributes.push(line.to_owned());
+            continue;
+        } else if is_import(line) || is_module(line) && !line.ends_with('{') {
+            current = Some(Item {
+                line: index + 1,
+                attributes: std::mem::take(&mut attributes),
+                text: line.to_owned(),
+            });
+        }
+        attributes.clear();
+        if line.ends_with(';') {
+            items.extend(current.take());
+        }
+    }
+    items
+}
+
+fn declaration(line: &str) -> &str {
+    line.strip_prefix("pub(crate) ")
+        .or_else(|| line.strip_prefix("pub "))
+        .unwrap_or(line)
+}
+
+fn is_import(line: &str) -> bool {
+    declaration(line).starts_with("use ")
+}
+
+fn is_module(line: &str) -> bool {
+    declaration(line).starts_with("mod ")
+}
+
+fn mentions(text: &str, identifier: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| word == identifier)
+}
+
+#[test]
+fn core_modules_gate_optional_imports() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut files = Vec::new();
+    source_files(&src, &mut files);
+    let mut violations = Vec::new();
+    for file in files {
+        let path = module_path(&src, &file);
+        let optional = OPTIONAL_MODULES
+            .iter()
+            .filter(|(module, _)| !is_in_module(&path, module));
+        let source = fs::read_to_string(&file).unwrap();
+        for (module, feature) in optional {
+            let (parent, name) = module.rsplit_once("::").unwrap_or(("", module));
+            let gate = format!("feature = \"{feature}\"");
+            for item in items(&source) {
+                let declares = is_module(&item.text) && path == parent;
+                let imports = is_import(&item.text) && mentions(&item.text, name);
+                let gated = item
+                    .attributes
+                    .iter()
+                    .any(|attribute| attribute.starts_with("#[cfg(") && attribute.contains(&gate));
+                if (declares && mentions(&item.text, name) || imports) && !gated {
+                    violations.push(format!("{}:{}: {}", file.display(), item.line, item.text));
+                }
+            }
+            // Paths in code aren't covered by the gates of the imports
+            let qualified = format!("crate::{module}::");
+            for (index, line) in source.lines().enumerate() {
+                let line = line.trim();
+                if line.contains(&qualified) && !line.starts_with("//") && !is_import(line) {
+                    violations.push(format!("{}:{}: {}", file.display(), index + 1, line));
+                }
+            }
+        }
+    }
+    assert!(
+        violations.is_empty(),
+        "Optional modules used without their feature:\n{}",
+        violations.join("\n")
+    );
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "help")]
+
+mod common;
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    help::HelpSystem,
+};
+
+use common::Source;
+
+fn dispatcher() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    for name in ["a", "b", "c", "d", "e"] {
+        let mut command = literal(name);
+        command.executes(|_| Ok(1));
+        dispatcher.register(command);
+    }
+    let mut teleport = literal("teleport");
+    teleport.describe("Moves the player");
+    teleport.executes(|_| Ok(1));
+    let mut relative = argument("relative", BoolArgumentType);
+    relative.executes(|_| Ok(1));
+    teleport.then(relative);
+    let teleport = dispatcher.register(teleport);
+    dispatcher.register_alias("tp", teleport);
+    dispatcher
+}
+
+#[test]
+fn help_pages() {
+    let dispatcher = dispatcher();
+    let help = HelpSystem::new(3);
+    let names = |page: usize| -> Vec<String> {
+        help.render_page(&dispatcher, &Source, page)
+            .entries
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    };
+    assert_eq!(names(1), ["a", "b", "c"]);
+    assert_eq!(names(2), ["d", "e", "teleport"]);
+    // The last page is only partially filled
+    assert_eq!(names(3), ["tp"]);
+    assert!(names(4).is_empty());
+    assert!(names(0).is_empty());
+
+    let page = help.render_page(&dispatcher, &Source, 3);
+    assert_eq!(page.page, 3);
+    assert_eq!(page.total_pages, 3);
+    assert_eq!(
+        page.to_string(),
+        "--- Showing help page 3 of 3 ---\n/tp -> teleport"
+    );
+    assert_eq!(HelpSystem::new(7).render_page(&dispatcher, &Source, 1).total_pages, 1);
+}
+
+#[test]
+fn help_page_annotates_aliases() {
+    let dispatcher = dispatcher();
+    let page = HelpSystem::new(10).render_page(&dispatcher, &Source, 1);
+    let teleport = &page.entries[5];
+    assert_eq!(teleport.usage, "teleport [<relative>]");
+    assert_eq!(teleport.alias_of, None);
+    assert_eq!(teleport.description.as_deref(), Some("Moves the player"));
+    let tp = &page.entries[6];
+    assert_eq!(tp.usage, "tp -> teleport");
+    assert_eq!(tp.alias_of.as_deref(), Some("teleport"));
+}
+
+#[test]
+fn help_command() {
+    let dispatcher = dispatcher();
+    let help = HelpSystem::new(10);
+    let teleport = help.render_command(&dispatcher, &Source, "teleport").unwrap();
+    assert_eq!(teleport.usages, ["teleport", "teleport <relative>"]);
+    assert_eq!(teleport.aliases, ["tp"]);
+    assert_eq!(teleport.examples, ["teleport", "teleport true"]);
+    assert_eq!(
+        teleport.to_string(),
+        "--- Help: /teleport ---\n\
+         Moves the player\n\
+         /teleport\n\
+         /teleport <relative>\n\
+         Aliases: tp\n\
+         Example: /teleport\n\
+         Example: /teleport true"
+    );
+    // Aliases are resolved to their command
+    assert_eq!(help.render_command(&dispatcher, &Source, "tp"), Some(teleport));
+    assert_eq!(help.render_command(&dispatcher, &Source, "unknown"), None);
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "history")]
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    history::{SuggestionHistory, RECENT_TOOLTIP},
+    suggestion::{StaticSuggestions, SuggestionsBuilder},
+    CommandSource, Shared, StringReader,
+};
+
+#[derive(Clone)]
+struct Source {
+    player: u64,
+}
+
+impl CommandSource for Source {}
+
+struct Word;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Word {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string()
+    }
+}
+
+fn fail<'i>(_context: &CommandContext<'i, Source, Word>) -> Result<i32, CommandSyntaxError<'i>> {
+    Err(CommandSyntaxError::new(
+        CommandErrorType::DispatcherUnknownCommand,
+    ))
+}
+
+/// `warp <name>` suggesting from the history, `fail <name>` which always fails and
+/// `login <password>` whose argument is sensitive.
+fn dispatcher(
+    capacity_per_key: usize,
+) -> (CommandDispatcher<Source, Word>, SuggestionHistory<Source>) {
+    let mut dispatcher = CommandDispatcher::<Source, Word>::new();
+    let history = dispatcher.enable_history(capacity_per_key, |source| source.player);
+    let mut warp = literal("warp");
+    let warps = StaticSuggestions::new(["spawn", "shop", "home"]);
+    let mut name = argument("name", Word);
+    name.suggests(Shared::new(history.with_history(warps)))
+        .executes(|_| Ok(1));
+    warp.then(name);
+    dispatcher.register(warp);
+    let mut failing = literal("fail");
+    let mut name = argument("name", Word);
+    name.executes(fail);
+    failing.then(name);
+    dispatcher.register(failing);
+    let mut login = literal("login");
+    let mut password = argument("password", Word);
+    password.executes(|_| Ok(1)).sensitive();
+    login.then(password);
+    dispatcher.register(login);
+    (dispatcher, history)
+}
+
+fn recent(dispatcher: &CommandDispatcher<Source, Word>, path: &[&str], player: u64) -> Vec<String> {
+    let node = dispatcher.find_node(path).unwrap();
+    dispatcher.history().unwrap().recent(player, node)
+}
+
+#[test]
+fn records_successful_commands_only() {
+    let (dispatcher, history) = dispatcher(5);
+    assert_eq!(
+        dispatcher.execute_input("warp home", Source { player: 1 }),
+        Ok(1)
+    );
+    assert!(dispatcher
+        .execute_input("fail home", Source { player: 1 })
+        .is_err());
+    assert!(dispatcher
+        .execute_input("warp", Source { player: 1 })
+        .is_err());
+    assert_eq!(recent(&dispatcher, &["warp", "name"], 1), vec!["home"]);
+    assert!(recent(&dispatcher, &["fail", "name"], 1).is_empty());
+    assert!(recent(&dispatcher, &["warp", "name"], 2).is_empty());
+    assert_eq!(history.len(), 1);
+}
+
+#[test]
+fn evicts_least_recently_used() {
+    let (dispatcher, history) = dispatcher(2);
+    for input in ["warp a", "warp b", "warp a", "warp c"] {
+        dispatcher
+            .execute_input(input, Source { player: 1 })
+            .unwrap();
+    }
+    assert_eq!(recent(&dispatcher, &["warp", "name"], 1), vec!["c", "a"]);
+
+    dispatcher
+        .execute_input("warp d", Source { player: 2 })
+        .unwrap();
+    history.set_max_entries(2);
+    assert_eq!(recent(&dispatcher, &["warp", "name"], 1), vec!["c"]);
+    assert_eq!(recent(&dispatcher, &["warp", "name"], 2), vec!["d"]);
+    dispatcher
+        .execute_input("warp e", Source { player: 2 })
+        .unwrap();
+    assert!(recent(&dispatcher, &["warp", "name"], 1).is_empty());
+    assert_eq!(history.len(), 2);
+}
+
+#[test]
+fn never_records_sensitive_arguments() {
+    let (dispatcher, history) = dispatcher(5);
+    assert_eq!(
+        dispatcher.execute_input("login hunter2", Source { player: 1 }),
+        Ok(1)
+    );
+    assert!(recent(&dispatcher, &["login", "password"], 1).is_empty());
+    assert!(history.is_empty());
+    let password = dispatcher.find_node(&["login", "password"]).unwrap();
+    assert!(dispatcher.tree().get(password).unwrap().is_sensitive());
+}
+
+#[test]
+fn suggests_recent_values_first() {
+    let (dispatcher, _) = dispatcher(5);
+    for input in ["warp shop", "warp sunset", "warp home"] {
+        dispatcher
+            .execute_input(input, Source { player: 1 })
+            .unwrap();
+    }
+    let name = dispatcher.find_node(&["warp", "name"]).unwrap();
+    let suggest = |input: &str| {
+        let context = dispatcher
+            .parse(input, Source { player: 1 })
+            .context()
+            .clone()
+            .build(input);
+        let builder = SuggestionsBuilder::new_with_lowercase(input, 5);
+        pollster::block_on(dispatcher.tree().list_suggestions(name, &context, builder)).unwrap()
+    };
+    let suggestions = suggest("warp s");
+    let texts: Vec<_> = suggestions.texts().collect();
+    // Recent values, most recent first, then the other suggestions without duplicates
+    assert_eq!(texts, vec!["sunset", "shop", "spawn"]);
+    let tooltips: Vec<_> = suggestions.iter().map(|s| s.tooltip.as_deref()).collect();
+    assert_eq!(
+        tooltips,
+        vec![Some(RECENT_TOOLTIP), Some(RECENT_TOOLTIP), None]
+    );
+    assert_eq!(suggestions.range(), 5..6);
+
+    let texts: Vec<String> = suggest("warp ").texts().map(str::to_owned).collect();
+    assert_eq!(texts, vec!["home", "sunset", "shop", "spawn"]);
+}
+
+#[test]
+fn history_key_captures_state() {
+    // Every player of a team shares the history of the team
+    let team_size = 10;
+    let history = SuggestionHistory::new(5, move |source: &Source| source.player / team_size);
+    let node = CommandDispatcher::<Source, Word>::new().root();
+    assert_eq!(history.key(&Source { player: 3 }), 0);
+    assert_eq!(history.key(&Source { player: 14 }), 1);
+    history.record(history.key(&Source { player: 12 }), node, "home");
+    assert_eq!(history.recent(1, node), vec!["home"]);
+    assert!(history.recent(0, node).is_empty());
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    context::CommandContextBuilder,
+    dispatcher::{CommandDispatcher, ParseResults},
+    errors::CommandSyntaxError,
+    StringReader,
+};
+
+use common::Source;
+
+/// Reads a word and counts how often it was parsed.
+struct Counting(Arc<AtomicUsize>);
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Counting {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        reader.read_unquoted_string()
+    }
+}
+
+/// `run <a> <b> <c> <d>` and `alias` redirecting to `run`.
+fn dispatcher(parses: &Arc<AtomicUsize>) -> CommandDispatcher<Source, Counting> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut d = argument("d", Counting(Arc::clone(parses)));
+    d.executes(|_| Ok(1));
+    let mut c = argument("c", Counting(Arc::clone(parses)));
+    c.then(d);
+    let mut b = argument("b", Counting(Arc::clone(parses)));
+    b.then(c);
+    let mut a = argument("a", Counting(Arc::clone(parses)));
+    a.then(b);
+    let mut run = literal("run");
+    run.then(a);
+    let run = dispatcher.register(run);
+    let mut alias = literal("alias");
+    alias.redirect(run);
+    dispatcher.register(alias);
+    dispatcher
+}
+
+fn assert_same_context(
+    a: &CommandContextBuilder<'_, Source, Counting>,
+    b: &CommandContextBuilder<'_, Source, Counting>,
+) {
+    assert_eq!(a.root(), b.root());
+    assert_eq!(a.range(), b.range());
+    assert_eq!(a.nodes(), b.nodes());
+    assert_eq!(a.arguments(), b.arguments());
+    assert_eq!(a.command().is_some(), b.command().is_some());
+    match (a.child(), b.child()) {
+        (Some(a), Some(b)) => assert_same_context(a, b),
+        (a, b) => assert_eq!(a.is_some(), b.is_some()),
+    }
+}
+
+fn assert_same(a: &ParseResults<'_, Source, Counting>, b: &ParseResults<'_, Source, Counting>) {
+    assert_same_context(a.context(), b.context());
+    assert_eq!(a.reader().cursor(), b.reader().cursor());
+    let mut a_errors: Vec<_> = a.exceptions().keys().collect();
+    let mut b_errors: Vec<_> = b.exceptions().keys().collect();
+    a_errors.sort();
+    b_errors.sort();
+    assert_eq!(a_errors, b_errors);
+}
+
+/// Parses `new` incrementally after `old`, returns the number of argument parses.
+fn reparse(old: &str, new: &str) -> usize {
+    let parses = Arc::new(AtomicUsize::new(0));
+    let dispatcher = dispatcher(&parses);
+    let previous = dispatcher.parse(old, Source);
+    parses.store(0, Ordering::SeqCst);
+    let incremental = dispatcher.parse_incremental(&previous, new, Source);
+    let count = parses.load(Ordering::SeqCst);
+    assert_same(&incremental, &dispatcher.parse(new, Source));
+    count
+}
+
+#[test]
+fn appending_reparses_last_node() {
+    assert_eq!(reparse("run 1 2 3 4", "run 1 2 3 45"), 1);
+}
+
+#[test]
+fn edit_in_the_middle() {
+    assert_eq!(reparse("run 1 2 3 4", "run 1 2 x 4"), 2);
+}
+
+#[test]
+fn edit_in_the_middle_of_a_token() {
+    // The prefix ends inside of `23`, so it is parsed again
+    assert_eq!(reparse("run 1 23 3 4", "run 1 24 3 4"), 3);
+}
+
+#[test]
+fn appending_a_node() {
+    // The separator after `3` is new, so `3` is parsed again
+    assert_eq!(reparse("run 1 2 3", "run 1 2 3 4"), 2);
+    assert_eq!(reparse("run 1 2 3 ", "run 1 2 3 4"), 1);
+}
+
+#[test]
+fn deleting_nodes() {
+    assert_eq!(reparse("run 1 2 3 4", "run 1 2"), 1);
+    assert_eq!(reparse("run 1 2 3 4", "run 1"), 1);
+}
+
+#[test]
+fn unrelated_input() {
+    assert_eq!(reparse("run 1 2 3 4", "alias 1"), 1);
+    assert_eq!(reparse("run 1 2", "unknown"), 0);
+}
+
+#[test]
+fn reuses_nodes_after_redirect() {
+    assert_eq!(reparse("alias 1 2 3 4", "alias 1 2 3 45"), 1);
+    assert_eq!(reparse("alias", "alias 1"), 1);
+}
+
+#[test]
+fn ambiguous_siblings_are_parsed_again() {
+    let parses = Arc::new(AtomicUsize::new(0));
+    let mut dispatcher = CommandDispatcher::new();
+    let mut first = argument("first", Counting(Arc::clone(&parses)));
+    first.then(literal("one"));
+    let mut second = argument("second", Counting(Arc::clone(&parses)));
+    second.then(literal("two"));
+    let mut run = literal("run");
+    run.then(first);
+    run.then(second);
+    dispatcher.register(run);
+    let previous = dispatcher.parse("run x one", Source);
+    assert_eq!(previous.context().nodes().len(), 3);
+    let incremental = dispatcher.parse_incremental(&previous, "run x two", Source);
+    assert_same(&incremental, &dispatcher.parse("run x two", Source));
+    assert!(incremental.context().arguments().contains_key("second"));
+}
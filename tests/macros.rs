@@ -0,0 +1,171 @@
+mod common;
+
+use std::collections::HashMap;
+
+use brigadier::{
+    arguments::{DoubleArgumentType, MacroAwareArgumentType, MacroValue, StringArgumentType},
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    StringReader,
+};
+
+use common::Source;
+
+type Type = MacroAwareArgumentType<DoubleArgumentType>;
+
+fn coordinate<'i>(context: &CommandContext<'i, Source, Type>, name: &str) -> f64 {
+    *context.get_argument(name).unwrap().concrete().unwrap()
+}
+
+fn teleport<'i>(
+    context: &CommandContext<'i, Source, Type>,
+) -> Result<i32, CommandSyntaxError<'i>> {
+    Ok((coordinate(context, "x") * 10.0 + coordinate(context, "y")) as i32)
+}
+
+fn dispatcher() -> CommandDispatcher<Source, Type> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut tp = literal("tp");
+    let mut x = argument("x", Type::new(DoubleArgumentType::new(0.0..=10.0)));
+    let mut y = argument("y", Type::new(DoubleArgumentType::new(0.0..=10.0)));
+    y.executes(teleport);
+    x.then(y);
+    tp.then(x);
+    dispatcher.register(tp);
+    dispatcher
+}
+
+fn bindings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+        .collect()
+}
+
+#[test]
+fn read_macro_placeholder() {
+    let mut reader = StringReader::new("$(pos_1) rest");
+    assert!(reader.can_read_macro_placeholder());
+    assert_eq!(reader.read_macro_placeholder(), Ok("pos_1"));
+    assert_eq!(reader.remaining(), " rest");
+
+    let mut reader = StringReader::new("$(abc");
+    let error = reader.read_macro_placeholder().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedEndOfMacro);
+    assert_eq!(error.context.unwrap().cursor, 5);
+
+    let mut reader = StringReader::new("$(a b)");
+    let error = reader.read_macro_placeholder().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedEndOfMacro);
+    assert_eq!(error.context.unwrap().cursor, 3);
+
+    let mut reader = StringReader::new("$()");
+    let error = reader.read_macro_placeholder().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderInvalidMacroName("$()"));
+    assert_eq!(error.context.unwrap().cursor, 0);
+
+    let mut reader = StringReader::new("x");
+    assert!(!reader.can_read_macro_placeholder());
+    assert!(reader.read_macro_placeholder().is_err());
+}
+
+#[test]
+fn parse_placeholders() {
+    let dispatcher = dispatcher();
+    let parse = dispatcher.parse("tp $(x) 3", Source);
+    assert!(parse.exceptions().is_empty());
+    let arguments = parse.context().arguments();
+    assert_eq!(arguments["x"].result, MacroValue::Placeholder("x"));
+    assert_eq!(arguments["x"].range, 3..7);
+    assert_eq!(arguments["y"].result, MacroValue::Concrete(3.0));
+
+    let parse = dispatcher.parse("tp $(x 3", Source);
+    assert_eq!(parse.context().nodes().len(), 1);
+    let error = parse.exceptions().values().next().unwrap();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedEndOfMacro);
+}
+
+#[test]
+fn execute_with_macros() {
+    let dispatcher = dispatcher();
+    let bound = bindings(&[("x", "5"), ("y", "7")]);
+    assert_eq!(dispatcher.execute_with_macros("tp $(x) 3", Source, &bound), Ok(53));
+    assert_eq!(dispatcher.execute_with_macros("tp $(x) $(y)", Source, &bound), Ok(57));
+    assert_eq!(dispatcher.execute_with_macros("tp 1 2", Source, &bound), Ok(12));
+}
+
+#[test]
+fn execute_with_macros_reparses_only_placeholders() {
+    let dispatcher = dispatcher();
+    // Substituting the whole input would parse "tp 1 2 3", which has trailing data
+    let bound = bindings(&[("x", "1 2")]);
+    let error = dispatcher
+        .execute_with_macros("tp $(x) 3", Source, &bound)
+        .unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherExpectedArgumentSeparator
+    );
+    assert_eq!(error.context.unwrap().cursor, 3);
+}
+
+#[test]
+fn execute_with_missing_bindings() {
+    let dispatcher = dispatcher();
+    let bound = bindings(&[("y", "1")]);
+    let error = dispatcher
+        .execute_with_macros("tp $(a) $(b)", Source, &bound)
+        .unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherMissingMacroBinding {
+            names: vec!["a".into(), "b".into()]
+        }
+    );
+    assert_eq!(error.context.as_ref().unwrap().cursor, 3);
+    assert_eq!(
+        error.raw_message(),
+        "No value bound to the macro variables a, b"
+    );
+}
+
+#[test]
+fn execute_with_invalid_binding() {
+    let dispatcher = dispatcher();
+    // The error doesn't borrow the bindings
+    let error = {
+        let bound = bindings(&[("y", "20")]);
+        dispatcher
+            .execute_with_macros("tp 1 $(y)", Source, &bound)
+            .unwrap_err()
+    };
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherInvalidMacroBinding {
+            name: "y".into(),
+            message: "Double must not be more than 10, found 20".into(),
+        }
+    );
+    let context = error.context.unwrap();
+    assert_eq!(context.input, "tp 1 $(y)");
+    assert_eq!(context.cursor, 5);
+}
+
+#[test]
+fn execute_with_string_binding() {
+    type Text = MacroAwareArgumentType<StringArgumentType>;
+    let mut dispatcher = CommandDispatcher::<Source, Text>::new();
+    let mut say = literal("say");
+    let mut message = argument("message", Text::new(StringArgumentType::greedy_string()));
+    message.executes(|context| {
+        let message = context.get_argument("message").unwrap().concrete().unwrap();
+        Ok(message.len() as i32)
+    });
+    say.then(message);
+    dispatcher.register(say);
+
+    let bound = bindings(&[("text", "hello world")]);
+    assert_eq!(dispatcher.execute_with_macros("say $(text)", Source, &bound), Ok(11));
+}
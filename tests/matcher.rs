@@ -0,0 +1,229 @@
+mod common;
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::literal,
+    dispatcher::CommandDispatcher,
+    tree::{matcher::LiteralMatcher, CommandNodeId, LiteralMatching},
+};
+
+use common::Source;
+
+fn ids(count: usize) -> Vec<CommandNodeId> {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    (0..count)
+        .map(|i| dispatcher.register(literal(&format!("n{i}"))))
+        .collect()
+}
+
+#[test]
+fn matcher_exact() {
+    let id = ids(3);
+    let mut matcher = LiteralMatcher::new();
+    matcher.insert("Time", id[0]);
+    matcher.insert("time", id[1]);
+    matcher.insert("timer", id[2]);
+    assert_eq!(matcher.len(), 3);
+    assert_eq!(matcher.exact("TIME"), &[id[0], id[1]]);
+    assert_eq!(matcher.exact("timer"), &[id[2]]);
+    assert!(matcher.exact("tim").is_empty());
+    assert!(matcher.exact("timers").is_empty());
+}
+
+#[test]
+fn matcher_unique_prefix() {
+    let id = ids(3);
+    let mut matcher = LiteralMatcher::new();
+    matcher.insert("teleport", id[0]);
+    matcher.insert("tell", id[1]);
+    matcher.insert("time", id[2]);
+    assert_eq!(matcher.unique_prefix("TELE"), Some(id[0]));
+    assert_eq!(matcher.unique_prefix("tel"), None);
+    assert_eq!(matcher.unique_prefix("ti"), Some(id[2]));
+    assert_eq!(matcher.unique_prefix("time"), Some(id[2]));
+    assert_eq!(matcher.unique_prefix("x"), None);
+    assert_eq!(matcher.unique_prefix(""), None);
+}
+
+#[test]
+fn matcher_completions() {
+    let id = ids(4);
+    let mut matcher = LiteralMatcher::new();
+    matcher.insert("tell", id[0]);
+    matcher.insert("Teleport", id[1]);
+    matcher.insert("time", id[2]);
+    matcher.insert("te", id[3]);
+    assert_eq!(matcher.completions("TE"), vec![id[3], id[1], id[0]]);
+    assert_eq!(matcher.completions(""), vec![id[3], id[1], id[0], id[2]]);
+    assert!(matcher.completions("u").is_empty());
+}
+
+#[test]
+fn matcher_unicode_keys() {
+    let id = ids(3);
+    let mut matcher = LiteralMatcher::new();
+    matcher.insert("Äpfel", id[0]);
+    matcher.insert("İstanbul", id[1]);
+    matcher.insert("ΣΟΦΙΑ", id[2]);
+    assert_eq!(matcher.exact("äPFEL"), &[id[0]]);
+    assert_eq!(matcher.unique_prefix("ä"), Some(id[0]));
+    // 'İ' lowercases to two chars, both typed forms reach the literal
+    assert_eq!(matcher.unique_prefix("İ"), Some(id[1]));
+    assert_eq!(matcher.unique_prefix("i\u{307}s"), Some(id[1]));
+    assert_eq!(matcher.completions("σοφ"), vec![id[2]]);
+}
+
+/// A small deterministic generator, so that failures can be reproduced.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+    fn word(&mut self) -> String {
+        let length = 1 + self.next(5);
+        (0..length)
+            .map(|_| ['a', 'b', 'c', 'é'][self.next(4)])
+            .collect()
+    }
+}
+
+/// The literal which `token` should match according to the documented semantics.
+fn expected(names: &[(&str, CommandNodeId)], token: &str) -> Option<CommandNodeId> {
+    let token = token.to_lowercase();
+    if let Some(&(_, id)) = names.iter().find(|(name, _)| *name == token) {
+        return Some(id);
+    }
+    let mut prefixed = names.iter().filter(|(name, _)| name.starts_with(&token));
+    match (prefixed.next(), prefixed.next()) {
+        (Some(&(_, id)), None) if !token.is_empty() => Some(id),
+        _ => None,
+    }
+}
+
+#[test]
+fn matcher_differential() {
+    let mut random = Lcg(0x5eed);
+    // Small sets use the naive scan, large ones the matcher
+    for count in [3, 8, 20, 60, 200] {
+        let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+        dispatcher.set_literal_matching(LiteralMatching {
+            case_insensitive: true,
+            unique_prefix: true,
+        });
+        let mut words: Vec<String> = (0..count).map(|_| random.word()).collect();
+        words.sort();
+        words.dedup();
+        let names: Vec<_> = words
+            .iter()
+            .map(|word| (word.as_str(), dispatcher.register(literal(word))))
+            .collect();
+        let root = dispatcher.root();
+        for _ in 0..200 {
+            let mut token = random.word();
+            if random.next(2) == 0 {
+                token = token.to_uppercase();
+            }
+            let parse = dispatcher.parse(&token, Source);
+            let parsed = parse.context().nodes().first().map(|node| node.node);
+            assert_eq!(parsed, expected(&names, &token), "{token} of {words:?}");
+
+            let lower_case = token.to_lowercase();
+            let mut completions: Vec<_> = names
+                .iter()
+                .filter(|(name, _)| name.starts_with(&lower_case))
+                .collect();
+            completions.sort_by_key(|(name, _)| *name);
+            let completions: Vec<_> = completions.into_iter().map(|&(_, id)| id).collect();
+            assert_eq!(
+                dispatcher.tree().literal_completions(root, &token),
+                completions
+            );
+        }
+    }
+}
+
+#[test]
+fn matcher_invalidated_by_changes() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    dispatcher.set_literal_matching(LiteralMatching {
+        case_insensitive: false,
+        unique_prefix: true,
+    });
+    for i in 0..32 {
+        dispatcher.register(literal(&format!("command{i:02}")));
+    }
+    let unique = dispatcher.register(literal("unique"));
+    let parse = dispatcher.parse("uni", Source);
+    assert_eq!(parse.context().nodes()[0].node, unique);
+    dispatcher.register(literal("universe"));
+    let parse = dispatcher.parse("uni", Source);
+    assert!(parse.context().nodes().is_empty());
+    let root = dispatcher.root();
+    assert_eq!(dispatcher.tree().literal_completions(root, "UNI").len(), 2);
+}
+
+#[test]
+fn merged_literals_resolve_to_merged_node() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let matching = LiteralMatching {
+        case_insensitive: true,
+        unique_prefix: false,
+    };
+    dispatcher.set_literal_matching(matching);
+    for i in 0..32 {
+        dispatcher.register(literal(&format!("command{i:02}")));
+    }
+    let root = dispatcher.root();
+    // Builds the matcher before the merge
+    assert_eq!(
+        dispatcher
+            .tree()
+            .matching_literals(root, "COMMAND07", matching)
+            .len(),
+        1
+    );
+    let mut first = literal("Merged");
+    first.then(literal("a"));
+    let merged = dispatcher.register(first);
+    let mut second = literal("Merged");
+    second.then(literal("b"));
+    assert_eq!(dispatcher.register(second), merged);
+
+    let tree = dispatcher.tree();
+    assert_eq!(tree.get(root).unwrap().find_literal("Merged"), Some(merged));
+    assert_eq!(tree.get(root).unwrap().find_literal("merged"), None);
+    assert_eq!(
+        tree.matching_literals(root, "MERGED", matching),
+        vec![merged]
+    );
+    let parse = dispatcher.parse("merged b", Source);
+    let nodes: Vec<_> = parse
+        .context()
+        .nodes()
+        .iter()
+        .map(|node| node.node)
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![merged, dispatcher.find_node(&["Merged", "b"]).unwrap()]
+    );
+}
+
+#[test]
+fn mismatched_literals_are_reported() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    for i in 0..32 {
+        dispatcher.register(literal(&format!("command{i:02}")));
+    }
+    assert!(dispatcher
+        .parse("command05", Source)
+        .exceptions()
+        .is_empty());
+    let parse = dispatcher.parse("unknown", Source);
+    assert_eq!(parse.exceptions().len(), 32);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use brigadier::{
+    arguments::StringArgumentType,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::{CommandDispatcher, Tokens},
+    errors::CommandSyntaxError,
+    suggestion::{StaticSuggestions, SuggestionsBuilder},
+    Shared,
+};
+
+use common::Source;
+
+fn send_message<'i>(
+    context: &CommandContext<'i, Source, StringArgumentType>,
+) -> Result<i32, CommandSyntaxError<'i>> {
+    let player = context.get_argument("player").unwrap();
+    let message = context.get_argument("message").unwrap();
+    Ok(match (&**player, &**message) {
+        ("alice", "hello there") => 1,
+        ("alice", "hi") => 2,
+        _ => 0,
+    })
+}
+
+/// `msg <player> [<message>]`
+fn dispatcher() -> CommandDispatcher<Source, StringArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut msg = literal("msg");
+    msg.then_build(argument("player", StringArgumentType::word()), |player| {
+        player.then_build(
+            argument("message", StringArgumentType::greedy_string()),
+            |message| {
+                message
+                    .optional_with_default("hello there")
+                    .suggests(Shared::new(StaticSuggestions::new(["hi", "hello"])))
+                    .executes(send_message)
+            },
+        )
+    });
+    dispatcher.register(msg);
+    dispatcher
+}
+
+#[test]
+fn default_is_used_when_input_ends() {
+    let dispatcher = dispatcher();
+    assert_eq!(dispatcher.execute_input("msg alice", Source), Ok(1));
+    assert_eq!(dispatcher.execute_input("msg alice hi", Source), Ok(2));
+    assert!(dispatcher.execute_input("msg", Source).is_err());
+
+    let input = "msg alice";
+    let parse = dispatcher.parse(input, Source);
+    let message = &parse.context().arguments()["message"];
+    assert_eq!(message.range, 9..9);
+    assert_eq!(message.result, "hello there");
+    let context = parse.context().clone().build(input);
+    assert_eq!(
+        context.get_argument("message").map(|message| &**message),
+        Some("hello there")
+    );
+    // The defaulted node isn't parsed
+    assert_eq!(context.nodes().len(), 2);
+}
+
+#[test]
+fn default_with_tokens() {
+    let dispatcher = dispatcher();
+    let tokens = Tokens::new(["msg", "alice"]);
+    let context = dispatcher.parse_tokens(&tokens, Source).unwrap();
+    assert!(context.command().is_some());
+    assert_eq!(context.arguments()["message"].result, "hello there");
+}
+
+#[test]
+fn defaulted_argument_still_suggests() {
+    let dispatcher = dispatcher();
+    let node = dispatcher.find_node(&["msg", "player", "message"]).unwrap();
+    let input = "msg alice h";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, "msg alice ".len());
+    let suggestions =
+        pollster::block_on(dispatcher.tree().list_suggestions(node, &context, builder)).unwrap();
+    assert_eq!(suggestions.texts().collect::<Vec<_>>(), vec!["hello", "hi"]);
+}
+
+#[test]
+fn usage_of_optional_argument() {
+    let dispatcher = dispatcher();
+    let tree = dispatcher.tree();
+    let node = dispatcher.find_node(&["msg", "player", "message"]).unwrap();
+    assert_eq!(tree.usage_text(node), "[<message>]");
+    assert_eq!(tree.argument_default(node), Some("hello there"));
+    let msg = dispatcher.find_node(&["msg"]).unwrap();
+    let usage = tree.get_smart_usage(msg, &Source);
+    assert_eq!(
+        usage.values().collect::<Vec<_>>(),
+        vec!["<player> [<message>]"]
+    );
+}
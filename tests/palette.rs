@@ -0,0 +1,98 @@
+#![cfg(feature = "palette")]
+
+mod common;
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    palette::PaletteCache,
+};
+
+use common::Source;
+
+fn dispatcher() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut gamemode = literal("gamemode");
+    gamemode.keywords(&["gm"]);
+    let mut creative = literal("creative");
+    creative
+        .keywords(&["creative mode", "gm"])
+        .describe("Build without limits")
+        .executes(|_| Ok(1));
+    gamemode.then(creative);
+    let mut spectator = literal("spectator");
+    spectator.executes(|_| Ok(1));
+    gamemode.then(spectator);
+    gamemode.then(literal("survival"));
+    dispatcher.register(gamemode);
+    let mut give = literal("give");
+    let mut flag = argument("flag", BoolArgumentType);
+    flag.executes(|_| Ok(1));
+    give.then(flag);
+    dispatcher.register(give);
+    dispatcher
+}
+
+#[test]
+fn palette_keyword_inheritance() {
+    let dispatcher = dispatcher();
+    let index = dispatcher.palette_index(&Source);
+    let entries: Vec<_> = index
+        .entries()
+        .iter()
+        .map(|entry| (entry.usage.as_str(), entry.keywords.clone()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("gamemode creative", vec!["creative mode".into(), "gm".into()]),
+            ("gamemode spectator", vec!["gm".into()]),
+            ("give <flag>", vec![]),
+        ]
+    );
+    let creative = &index.entries()[0];
+    assert_eq!(creative.path, vec!["gamemode", "creative"]);
+    assert_eq!(creative.description.as_deref(), Some("Build without limits"));
+}
+
+#[test]
+fn palette_search_order() {
+    let dispatcher = dispatcher();
+    let index = dispatcher.palette_index(&Source);
+    let search = |query| {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|(score, entry)| (score, entry.usage.as_str()))
+            .collect::<Vec<_>>()
+    };
+    // Both inherit the keyword "gm", which scores 1 + 3 for the word start and
+    // 1 + 2 for the consecutive character
+    assert_eq!(
+        search("gm"),
+        vec![(7, "gamemode creative"), (7, "gamemode spectator")]
+    );
+    assert_eq!(search("spec"), vec![(13, "gamemode spectator")]);
+    assert_eq!(search("creative mode")[0].1, "gamemode creative");
+    assert_eq!(search("g f"), vec![(8, "give <flag>")]);
+    // Word starts outweigh the earlier entry
+    assert_eq!(
+        search("gc"),
+        vec![(8, "gamemode creative"), (5, "gamemode spectator")]
+    );
+    assert!(search("xyz").is_empty());
+    assert_eq!(index.search("g", 1).len(), 1);
+}
+
+#[test]
+fn palette_cache_per_fingerprint() {
+    let mut dispatcher = dispatcher();
+    let mut cache = PaletteCache::new();
+    assert_eq!(cache.get_or_build(&dispatcher, &Source, 0).entries().len(), 3);
+    let mut list = literal("list");
+    list.executes(|_| Ok(1));
+    dispatcher.register(list);
+    assert_eq!(cache.get_or_build(&dispatcher, &Source, 0).entries().len(), 4);
+    assert_eq!(cache.get_or_build(&dispatcher, &Source, 1).entries().len(), 4);
+}
@@ -0,0 +1,249 @@
+#![cfg(feature = "packet")]
+
+mod common;
+
+use brigadier::{
+    arguments::{
+        ArgCapabilities, ArgumentType, BoolArgumentType, DoubleArgumentType, UIntArgumentType,
+        ULongArgumentType,
+    },
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    tree::protocol::{
+        ProtocolParser, ASK_SERVER_SUGGESTIONS, FALLBACK_PARSER, FLAG_EXECUTABLE, FLAG_REDIRECT,
+        FLAG_SUGGESTIONS_TYPE, NODE_TYPE_ARGUMENT, NODE_TYPE_LITERAL, NODE_TYPE_ROOT,
+    },
+    StringReader,
+};
+
+use common::Source;
+
+/// A word parsed by a custom parser, which may or may not be known to clients.
+struct Custom {
+    protocol_id: Option<&'static str>,
+    capabilities: ArgCapabilities,
+    greedy: bool,
+}
+
+impl Custom {
+    fn new(protocol_id: Option<&'static str>, capabilities: ArgCapabilities) -> Self {
+        Self {
+            protocol_id,
+            capabilities,
+            greedy: false,
+        }
+    }
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Custom {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string()
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        self.capabilities
+    }
+    fn is_greedy(&self) -> bool {
+        self.greedy
+    }
+    fn protocol_id(&self) -> Option<&'static str> {
+        self.protocol_id
+    }
+}
+
+#[test]
+fn protocol_nodes() {
+    let mut dispatcher = CommandDispatcher::<Source, DoubleArgumentType>::new();
+    let mut speed = literal("speed");
+    let mut value = argument("value", DoubleArgumentType::new(0.0..=10.0));
+    value.executes(|_| Ok(1));
+    speed.then(value);
+    let speed = dispatcher.register(speed);
+    dispatcher.register_alias("sp", speed);
+
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    assert_eq!(nodes.len(), 4);
+    let root = &nodes[0];
+    assert_eq!(root.node_type(), NODE_TYPE_ROOT);
+    assert_eq!(root.name, None);
+    assert_eq!(root.children, vec![1, 2]);
+
+    let alias = &nodes[1];
+    assert_eq!(alias.name.as_deref(), Some("sp"));
+    assert_eq!(alias.flags, NODE_TYPE_LITERAL | FLAG_REDIRECT);
+    assert_eq!(alias.redirect, Some(2));
+
+    let speed = &nodes[2];
+    assert_eq!(speed.name.as_deref(), Some("speed"));
+    assert_eq!(speed.flags, NODE_TYPE_LITERAL);
+    assert_eq!(speed.children, vec![3]);
+
+    let value = &nodes[3];
+    assert_eq!(value.flags, NODE_TYPE_ARGUMENT | FLAG_EXECUTABLE);
+    assert!(value.is_executable());
+    let mut properties = vec![0x03];
+    properties.extend_from_slice(&0.0f64.to_be_bytes());
+    properties.extend_from_slice(&10.0f64.to_be_bytes());
+    assert_eq!(
+        value.parser,
+        Some(ProtocolParser {
+            id: "brigadier:double",
+            properties,
+        })
+    );
+    assert_eq!(value.suggestions_type, None);
+}
+
+#[test]
+fn protocol_parser_properties() {
+    let mut dispatcher = CommandDispatcher::<Source, DoubleArgumentType>::new();
+    let mut root = literal("bounds");
+    root.then(argument("any", DoubleArgumentType::new(..)));
+    root.then(argument("min", DoubleArgumentType::new(1.5..)));
+    dispatcher.register(root);
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    let properties: Vec<_> = nodes[2..]
+        .iter()
+        .map(|node| node.parser.as_ref().unwrap().properties.clone())
+        .collect();
+    let mut min = vec![0x01];
+    min.extend_from_slice(&1.5f64.to_be_bytes());
+    assert_eq!(properties, vec![vec![0x00], min]);
+
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut root = literal("toggle");
+    root.then(argument("value", BoolArgumentType));
+    dispatcher.register(root);
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    assert_eq!(
+        nodes[2].parser,
+        Some(ProtocolParser {
+            id: "brigadier:bool",
+            properties: Vec::new(),
+        })
+    );
+}
+
+#[test]
+fn protocol_unsigned_parser_properties() {
+    let mut dispatcher = CommandDispatcher::<Source, UIntArgumentType>::new();
+    let mut root = literal("page");
+    root.then(argument("any", UIntArgumentType::new(..)));
+    root.then(argument("bounded", UIntArgumentType::new(1..=10)));
+    root.then(argument("large", UIntArgumentType::new(3_000_000_000..)));
+    dispatcher.register(root);
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    let parsers: Vec<_> = nodes[2..]
+        .iter()
+        .map(|node| node.parser.clone().unwrap())
+        .collect();
+    assert!(parsers
+        .iter()
+        .all(|parser| parser.id == "brigadier:integer"));
+    // The minimum is always sent, since the client's parser is signed
+    let mut any = vec![0x01];
+    any.extend_from_slice(&0i32.to_be_bytes());
+    let mut bounded = vec![0x03];
+    bounded.extend_from_slice(&1i32.to_be_bytes());
+    bounded.extend_from_slice(&10i32.to_be_bytes());
+    let mut large = vec![0x01];
+    large.extend_from_slice(&i32::MAX.to_be_bytes());
+    let properties: Vec<_> = parsers
+        .into_iter()
+        .map(|parser| parser.properties)
+        .collect();
+    assert_eq!(properties, vec![any, bounded, large]);
+
+    let mut dispatcher = CommandDispatcher::<Source, ULongArgumentType>::new();
+    dispatcher.register(argument("id", ULongArgumentType::new(..)));
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    let mut properties = vec![0x01];
+    properties.extend_from_slice(&0i64.to_be_bytes());
+    assert_eq!(
+        nodes[1].parser,
+        Some(ProtocolParser {
+            id: "brigadier:long",
+            properties,
+        })
+    );
+}
+
+#[test]
+fn protocol_fallback_parser() {
+    let mut dispatcher = CommandDispatcher::<Source, Custom>::new();
+    let server_only = ArgCapabilities::all() - ArgCapabilities::CLIENT_SIDE;
+    let mut root = literal("kill");
+    root.then(argument(
+        "client",
+        Custom::new(Some("minecraft:entity"), ArgCapabilities::all()),
+    ));
+    root.then(argument(
+        "server",
+        Custom::new(Some("minecraft:entity"), server_only),
+    ));
+    let mut greedy = Custom::new(None, server_only);
+    greedy.greedy = true;
+    root.then(argument("tail", greedy));
+    dispatcher.register(root);
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    assert_eq!(nodes.len(), 5);
+
+    let client = &nodes[2];
+    assert_eq!(
+        client.parser,
+        Some(ProtocolParser {
+            id: "minecraft:entity",
+            properties: Vec::new(),
+        })
+    );
+    assert_eq!(client.suggestions_type, None);
+
+    let server = &nodes[3];
+    assert_eq!(
+        server.parser,
+        Some(ProtocolParser {
+            id: FALLBACK_PARSER,
+            properties: vec![0],
+        })
+    );
+    assert_eq!(server.flags, NODE_TYPE_ARGUMENT | FLAG_SUGGESTIONS_TYPE);
+    assert_eq!(
+        server.suggestions_type.as_deref(),
+        Some(ASK_SERVER_SUGGESTIONS)
+    );
+
+    let tail = &nodes[4];
+    assert_eq!(
+        tail.parser,
+        Some(ProtocolParser {
+            id: FALLBACK_PARSER,
+            properties: vec![2],
+        })
+    );
+}
+
+#[test]
+fn protocol_redirect_to_unsendable_argument() {
+    let mut dispatcher = CommandDispatcher::<Source, Custom>::new();
+    let mut root = literal("tp");
+    root.then(argument(
+        "target",
+        Custom::new(None, ArgCapabilities::all()),
+    ));
+    dispatcher.register(root);
+    let target = dispatcher.find_node(&["tp", "target"]).unwrap();
+    dispatcher.register_alias("teleport", target);
+
+    let nodes = dispatcher.to_protocol_nodes(&Source);
+    assert_eq!(nodes.len(), 3);
+    assert!(nodes
+        .iter()
+        .all(|node| node.node_type() != NODE_TYPE_ARGUMENT));
+    let alias = &nodes[1];
+    assert_eq!(alias.name.as_deref(), Some("teleport"));
+    assert_eq!(alias.flags, NODE_TYPE_LITERAL);
+    assert_eq!(alias.redirect, None);
+}
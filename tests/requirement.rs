@@ -0,0 +1,76 @@
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::literal,
+    dispatcher::CommandDispatcher,
+    errors::CommandErrorType,
+    requirement::{CommandRequirement, Or},
+    CommandSource,
+};
+
+#[derive(Clone)]
+struct Source {
+    operator: bool,
+    permissions: Vec<&'static str>,
+}
+
+impl CommandSource for Source {}
+
+fn source(operator: bool, permissions: &[&'static str]) -> Source {
+    Source {
+        operator,
+        permissions: permissions.to_vec(),
+    }
+}
+
+fn permission(name: &'static str) -> impl CommandRequirement<Source> + Clone {
+    move |source: &Source| source.permissions.contains(&name)
+}
+
+#[test]
+fn closure_requirement() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut stop = literal("stop");
+    stop.requires(|source: &Source| source.operator)
+        .executes(|_| Ok(1));
+    let stop = dispatcher.register(stop);
+    let node = dispatcher.tree().get(stop).unwrap();
+    assert!(node.is_restricted());
+    assert!(node.can_use(&source(true, &[])));
+    assert!(!node.can_use(&source(false, &[])));
+
+    assert_eq!(dispatcher.execute_input("stop", source(true, &[])), Ok(1));
+    let error = dispatcher
+        .execute_input("stop", source(false, &[]))
+        .unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::DispatcherUnknownCommand);
+}
+
+#[test]
+fn combined_requirements() {
+    let moderator = permission("admin").or(permission("mod"));
+    assert!(moderator.test(&source(false, &["admin"])));
+    assert!(moderator.test(&source(false, &["mod"])));
+    assert!(!moderator.test(&source(false, &["user"])));
+
+    let operator_moderator = moderator.clone().and(|source: &Source| source.operator);
+    assert!(operator_moderator.test(&source(true, &["mod"])));
+    assert!(!operator_moderator.test(&source(false, &["mod"])));
+    assert!(!operator_moderator.test(&source(true, &[])));
+
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut kick = literal("kick");
+    kick.requires(Or(permission("admin"), permission("mod")));
+    let kick = dispatcher.register(kick);
+    let node = dispatcher.tree().get(kick).unwrap();
+    assert!(node.can_use(&source(false, &["mod"])));
+    assert!(!node.can_use(&source(false, &["user"])));
+}
+
+#[test]
+fn nodes_without_requirement_are_usable() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let help = dispatcher.register(literal("help"));
+    let node = dispatcher.tree().get(help).unwrap();
+    assert!(!node.is_restricted());
+    assert!(node.can_use(&source(false, &[])));
+}
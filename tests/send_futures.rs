@@ -0,0 +1,78 @@
+#![cfg(feature = "send-futures")]
+
+mod common;
+
+use std::sync::Arc;
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    suggestion::{StaticSuggestions, SuggestionFuture, SuggestionsBuilder},
+    Shared,
+};
+
+use common::Source;
+
+fn numbers<'c, 'i>(
+    _context: &'c CommandContext<'i, Source, BoolArgumentType>,
+    mut builder: SuggestionsBuilder<'i, 'c, 'c>,
+) -> SuggestionFuture<'c, 'i> {
+    Box::pin(async move {
+        tokio::task::yield_now().await;
+        builder.suggest_int(1).suggest_int(2);
+        Ok(builder.build())
+    })
+}
+
+fn suggest(
+    dispatcher: Arc<CommandDispatcher<Source, BoolArgumentType>>,
+    path: &'static [&'static str],
+    input: &'static str,
+) -> tokio::task::JoinHandle<Vec<String>> {
+    // Spawning requires the future, which holds the dispatcher across `.await`, to be Send
+    tokio::spawn(async move {
+        let node = dispatcher.find_node(path).unwrap();
+        let context = dispatcher
+            .parse(input, Source)
+            .context()
+            .clone()
+            .build(input);
+        let start = input.rfind(' ').unwrap() + 1;
+        let builder = SuggestionsBuilder::new_with_lowercase(input, start);
+        let suggestions = dispatcher
+            .tree()
+            .list_suggestions(node, &context, builder)
+            .await
+            .unwrap();
+        suggestions.texts().map(str::to_owned).collect()
+    })
+}
+
+#[tokio::test]
+async fn suggestions_can_be_spawned() {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut item = literal("item");
+    let items = StaticSuggestions::new(["Stone", "stick", "dirt"]);
+    item.then_build(argument("value", BoolArgumentType), |value| {
+        value.suggests(Shared::new(items))
+    });
+    dispatcher.register(item);
+    let mut count = literal("count");
+    count.then_build(argument("value", BoolArgumentType), |value| {
+        value.suggests(Shared::new(numbers))
+    });
+    dispatcher.register(count);
+    let mut toggle = literal("toggle");
+    toggle.then(argument("value", BoolArgumentType));
+    dispatcher.register(toggle);
+    let dispatcher = Arc::new(dispatcher);
+
+    let items = suggest(Arc::clone(&dispatcher), &["item", "value"], "item st");
+    let numbers = suggest(Arc::clone(&dispatcher), &["count", "value"], "count ");
+    let toggle = suggest(dispatcher, &["toggle", "value"], "toggle t");
+    assert_eq!(items.await.unwrap(), vec!["stick", "Stone"]);
+    assert_eq!(numbers.await.unwrap(), vec!["1", "2"]);
+    assert_eq!(toggle.await.unwrap(), vec!["true"]);
+}
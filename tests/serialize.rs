@@ -0,0 +1,85 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use brigadier::{
+    arguments::DoubleArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    suggestion::{Suggestion, Suggestions},
+    tree::serialize::{to_json_value, ArgumentTypeDescriptor},
+};
+
+use common::Source;
+
+fn describe(argument_type: &DoubleArgumentType) -> ArgumentTypeDescriptor {
+    let mut properties = serde_json::Map::new();
+    properties.insert("min".into(), (*argument_type.range.start()).into());
+    properties.insert("max".into(), (*argument_type.range.end()).into());
+    ArgumentTypeDescriptor {
+        parser: "brigadier:double".into(),
+        properties,
+    }
+}
+
+#[test]
+fn tree_to_json() {
+    let mut dispatcher = CommandDispatcher::<Source, DoubleArgumentType>::new();
+    let mut speed = literal("speed");
+    speed.then(argument("value", DoubleArgumentType::new(0.0..=10.0)));
+    let speed = dispatcher.register(speed);
+    dispatcher.register_alias("sp", speed);
+
+    let json = to_json_value(dispatcher.tree(), dispatcher.root(), describe);
+    let expected: serde_json::Value =
+        serde_json::from_str(include_str!("golden/tree.json")).unwrap();
+    assert_eq!(json, expected);
+}
+
+#[test]
+fn suggestions_to_json() {
+    let suggestions: Suggestions = Suggestions::new(
+        3..5,
+        vec![
+            Suggestion::new_text(3..5, "stone"),
+            Suggestion::new_int_with_tooltip(3..5, 42, "The answer"),
+        ],
+    );
+    let json = serde_json::to_value(&suggestions).unwrap();
+    let expected = serde_json::json!({
+        "range": { "start": 3, "end": 5 },
+        "suggestions": [
+            { "range": { "start": 3, "end": 5 }, "text": "stone" },
+            {
+                "range": { "start": 3, "end": 5 },
+                "text": "42",
+                "int": 42,
+                "tooltip": "The answer",
+            },
+        ],
+    });
+    assert_eq!(json, expected);
+}
+
+#[test]
+fn suggestions_round_trip() {
+    let suggestions: Suggestions = Suggestions::new(
+        0..4,
+        vec![
+            Suggestion::new_text_with_tooltip(0..4, "give", "Gives items"),
+            Suggestion::new_int(0..4, 7),
+        ],
+    )
+    .with_transaction_id(Some(3));
+    let json = serde_json::to_string(&suggestions).unwrap();
+    // Deserialized from a temporary string, so the data must be owned
+    let deserialized: Suggestions<'static, 'static> =
+        serde_json::from_str(&json.clone()).unwrap();
+    assert_eq!(deserialized, suggestions);
+    assert_eq!(deserialized.transaction_id(), Some(3));
+    let tooltips: Vec<_> = deserialized
+        .into_iter()
+        .map(|suggestion| suggestion.tooltip.as_deref().map(str::to_owned))
+        .collect();
+    assert_eq!(tooltips, vec![Some("Gives items".to_owned()), None]);
+}
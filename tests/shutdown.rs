@@ -0,0 +1,73 @@
+mod common;
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    dispatcher::{CommandDispatcher, Tokens},
+    errors::CommandErrorType,
+};
+
+use common::{toggle_dispatcher, Source};
+
+/// Counts how often the task was woken.
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn shutdown_rejects_and_waits_for_in_flight() {
+    let dispatcher = toggle_dispatcher();
+    let controller = dispatcher.shutdown_controller();
+    assert!(!controller.is_shutting_down());
+
+    // Two commands which are still awaiting something
+    let first = controller.track();
+    let second = controller.track();
+    assert_eq!(controller.in_flight(), 2);
+
+    controller.begin_shutdown();
+    let error = dispatcher.execute_input("toggle true", Source).unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::DispatcherShuttingDown);
+    let parse = dispatcher.parse("toggle true", Source);
+    assert_eq!(
+        parse.exceptions()[&dispatcher.root()].error_type,
+        CommandErrorType::DispatcherShuttingDown
+    );
+    let tokens = Tokens::new(["toggle", "true"]);
+    let error = dispatcher.parse_tokens(&tokens, Source).err().unwrap();
+    assert_eq!(error.error.error_type, CommandErrorType::DispatcherShuttingDown);
+    assert_eq!(controller.in_flight(), 2);
+
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(Arc::clone(&counter));
+    let mut cx = Context::from_waker(&waker);
+    let mut idle = pin!(controller.wait_idle());
+    assert_eq!(idle.as_mut().poll(&mut cx), Poll::Pending);
+    drop(first);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+    assert_eq!(idle.as_mut().poll(&mut cx), Poll::Pending);
+    drop(second);
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+    assert_eq!(controller.in_flight(), 0);
+    assert_eq!(idle.as_mut().poll(&mut cx), Poll::Ready(()));
+}
+
+#[test]
+fn wait_idle_without_executions() {
+    let dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    pollster::block_on(dispatcher.shutdown_controller().wait_idle());
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "testing")]
+
+use brigadier::{
+    arguments::DoubleArgumentType,
+    assert_tree_matches_snapshot,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    tree::snapshot::{assert_matches_snapshot, unified_diff},
+    CommandSource,
+};
+
+#[derive(Clone)]
+struct Source {
+    operator: bool,
+}
+
+impl CommandSource for Source {}
+
+fn describe(argument_type: &DoubleArgumentType) -> String {
+    format!("double({:?})", argument_type.range)
+}
+
+fn same_source<'i>(
+    context: &CommandContext<'i, Source, DoubleArgumentType>,
+) -> Result<Vec<Source>, CommandSyntaxError<'i>> {
+    Ok(vec![context.source().clone()])
+}
+
+/// A small server: `speed <value>` with the alias `sp`, an operator-only
+/// `execute as <target> run ...` and `login <password>`.
+fn server(max_speed: f64) -> CommandDispatcher<Source, DoubleArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut speed = literal("speed");
+    speed.describe("Changes the walking speed");
+    speed.then_build(
+        argument("value", DoubleArgumentType::new(0.0..=max_speed)),
+        |value| value.executes(|_| Ok(1)).keywords(&["walk", "run"]),
+    );
+    let speed = dispatcher.register(speed);
+    dispatcher.register_alias("sp", speed);
+
+    let mut execute = literal("execute");
+    execute.requires(|source: &Source| source.operator);
+    let execute = dispatcher.register(execute);
+    let mut run = literal("run");
+    run.redirect(dispatcher.root());
+    let mut target = argument("target", DoubleArgumentType::new(0.0..=100.0));
+    target.fork(execute, same_source);
+    let mut as_ = literal("as");
+    as_.then(target);
+    let mut execute = literal("execute");
+    execute.then(as_).then(run);
+    dispatcher.register(execute);
+
+    let mut login = literal("login");
+    login.then_build(
+        argument("password", DoubleArgumentType::new(0.0..=9999.0)),
+        |password| password.executes(|_| Ok(1)).sensitive(),
+    );
+    dispatcher.register(login);
+    dispatcher
+}
+
+#[test]
+fn tree_matches_golden_file() {
+    let dispatcher = server(10.0);
+    let tree = dispatcher.tree();
+    assert_tree_matches_snapshot!(tree, dispatcher.root(), describe, "tests/golden/tree.snap");
+}
+
+#[test]
+fn description_is_deterministic() {
+    let first = server(10.0);
+    let second = server(10.0);
+    assert_eq!(
+        first.tree().canonical_description(first.root(), describe),
+        second.tree().canonical_description(second.root(), describe)
+    );
+}
+
+#[test]
+fn diff_names_changed_node() {
+    let before = server(10.0);
+    let after = server(20.0);
+    let diff = unified_diff(
+        &before.tree().canonical_description(before.root(), describe),
+        &after.tree().canonical_description(after.root(), describe),
+    );
+    assert!(diff.contains("@@ -7,4 +7,4 @@ /speed/value\n"), "{diff}");
+    assert!(
+        diff.contains("\n-    argument value: double(0.0..=10.0) executes"),
+        "{diff}"
+    );
+    assert!(
+        diff.contains("\n+    argument value: double(0.0..=20.0) executes"),
+        "{diff}"
+    );
+    assert_eq!(unified_diff("root\n", "root\n"), "--- snapshot\n+++ tree\n");
+}
+
+#[test]
+fn mismatch_panics_with_diff() {
+    let dispatcher = server(20.0);
+    let description = dispatcher
+        .tree()
+        .canonical_description(dispatcher.root(), describe);
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/tree.snap");
+    let panic =
+        std::panic::catch_unwind(|| assert_matches_snapshot(&description, path)).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("/speed/value"), "{message}");
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    suggestion::{
+        SortedSliceProvider, Stateful, StatefulSuggestionProvider, StaticSuggestions,
+        SuggestionProvider, Suggestions, SuggestionsBuilder,
+    },
+    Shared, StringReader,
+};
+
+use common::Source;
+
+struct Word;
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Word {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        reader.read_unquoted_string()
+    }
+}
+
+/// Suggests the names, counting how often its state was built.
+struct Counting {
+    builds: Arc<AtomicUsize>,
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl StatefulSuggestionProvider<Source, Word> for Counting {
+    type State = Vec<&'static str>;
+
+    fn build_state(&self) -> Self::State {
+        self.builds.fetch_add(1, Ordering::SeqCst);
+        vec!["alex", "steve"]
+    }
+    async fn suggest_with_state<'i, 't, 'm>(
+        &self,
+        state: &Self::State,
+        _context: &CommandContext<'i, Source, Word>,
+        mut builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Result<Suggestions<'t, 'm>, CommandSyntaxError<'i>>
+    where
+        Word: ArgumentType<'i, Source>,
+    {
+        for name in state {
+            builder.suggest_text_matching(*name);
+        }
+        Ok(builder.build())
+    }
+}
+
+fn counting_dispatcher() -> (CommandDispatcher<Source, Word>, Arc<AtomicUsize>) {
+    let builds = Arc::new(AtomicUsize::new(0));
+    let mut dispatcher = CommandDispatcher::new();
+    let mut kick = literal("kick");
+    let counting = Stateful(Counting {
+        builds: Arc::clone(&builds),
+    });
+    kick.then_build(argument("player", Word), |player| {
+        player.suggests(Shared::new(counting))
+    });
+    dispatcher.register(kick);
+    (dispatcher, builds)
+}
+
+fn suggest(dispatcher: &CommandDispatcher<Source, Word>, input: &str) -> Vec<String> {
+    let player = dispatcher.find_node(&["kick", "player"]).unwrap();
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, "kick ".len());
+    pollster::block_on(
+        dispatcher
+            .tree()
+            .list_suggestions(player, &context, builder),
+    )
+    .unwrap()
+    .texts()
+    .map(str::to_owned)
+    .collect()
+}
+
+#[test]
+fn state_is_built_once() {
+    let (dispatcher, builds) = counting_dispatcher();
+    assert_eq!(builds.load(Ordering::SeqCst), 0);
+    for _ in 0..10 {
+        assert_eq!(suggest(&dispatcher, "kick "), vec!["alex", "steve"]);
+        assert_eq!(suggest(&dispatcher, "kick s"), vec!["steve"]);
+    }
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn state_is_rebuilt_after_changes() {
+    let (mut dispatcher, builds) = counting_dispatcher();
+    suggest(&dispatcher, "kick ");
+    suggest(&dispatcher, "kick ");
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+    dispatcher.register(literal("ban"));
+    suggest(&dispatcher, "kick ");
+    suggest(&dispatcher, "kick ");
+    assert_eq!(builds.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn warm_suggestions_builds_ahead_of_time() {
+    let (dispatcher, builds) = counting_dispatcher();
+    dispatcher.warm_suggestions();
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+    assert_eq!(suggest(&dispatcher, "kick a"), vec!["alex"]);
+    dispatcher.warm_suggestions();
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+}
+
+/// A small deterministic generator, so that failures can be reproduced.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+    fn word(&mut self) -> String {
+        let length = self.next(5);
+        (0..length)
+            .map(|_| ['a', 'B', 'b', 'c', 'É', 'é'][self.next(6)])
+            .collect()
+    }
+}
+
+fn provider_suggestions(
+    dispatcher: &CommandDispatcher<Source, Word>,
+    provider: &dyn SuggestionProvider<Source, Word>,
+    input: &str,
+) -> Vec<String> {
+    let context = dispatcher.parse("", Source).context().clone().build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, 0);
+    let state = provider.build_state();
+    pollster::block_on(provider.get_suggestions_with_state(state.as_ref(), &context, builder))
+        .unwrap()
+        .texts()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn sorted_slice_matches_naive_filter() {
+    let dispatcher = CommandDispatcher::<Source, Word>::new();
+    let mut rng = Lcg(42);
+    for _ in 0..50 {
+        let texts: Vec<String> = (0..rng.next(40)).map(|_| rng.word()).collect();
+        let naive = StaticSuggestions::new(texts.clone());
+        let sorted = Stateful(SortedSliceProvider::new(texts));
+        for _ in 0..20 {
+            let input = rng.word();
+            assert_eq!(
+                provider_suggestions(&dispatcher, &sorted, &input),
+                provider_suggestions(&dispatcher, &naive, &input),
+                "input {input:?}"
+            );
+        }
+    }
+}
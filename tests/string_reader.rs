@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 
-use brigadier::StringReader;
+use brigadier::{
+    errors::{CommandErrorType, CommandSyntaxError},
+    StringReader,
+};
 
 #[test]
 fn read_string_unquoted() {
@@ -36,3 +39,150 @@ fn read_string_quoted_escaped() {
     );
     assert_eq!(reader.remaining(), "abc");
 }
+
+#[test]
+fn read_string_until_multibyte_terminator() {
+    let mut reader = StringReader::new("«guillemets» rest");
+    reader.skip();
+    assert_eq!(reader.read_string_until('»'), Ok(Cow::Borrowed("guillemets")));
+    assert_eq!(reader.remaining(), " rest");
+
+    let mut reader = StringReader::new(r"«a \» b» rest");
+    reader.skip();
+    assert_eq!(
+        reader.read_string_until('»'),
+        Ok(Cow::Owned(String::from("a » b")))
+    );
+    assert_eq!(reader.remaining(), " rest");
+}
+
+#[test]
+fn read_string_invalid_escape_points_at_backslash() {
+    const TEXT: &str = r#""café \x""#;
+    let mut reader = StringReader::new(TEXT);
+    let error = reader.read_string().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderInvalidEscape('x'));
+    assert_eq!(error.context.unwrap().cursor, TEXT.find('\\').unwrap());
+
+    // After an escaped character
+    const ESCAPED: &str = r#""é\\ü\é""#;
+    let mut reader = StringReader::new(ESCAPED);
+    let error = reader.read_string().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderInvalidEscape('é'));
+    assert_eq!(error.context.unwrap().cursor, ESCAPED.rfind('\\').unwrap());
+}
+
+#[test]
+fn read_uint() {
+    let mut reader = StringReader::new("4294967295 abc");
+    assert_eq!(reader.read_uint(), Ok(u32::MAX));
+    assert_eq!(reader.remaining(), " abc");
+}
+
+#[test]
+fn read_uint_negative() {
+    let mut reader = StringReader::new("x -1");
+    reader.set_cursor(2);
+    let error = reader.read_uint().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedNonNegative);
+    assert_eq!(error.context.unwrap().cursor, 2);
+    assert_eq!(reader.remaining(), "-1");
+}
+
+#[test]
+fn read_uint_overflow() {
+    let mut reader = StringReader::new("4294967296");
+    assert_eq!(
+        reader.read_uint().unwrap_err().error_type,
+        CommandErrorType::ReaderInvalidInt("4294967296")
+    );
+    assert_eq!(reader.cursor(), 0);
+}
+
+#[test]
+fn read_ulong() {
+    let mut reader = StringReader::new("18446744073709551615");
+    assert_eq!(reader.read_ulong(), Ok(u64::MAX));
+    let mut reader = StringReader::new("18446744073709551616");
+    assert_eq!(
+        reader.read_ulong().unwrap_err().error_type,
+        CommandErrorType::ReaderInvalidLong("18446744073709551616")
+    );
+    let mut reader = StringReader::new("-0");
+    assert_eq!(
+        reader.read_ulong().unwrap_err().error_type,
+        CommandErrorType::ReaderExpectedNonNegative
+    );
+}
+
+#[test]
+fn read_double_invalid_span() {
+    let mut reader = StringReader::new("speed 1.2.3 fast");
+    reader.set_cursor(6);
+    let error = reader.read_double().unwrap_err();
+    let context = error.context.clone().unwrap();
+    assert_eq!(context.cursor(), 6);
+    assert_eq!(context.span(), Some(6..11));
+    assert_eq!(context.span_text(), Some("1.2.3"));
+    assert_eq!(context.to_string(), "speed <<1.2.3>> fast");
+    assert_eq!(error.context().as_deref(), Some("speed <<1.2.3>>"));
+    assert_eq!(reader.cursor(), 6);
+}
+
+#[test]
+fn read_boolean_invalid_span() {
+    let mut reader = StringReader::new("fly \"yes\" now");
+    reader.set_cursor(4);
+    let context = reader.read_boolean().unwrap_err().context.unwrap();
+    assert_eq!(context.span_text(), Some("\"yes\""));
+    assert_eq!(reader.cursor(), 4);
+}
+
+#[test]
+fn context_without_span() {
+    let mut reader = StringReader::new("foo bar");
+    reader.set_cursor(4);
+    assert_eq!(reader.context().span(), None);
+    assert_eq!(reader.context().to_string(), "foo <<>>bar");
+    assert_eq!(
+        format!("{:?}", reader.context_span(0)),
+        r#"StringReaderContext("<<foo >>bar")"#
+    );
+}
+
+#[test]
+fn failed_transaction_restores_cursor() {
+    let mut reader = StringReader::new("go 12 abc");
+    reader.set_cursor(3);
+    let result: Result<_, CommandSyntaxError> = reader.try_parse(|reader| {
+        let x = reader.read_int()?;
+        reader.skip_whitespace();
+        // Fails after the inner attempt has been committed
+        let y = reader.try_parse(|reader| reader.read_int())?;
+        Ok((x, y))
+    });
+    assert!(result.is_err());
+    assert_eq!(reader.cursor(), 3);
+
+    let mut transaction = reader.transaction();
+    assert_eq!(transaction.read_int(), Ok(12));
+    let mut inner = transaction.transaction();
+    inner.skip_whitespace();
+    assert_eq!(inner.start(), 5);
+    inner.commit();
+    assert_eq!(transaction.remaining(), "abc");
+    drop(transaction);
+    assert_eq!(reader.remaining(), "12 abc");
+
+    assert_eq!(reader.try_parse(|reader| reader.read_int()), Ok(12));
+    assert_eq!(reader.remaining(), " abc");
+}
+
+#[test]
+fn invalid_bool_restores_cursor() {
+    let mut reader = StringReader::new("x \"maybe");
+    reader.set_cursor(2);
+    let error = reader.read_boolean().unwrap_err();
+    assert_eq!(error.error_type, CommandErrorType::ReaderExpectedEndOfQuote);
+    assert_eq!(reader.cursor(), 2);
+}
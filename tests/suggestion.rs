@@ -0,0 +1,630 @@
+mod common;
+
+use brigadier::{
+    arguments::{ArgumentType, BoolArgumentType},
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    suggestion::{
+        NoSuggestions, StaticSuggestions, Suggestion, SuggestionFuture, SuggestionMode,
+        SuggestionRangeCheck, Suggestions, SuggestionsBuilder,
+    },
+    Message, Shared, StringReader,
+};
+
+use common::Source;
+
+fn suggest_from(input: &str) -> Suggestions<'_, 'static> {
+    let lower_case = input.to_lowercase();
+    let mut builder = SuggestionsBuilder::new(input, &lower_case, 4);
+    builder.suggest_text(&input[..3]);
+    builder.suggest_text("foo");
+    builder.build()
+}
+
+#[test]
+fn suggestions_into_owned_outlive_input() {
+    let input = String::from("bar b");
+    let suggestions = suggest_from(&input).into_owned();
+    drop(input);
+    assert_eq!(suggestions.len(), 2);
+    assert_eq!(suggestions.range(), 4..5);
+    assert_eq!(suggestions.texts().collect::<Vec<_>>(), vec!["bar", "foo"]);
+}
+
+#[test]
+fn suggestions_iter() {
+    let suggestions = suggest_from("bar b");
+    let ranges: Vec<_> = suggestions.iter().map(|s| s.range()).collect();
+    assert_eq!(ranges, vec![4..5, 4..5]);
+    assert!(!suggestions.is_empty());
+    assert!(Suggestions::EMPTY.texts().next().is_none());
+}
+
+#[test]
+fn suggest_text_matching_filters_by_prefix() {
+    let input = "give St";
+    let mut builder: SuggestionsBuilder = SuggestionsBuilder::new_with_lowercase(input, 5);
+    builder
+        .suggest_text_matching("stone")
+        .suggest_text_matching("Stick")
+        .suggest_text_matching("dirt")
+        .suggest_text_matching("St");
+    let suggestions = builder.build();
+    assert_eq!(
+        suggestions.texts().collect::<Vec<_>>(),
+        vec!["Stick", "stone"]
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Component {
+    text: String,
+    bold: bool,
+}
+
+impl Message for Component {
+    fn to_plain_string(&self) -> String {
+        self.text.clone()
+    }
+}
+
+#[test]
+fn suggestions_dedupe_ignores_tooltip() {
+    let input = "give st";
+    let mut builder: SuggestionsBuilder = SuggestionsBuilder::new(input, input, 5);
+    builder.suggest_text_with_tooltip("stone", "first");
+    builder.suggest_text_with_tooltip("stone", "second");
+    builder.suggest_text("stick");
+    let suggestions = builder.build();
+    assert_eq!(
+        suggestions.texts().collect::<Vec<_>>(),
+        vec!["stick", "stone"]
+    );
+    let stone = suggestions.iter().find(|s| s.text() == "stone").unwrap();
+    assert_eq!(stone.tooltip.as_deref(), Some("first"));
+}
+
+#[test]
+fn suggestions_rich_tooltip() {
+    let input = "give st";
+    let mut builder = SuggestionsBuilder::<Component>::new(input, input, 5);
+    builder.suggest_text_with_tooltip(
+        "stone",
+        Component {
+            text: String::from("A block"),
+            bold: true,
+        },
+    );
+    let suggestions = builder.build();
+    let tooltip = suggestions.iter().next().unwrap().tooltip.as_ref().unwrap();
+    assert!(tooltip.bold);
+    assert_eq!(tooltip.to_plain_string(), "A block");
+}
+
+#[test]
+fn ghost_text_unique_extension() {
+    let input = "give minecraft:sto";
+    let suggestions: Suggestions = Suggestions::create(
+        input,
+        vec![
+            Suggestion::new_text(5..18, "minecraft:stone"),
+            Suggestion::new_text(5..18, "minecraft:dirt"),
+        ],
+    );
+    assert_eq!(suggestions.ghost_text(input), Some((18, "ne".to_owned())));
+    let stone = suggestions
+        .iter()
+        .find(|s| s.text() == "minecraft:stone")
+        .unwrap();
+    assert_eq!(stone.completion_suffix(input), Some((18, "ne")));
+}
+
+#[test]
+fn ghost_text_ambiguous_extensions() {
+    let input = "give minecraft:sto";
+    let suggestions: Suggestions = Suggestions::create(
+        input,
+        vec![
+            Suggestion::new_text(5..18, "minecraft:stone"),
+            Suggestion::new_text(5..18, "minecraft:stone_bricks"),
+        ],
+    );
+    assert_eq!(suggestions.ghost_text(input), None);
+}
+
+#[test]
+fn completion_suffix_ignore_case_needs_replacement() {
+    let input = "give Minecraft:STO";
+    let suggestion: Suggestion = Suggestion::new_text(5..18, "minecraft:stone");
+    assert_eq!(suggestion.completion_suffix(input), None);
+    let completion = suggestion.completion_suffix_ignore_case(input).unwrap();
+    assert_eq!(completion.offset, 18);
+    assert_eq!(completion.suffix, "ne");
+    assert_eq!(completion.replacement, Some("minecraft:sto"));
+
+    let exact = suggestion
+        .completion_suffix_ignore_case("give minecraft:sto")
+        .unwrap();
+    assert_eq!(exact.replacement, None);
+}
+
+#[test]
+fn completion_suffix_no_match() {
+    let input = "give minecr aft:sto";
+    let suggestion: Suggestion = Suggestion::new_text(5..19, "minecraft:stone");
+    assert_eq!(suggestion.completion_suffix(input), None);
+    assert_eq!(suggestion.completion_suffix_ignore_case(input), None);
+    let suggestions = Suggestions::create(input, vec![suggestion]);
+    assert_eq!(suggestions.ghost_text(input), None);
+}
+
+#[test]
+fn builder_start_inside_multibyte_char() {
+    let input = "say 😀x";
+    let lower_case = input.to_lowercase();
+    let builder: SuggestionsBuilder = SuggestionsBuilder::new(input, &lower_case, 6);
+    assert_eq!(builder.start(), 4);
+    assert_eq!(builder.remaining(), "😀x");
+    assert_eq!(builder.remaining_lower_case(), "😀x");
+
+    let builder: SuggestionsBuilder = SuggestionsBuilder::new(input, &lower_case, 100);
+    assert_eq!(builder.remaining(), "");
+}
+
+#[test]
+fn builder_with_lowercase_changing_length() {
+    // 'İ' is 2 bytes, but its lower case form is 3 bytes
+    let input = "İİ Ab😀";
+    let builder: SuggestionsBuilder = SuggestionsBuilder::new_with_lowercase(input, 5);
+    assert_eq!(builder.start(), 5);
+    assert_eq!(builder.remaining(), "Ab😀");
+    assert_eq!(builder.remaining_lower_case(), "ab😀");
+
+    let builder: SuggestionsBuilder = builder.create_offset(8);
+    assert_eq!(builder.start(), 7);
+    assert_eq!(builder.remaining(), "😀");
+    assert_eq!(builder.remaining_lower_case(), "😀");
+}
+
+#[test]
+fn builder_add_all() {
+    let input = "give s";
+    let mut builder: SuggestionsBuilder = SuggestionsBuilder::new_with_lowercase(input, 5);
+    builder.add_all(vec![
+        Suggestion::new_text(5..6, "stone"),
+        Suggestion::new_text(5..6, "sand"),
+    ]);
+    let suggestions = builder.build();
+    assert_eq!(
+        suggestions.texts().collect::<Vec<_>>(),
+        vec!["sand", "stone"]
+    );
+}
+
+#[test]
+fn expand_and_apply_inside_multibyte_char() {
+    // 'é' spans the bytes 3..5
+    let input = "café au lait";
+    let suggestion: Suggestion = Suggestion::new_text(0..4, "cafés");
+    assert_eq!(suggestion.apply(input), "cafés au lait");
+
+    let expanded = suggestion.expand(input, 0..8);
+    assert_eq!(expanded.text(), "cafés au");
+    assert_eq!(expanded.range(), 0..8);
+
+    let suggestion: Suggestion = Suggestion::new_text(6..8, "an");
+    let expanded = suggestion.expand_owned(input, 4..8);
+    assert_eq!(expanded.range(), 3..8);
+    assert_eq!(expanded.text(), "é an");
+    assert_eq!(expanded.apply(input), "café an lait");
+}
+
+#[test]
+fn apply_to_shorter_input() {
+    // Computed for "give stone", but the input was edited since
+    let suggestion: Suggestion = Suggestion::new_text(5..10, "stone");
+    assert_eq!(suggestion.apply("give st"), "give stone");
+    assert_eq!(suggestion.apply("gi"), "gistone");
+    assert_eq!(suggestion.apply(""), "stone");
+    // The start of the range is within 'é' (bytes 3..5) of the edited input
+    let suggestion: Suggestion = Suggestion::new_text(4..6, "e");
+    assert_eq!(suggestion.apply("café"), "cafe");
+}
+
+fn permutations<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+    let mut result = Vec::new();
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, first.clone());
+            result.push(permutation);
+        }
+    }
+    result
+}
+
+#[test]
+fn mixed_suggestions_sort_deterministically() {
+    let items: Vec<Suggestion> = vec![
+        Suggestion::new_int(0..1, 10),
+        Suggestion::new_int(0..1, 9),
+        Suggestion::new_text(0..1, "10"),
+        Suggestion::new_text(0..1, "5"),
+        Suggestion::new_text(0..1, "B"),
+        Suggestion::new_text(0..1, "a"),
+    ];
+    let mut expected = items.clone();
+    expected.sort();
+    let texts: Vec<_> = expected.iter().map(Suggestion::text).collect();
+    assert_eq!(texts, vec!["9", "10", "10", "5", "B", "a"]);
+    assert_eq!(expected[0].int(), Some(9));
+
+    let created: Vec<String> = Suggestions::create("x", items.clone())
+        .texts()
+        .map(str::to_owned)
+        .collect();
+    for permutation in permutations(&items) {
+        let mut sorted = permutation.clone();
+        sorted.sort();
+        assert_eq!(sorted, expected);
+        let texts: Vec<String> = Suggestions::create("x", permutation)
+            .texts()
+            .map(str::to_owned)
+            .collect();
+        assert_eq!(texts, created);
+    }
+}
+
+fn teleport_suggestions(input: &str) -> Suggestions<'static, 'static> {
+    let mut builder = SuggestionsBuilder::new_with_lowercase(input, 9);
+    builder.set_transaction_id(Some(7));
+    builder.suggest_text("alice");
+    builder.suggest_text("alex");
+    builder.build().into_owned()
+}
+
+#[test]
+fn rebase_append() {
+    let suggestions = teleport_suggestions("teleport a");
+    assert_eq!(suggestions.transaction_id(), Some(7));
+    let rebased = suggestions.rebase("teleport a", "teleport al").unwrap();
+    assert_eq!(rebased.range(), 9..10);
+    assert_eq!(rebased.transaction_id(), Some(7));
+    assert_eq!(rebased, suggestions);
+}
+
+#[test]
+fn rebase_edit_after_range() {
+    let suggestions = teleport_suggestions("teleport a b");
+    assert_eq!(suggestions.range(), 9..12);
+    let rebased = suggestions
+        .rebase("teleport a b", "teleport a b c")
+        .unwrap();
+    assert_eq!(rebased.range(), 9..12);
+}
+
+#[test]
+fn rebase_edit_inside_range() {
+    let suggestions = teleport_suggestions("teleport a");
+    assert!(suggestions.rebase("teleport a", "teleport b").is_none());
+    assert!(suggestions.rebase("teleport a", "teleport").is_none());
+}
+
+#[test]
+fn rebase_deletion_before_range() {
+    let suggestions = teleport_suggestions("teleport a");
+    let rebased = suggestions.rebase("teleport a", "tp a").unwrap();
+    assert_eq!(rebased.range(), 3..4);
+    let ranges: Vec<_> = rebased.iter().map(Suggestion::range).collect();
+    assert_eq!(ranges, vec![3..4, 3..4]);
+    assert_eq!(rebased.iter().next().unwrap().apply("tp a"), "tp alex");
+}
+
+/// Suggests the boolean values, but also one that replaces the whole input.
+enum Type {
+    Bool,
+    Misbehaving,
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Type {
+    type Output = bool;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<bool, CommandSyntaxError<'i>> {
+        reader.read_boolean()
+    }
+    async fn list_suggestions<'t, 'm, A>(
+        &self,
+        context: &CommandContext<'i, Source, A>,
+        builder: SuggestionsBuilder<'i, 't, 'm>,
+    ) -> Suggestions<'t, 'm>
+    where
+        A: ArgumentType<'i, Source>,
+    {
+        let input = builder.input();
+        let start = builder.start();
+        let suggestions = BoolArgumentType.list_suggestions(context, builder).await;
+        match self {
+            Self::Bool => suggestions,
+            Self::Misbehaving => {
+                let mut list: Vec<_> = suggestions.into_iter().collect();
+                list.push(Suggestion::new_text(0..input.len(), "reset"));
+                Suggestions::new(0..input.len().max(start), list)
+            }
+        }
+    }
+}
+
+fn suggest_value(
+    dispatcher: &CommandDispatcher<Source, Type>,
+    command: &str,
+    input: &str,
+) -> Result<Vec<String>, String> {
+    let value = dispatcher.find_node(&[command, "value"]).unwrap();
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let builder = SuggestionsBuilder::new_with_lowercase(input, command.len() + 1);
+    pollster::block_on(dispatcher.tree().list_suggestions(value, &context, builder))
+        .map(|suggestions| suggestions.texts().map(str::to_owned).collect())
+        .map_err(|error| error.to_string())
+}
+
+fn range_check_dispatcher() -> CommandDispatcher<Source, Type> {
+    let mut dispatcher = CommandDispatcher::new();
+    for (name, argument_type) in [("good", Type::Bool), ("bad", Type::Misbehaving)] {
+        let mut command = literal(name);
+        command.then(argument("value", argument_type));
+        dispatcher.register(command);
+    }
+    dispatcher
+}
+
+#[test]
+fn suggestions_outside_of_argument_are_dropped() {
+    let dispatcher = range_check_dispatcher();
+    assert_eq!(
+        suggest_value(&dispatcher, "good", "good "),
+        Ok(vec!["false".into(), "true".into()])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "bad", "bad t"),
+        Ok(vec!["true".into()])
+    );
+}
+
+#[test]
+fn suggestions_outside_of_argument_fail_strictly() {
+    let mut dispatcher = range_check_dispatcher();
+    dispatcher
+        .tree_mut()
+        .set_suggestion_range_check(SuggestionRangeCheck::Strict);
+    assert_eq!(
+        suggest_value(&dispatcher, "good", "good t"),
+        Ok(vec!["true".into()])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "bad", "bad t"),
+        Err("Suggestion for 0..5 is outside of the argument at 4..5".into())
+    );
+}
+
+#[test]
+fn remove_outside_shrinks_range() {
+    let mut suggestions: Suggestions = Suggestions::new(
+        0..8,
+        vec![
+            Suggestion::new_text(0..8, "replace"),
+            Suggestion::new_text(5..6, "a"),
+            Suggestion::new_text(4..8, "b"),
+        ],
+    );
+    let removed = suggestions.remove_outside(4..8);
+    assert_eq!(removed, vec![Suggestion::new_text(0..8, "replace")]);
+    assert_eq!(suggestions.range(), 4..8);
+    assert_eq!(suggestions.len(), 2);
+    assert!(suggestions.remove_outside(4..8).is_empty());
+}
+
+fn numbers<'c, 'i>(
+    _context: &'c CommandContext<'i, Source, Type>,
+    mut builder: SuggestionsBuilder<'i, 'c, 'c>,
+) -> SuggestionFuture<'c, 'i> {
+    Box::pin(async move {
+        builder.suggest_int(1).suggest_int(2);
+        Ok(builder.build())
+    })
+}
+
+#[test]
+fn suggestion_providers() {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut item = literal("item");
+    let items = StaticSuggestions::new(["Stone", "stick", "dirt"]);
+    item.then_build(argument("value", Type::Bool), |value| {
+        value.suggests(Shared::new(items))
+    });
+    dispatcher.register(item);
+    let mut count = literal("count");
+    count.then_build(argument("value", Type::Bool), |value| {
+        value.suggests(Shared::new(numbers))
+    });
+    dispatcher.register(count);
+    let mut hidden = literal("hidden");
+    hidden.then_build(argument("value", Type::Bool), |value| {
+        value.suggests(Shared::new(NoSuggestions))
+    });
+    dispatcher.register(hidden);
+
+    assert_eq!(
+        suggest_value(&dispatcher, "item", "item ST"),
+        Ok(vec!["stick".into(), "Stone".into()])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "item", "item "),
+        Ok(vec!["dirt".into(), "stick".into(), "Stone".into()])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "count", "count "),
+        Ok(vec!["1".into(), "2".into()])
+    );
+    assert_eq!(suggest_value(&dispatcher, "hidden", "hidden "), Ok(vec![]));
+}
+
+fn item_ids<'c, 'i>(
+    _context: &'c CommandContext<'i, Source, Type>,
+    mut builder: SuggestionsBuilder<'i, 'c, 'c>,
+) -> SuggestionFuture<'c, 'i> {
+    Box::pin(async move {
+        // Added in reverse, so that insertion order differs from sorted order
+        for id in (0..10_000).rev() {
+            builder.suggest_text(format!("item_{id:05}"));
+        }
+        Ok(builder.build())
+    })
+}
+
+#[test]
+fn limit_keeps_first_sorted_suggestions() {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut give = literal("give");
+    give.then_build(argument("value", Type::Bool), |value| {
+        value.suggests(Shared::new(item_ids))
+    });
+    dispatcher.register(give);
+    let value = dispatcher.find_node(&["give", "value"]).unwrap();
+    let input = "give ";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    let mut builder = SuggestionsBuilder::new_with_lowercase(input, input.len());
+    builder.with_limit(50);
+    assert_eq!(builder.limit(), Some(50));
+    let suggestions =
+        pollster::block_on(dispatcher.tree().list_suggestions(value, &context, builder)).unwrap();
+    assert_eq!(suggestions.len(), 50);
+    assert!(suggestions.truncated());
+    let expected: Vec<String> = (0..50).map(|id| format!("item_{id:05}")).collect();
+    assert_eq!(suggestions.texts().collect::<Vec<_>>(), expected);
+
+    let mut builder = SuggestionsBuilder::new_with_lowercase(input, input.len());
+    builder.with_limit(2).suggest_text("b").suggest_text("a");
+    let limited = builder.build();
+    assert!(!limited.truncated());
+    let mut builder = SuggestionsBuilder::new_with_lowercase(input, input.len());
+    builder
+        .with_limit(2)
+        .suggest_text("c")
+        .suggest_int(1)
+        .add_all(limited.clone());
+    let dropped = builder.build();
+    assert!(dropped.truncated());
+    assert_eq!(dropped.texts().collect::<Vec<_>>(), vec!["1", "a"]);
+    let merged = Suggestions::merge(input, [suggestions.clone(), limited.clone()]);
+    assert!(merged.truncated());
+    assert_eq!(merged.len(), 52);
+    assert!(!Suggestions::merge(input, [limited]).truncated());
+}
+
+fn completions(
+    dispatcher: &CommandDispatcher<Source, Type>,
+    input: &str,
+    cursor: usize,
+) -> Vec<String> {
+    let parse = dispatcher.parse(input, Source);
+    pollster::block_on(dispatcher.get_completion_suggestions(&parse, cursor))
+        .unwrap()
+        .texts()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn completion_suggestions_at_cursor() {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut foo = literal("foo");
+    foo.then(literal("bar")).then(literal("baz"));
+    dispatcher.register(foo);
+    let mut fizz = literal("fizz");
+    fizz.then(argument("value", Type::Bool));
+    dispatcher.register(fizz);
+    let mut hidden = literal("forbidden");
+    hidden.requires(|_: &Source| false);
+    dispatcher.register(hidden);
+    let foo = dispatcher.find_node(&["foo"]).unwrap();
+
+    assert_eq!(completions(&dispatcher, "", 0), vec!["fizz", "foo"]);
+    assert_eq!(completions(&dispatcher, "fo", 2), vec!["foo"]);
+    assert_eq!(completions(&dispatcher, "foo ", 4), vec!["bar", "baz"]);
+    assert_eq!(completions(&dispatcher, "foo ba", 6), vec!["bar", "baz"]);
+    // Complete tokens aren't suggested again
+    assert!(completions(&dispatcher, "foo bar", 7).is_empty());
+    // Within the first token, the rest of the input is ignored
+    assert_eq!(completions(&dispatcher, "foo bar", 2), vec!["foo"]);
+    assert_eq!(completions(&dispatcher, "fizz t", 6), vec!["true"]);
+
+    let parse = dispatcher.parse("foo ba", Source);
+    let context = parse.find_suggestion_context(6).unwrap();
+    assert_eq!(context.parent, foo);
+    assert_eq!(context.start_pos, 4);
+    let context = parse.find_suggestion_context(1).unwrap();
+    assert_eq!(context.parent, dispatcher.root());
+    assert_eq!(context.start_pos, 0);
+
+    let parse = dispatcher.parse("foo ba", Source);
+    let suggestions = pollster::block_on(dispatcher.get_completion_suggestions(&parse, 6)).unwrap();
+    assert_eq!(suggestions.range(), 4..6);
+}
+
+#[test]
+fn provider_replaces_or_merges_with_type() {
+    let mut dispatcher = CommandDispatcher::new();
+    for (name, mode) in [
+        ("replace", SuggestionMode::Replace),
+        ("merge", SuggestionMode::Merge),
+    ] {
+        let mut command = literal(name);
+        let constants = StaticSuggestions::new(["on", "off"]);
+        command.then_build(argument("value", Type::Bool), |value| {
+            value.suggests(Shared::new(constants)).suggestion_mode(mode)
+        });
+        dispatcher.register(command);
+    }
+    let merge = dispatcher.find_node(&["merge", "value"]).unwrap();
+    assert_eq!(
+        dispatcher
+            .tree()
+            .as_argument(merge)
+            .unwrap()
+            .suggestion_mode(),
+        SuggestionMode::Merge
+    );
+
+    assert_eq!(
+        suggest_value(&dispatcher, "replace", "replace "),
+        Ok(vec!["off".into(), "on".into()])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "merge", "merge "),
+        Ok(vec![
+            "false".into(),
+            "off".into(),
+            "on".into(),
+            "true".into()
+        ])
+    );
+    assert_eq!(
+        suggest_value(&dispatcher, "merge", "merge o"),
+        Ok(vec!["off".into(), "on".into()])
+    );
+}
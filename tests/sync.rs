@@ -0,0 +1,52 @@
+#![cfg(feature = "sync")]
+
+mod common;
+
+use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Arc,
+};
+
+use brigadier::{
+    arguments::BoolArgumentType, builder::literal, command::SharedCommand, context::CommandContext,
+    dispatcher::CommandDispatcher, tree::Tree,
+};
+
+use common::{toggle_dispatcher, Source};
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn tree_is_send() {
+    assert_send::<Tree<Source, BoolArgumentType>>();
+    assert_send::<CommandDispatcher<Source, BoolArgumentType>>();
+    assert_send::<CommandContext<'static, Source, BoolArgumentType>>();
+    assert_send::<SharedCommand<Source, BoolArgumentType>>();
+}
+
+#[test]
+fn parse_on_another_thread() {
+    let dispatcher = toggle_dispatcher();
+    let parsed = std::thread::spawn(move || {
+        let parse = dispatcher.parse("toggle true", Source);
+        parse.context().arguments()["value"].result
+    })
+    .join()
+    .unwrap();
+    assert!(parsed);
+}
+
+#[test]
+fn execute_on_another_thread() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let total = Arc::new(AtomicI32::new(0));
+    let mut add = literal("add");
+    let sum = Arc::clone(&total);
+    add.executes(move |_| Ok(sum.fetch_add(1, Ordering::SeqCst) + 1));
+    dispatcher.register(add);
+    let result = std::thread::spawn(move || dispatcher.execute_input("add", Source))
+        .join()
+        .unwrap();
+    assert_eq!(result, Ok(1));
+    assert_eq!(total.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "testing")]
+
+use brigadier::{
+    arguments::{
+        ArgCapabilities, ArgumentType, BoolArgumentType, DoubleArgumentType, StringArgumentType,
+        UIntArgumentType, ULongArgumentType,
+    },
+    errors::CommandSyntaxError,
+    testing::{assert_examples_parse, assert_roundtrip_suggestions, MockContext},
+    StringReader,
+};
+
+#[test]
+fn builtin_examples_parse() {
+    assert_examples_parse(&BoolArgumentType);
+    assert_examples_parse(&DoubleArgumentType::new(..));
+    assert_examples_parse(&UIntArgumentType::new(..));
+    assert_examples_parse(&ULongArgumentType::new(..));
+    assert_examples_parse(&StringArgumentType::word());
+    assert_examples_parse(&StringArgumentType::string());
+    assert_examples_parse(&StringArgumentType::greedy_string());
+}
+
+#[test]
+fn bool_suggestions_roundtrip() {
+    let suggestions = assert_roundtrip_suggestions(&BoolArgumentType, "");
+    assert_eq!(
+        suggestions.texts().collect::<Vec<_>>(),
+        vec!["false", "true"]
+    );
+    let suggestions = assert_roundtrip_suggestions(&BoolArgumentType, "T");
+    assert_eq!(suggestions.texts().collect::<Vec<_>>(), vec!["true"]);
+    assert!(assert_roundtrip_suggestions(&DoubleArgumentType::new(..), "1").is_empty());
+}
+
+/// Claims an example which it can't parse completely, unless its capabilities say
+/// that the examples don't parse.
+struct Sloppy(ArgCapabilities);
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i, S> ArgumentType<'i, S> for Sloppy
+where
+    S: brigadier::CommandSource,
+{
+    type Output = bool;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<bool, CommandSyntaxError<'i>> {
+        reader.read_boolean()
+    }
+    fn examples(&self) -> &'static [&'static str] {
+        &["true", "false!"]
+    }
+    fn capabilities(&self) -> ArgCapabilities {
+        self.0
+    }
+}
+
+#[test]
+fn partially_parsed_example_fails() {
+    let panic = std::panic::catch_unwind(|| assert_examples_parse(&Sloppy(ArgCapabilities::all())))
+        .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(message, r#"Example "false!" is only parsed up to "false""#);
+}
+
+#[test]
+fn examples_without_capability_are_skipped() {
+    assert_examples_parse(&Sloppy(ArgCapabilities::all() - ArgCapabilities::EXAMPLES));
+    // "-1" is out of the range
+    assert_examples_parse(&DoubleArgumentType::new(0.0..=10.0));
+    assert_examples_parse(&UIntArgumentType::new(1..=10));
+}
+
+#[test]
+fn mock_context_with_arguments() {
+    let input = "1.5 2";
+    let context = MockContext::<DoubleArgumentType>::new()
+        .argument("x", 0..3, 1.5)
+        .argument("y", 4..5, 2.0)
+        .build(input);
+    assert_eq!(context.get_argument("x"), Some(&1.5));
+    assert_eq!(context.get_argument("y"), Some(&2.0));
+    assert_eq!(context.input(), input);
+}
@@ -0,0 +1,465 @@
+mod common;
+
+use brigadier::{
+    arguments::{BoolArgumentType, DoubleArgumentType},
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    suggestion::StaticSuggestions,
+    Shared,
+};
+
+use common::Source;
+
+#[test]
+fn reachable_directly_and_via_alias() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let teleport = dispatcher.register(literal("teleport"));
+    let flag = dispatcher
+        .tree_mut()
+        .add_node(argument("flag", BoolArgumentType));
+    dispatcher.tree_mut().add_child(teleport, flag).unwrap();
+    let mut tp = literal("tp");
+    tp.redirect(teleport);
+    let tp = dispatcher.register(tp);
+
+    let mut expected = vec![vec![teleport, flag], vec![tp, flag]];
+    expected.sort();
+    let reachable = dispatcher.tree().reachable_from_root_paths(flag, 10);
+    assert_eq!(reachable.paths, expected);
+    assert!(!reachable.truncated);
+
+    let mut expected = vec![vec![teleport], vec![tp]];
+    expected.sort();
+    let reachable = dispatcher.tree().reachable_from_root_paths(teleport, 10);
+    assert_eq!(reachable.paths, expected);
+}
+
+#[test]
+fn reachable_via_shared_subtree() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let give = dispatcher.register(literal("give"));
+    let take = dispatcher.register(literal("take"));
+    let item = dispatcher.tree_mut().add_node(literal("item"));
+    let count = dispatcher
+        .tree_mut()
+        .add_node(argument("count", BoolArgumentType));
+    let tree = dispatcher.tree_mut();
+    tree.add_child(item, count).unwrap();
+    tree.add_child(give, item).unwrap();
+    tree.add_child(take, item).unwrap();
+
+    let mut expected = vec![vec![give, item, count], vec![take, item, count]];
+    expected.sort();
+    let reachable = dispatcher.tree().reachable_from_root_paths(count, 10);
+    assert_eq!(reachable.paths, expected);
+}
+
+#[test]
+fn reachable_via_redirect_to_root() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let say = dispatcher.register(literal("say"));
+    let mut execute = literal("execute");
+    let mut run = literal("run");
+    run.redirect(root);
+    execute.then(run);
+    let execute = dispatcher.register(execute);
+    let run = dispatcher.tree_mut().add_node(literal("run"));
+    let run = dispatcher.tree_mut().add_child(execute, run).unwrap();
+
+    let mut expected = vec![vec![say], vec![execute, run, say]];
+    expected.sort();
+    let reachable = dispatcher.tree().reachable_from_root_paths(say, 10);
+    assert_eq!(reachable.paths, expected);
+
+    let reachable = dispatcher.tree().reachable_from_root_paths(say, 1);
+    assert_eq!(reachable.paths.len(), 1);
+    assert!(reachable.truncated);
+}
+
+#[test]
+fn all_usage_of_redirects() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let mut teleport = literal("teleport");
+    teleport.then(argument("flag", BoolArgumentType));
+    let teleport = dispatcher.register(teleport);
+    let mut tp = literal("tp");
+    tp.redirect(teleport);
+    dispatcher.register(tp);
+    let mut execute = literal("execute");
+    let mut run = literal("run");
+    run.redirect(root);
+    let mut as_ = literal("as");
+    let mut flag = argument("flag", BoolArgumentType);
+    flag.redirect(root);
+    as_.then(flag);
+    execute.then(run).then(as_);
+    dispatcher.register(execute);
+
+    let tree = dispatcher.tree();
+    assert_eq!(tree.usage_text(teleport), "teleport");
+    assert_eq!(
+        tree.get_all_usage(root, &Source, false),
+        vec!["execute as <flag> ...", "execute run ...", "tp -> teleport"]
+    );
+    assert_eq!(
+        tree.get_all_usage(teleport, &Source, true),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn usage_text_of_forks_and_redirects() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let mut execute = literal("execute");
+    execute.executes(|_| Ok(1));
+    let execute = dispatcher.register(execute);
+    let mut each = literal::<Source, BoolArgumentType>("each");
+    each.fork(execute, |context| Ok(vec![context.source().clone(); 2]));
+    let each = dispatcher.register(each);
+    let mut exec = literal("exec");
+    exec.redirect(execute);
+    let exec = dispatcher.register(exec);
+    let mut run = literal("run");
+    run.redirect(root);
+    let run = dispatcher.register(run);
+
+    let tree = dispatcher.tree();
+    assert!(tree.get(each).unwrap().is_fork());
+    assert!(!tree.get(exec).unwrap().is_fork());
+    assert_eq!(tree.get(exec).unwrap().redirect(), Some(execute));
+    assert!(tree.get(execute).unwrap().is_executable());
+    assert!(!tree.get(each).unwrap().is_executable());
+    assert_eq!(tree.usage_text_with_redirect(each), "each -> execute ...");
+    assert_eq!(tree.usage_text_with_redirect(exec), "exec -> execute");
+    assert_eq!(tree.usage_text_with_redirect(run), "run ...");
+    assert_eq!(tree.usage_text_with_redirect(execute), "execute");
+
+    assert!(dispatcher.tree_mut().remove_node(execute));
+    assert_eq!(dispatcher.tree().usage_text_with_redirect(each), "each");
+}
+
+#[test]
+fn smart_usage() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let mut give = literal("give");
+    let mut item = literal("item");
+    item.then(argument("count", BoolArgumentType));
+    give.then(item).then(literal("player"));
+    let give = dispatcher.register(give);
+    let mut teleport = literal("teleport");
+    let mut flag = argument("flag", BoolArgumentType);
+    flag.then(literal("now"));
+    teleport.then(flag);
+    let teleport = dispatcher.register(teleport);
+    let tp = dispatcher.register_alias("tp", teleport);
+    let kill = dispatcher.register(literal("kill"));
+
+    let usage = dispatcher.tree().get_smart_usage(root, &Source);
+    let usage: Vec<_> = usage.into_iter().collect();
+    assert_eq!(
+        usage,
+        vec![
+            (give, "give (item|player)".to_owned()),
+            (kill, "kill".to_owned()),
+            (teleport, "teleport <flag> now".to_owned()),
+            (tp, "tp -> teleport".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn remove_node() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let mut teleport = literal("teleport");
+    teleport.then(argument("flag", BoolArgumentType));
+    let teleport = dispatcher.register(teleport);
+    let tp = dispatcher.register_alias("tp", teleport);
+    dispatcher.register(literal("kill"));
+
+    let generation = dispatcher.tree().generation();
+    assert!(dispatcher.tree_mut().remove_node(teleport));
+    assert!(!dispatcher.tree_mut().remove_node(teleport));
+    assert!(dispatcher.tree().generation() > generation);
+
+    let tree = dispatcher.tree();
+    assert!(tree.get(teleport).is_none());
+    assert_eq!(tree.name(tp), Some("tp"));
+    assert_eq!(tree.get_smart_usage(root, &Source).len(), 2);
+    assert!(tree.get_all_usage(root, &Source, false).is_empty());
+    assert!(dispatcher
+        .parse("teleport", Source)
+        .context()
+        .nodes()
+        .is_empty());
+}
+
+#[test]
+fn write_dot_with_cycle() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let execute = dispatcher.register(literal("execute"));
+    let mut run = literal("run");
+    run.redirect(execute);
+    let mut flag = argument("flag", BoolArgumentType);
+    flag.then(run);
+    let flag = dispatcher.tree_mut().add_node(flag);
+    dispatcher.tree_mut().add_child(execute, flag).unwrap();
+
+    let mut dot = Vec::new();
+    dispatcher.tree().write_dot(&mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains("[label=\"<root>\", shape=doublecircle]"));
+    assert!(dot.contains("[label=\"execute\", shape=box]"));
+    assert!(dot.contains("[label=\"<flag>\", shape=ellipse]"));
+    assert_eq!(dot.matches("[style=dashed]").count(), 1);
+    assert_eq!(dot.matches(" -> ").count(), 4);
+}
+
+#[test]
+fn parents_survive_merge() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut time = literal("time");
+    let mut set = literal("set");
+    set.then(literal("day"));
+    time.then(set);
+    let time = dispatcher.register(time);
+    let mut time_again = literal("time");
+    let mut set_again = literal("set");
+    set_again.then(literal("night"));
+    time_again.then(set_again);
+    assert_eq!(dispatcher.register(time_again), time);
+
+    let tree = dispatcher.tree();
+    let set = dispatcher.find_node(&["time", "set"]).unwrap();
+    let day = dispatcher.find_node(&["time", "set", "day"]).unwrap();
+    let night = dispatcher.find_node(&["time", "set", "night"]).unwrap();
+    assert_eq!(tree.parent(time), Some(dispatcher.root()));
+    assert_eq!(tree.parent(set), Some(time));
+    assert_eq!(tree.parent(day), Some(set));
+    assert_eq!(tree.parent(night), Some(set));
+    assert_eq!(tree.parent(dispatcher.root()), None);
+
+    dispatcher.tree_mut().remove_node(set);
+    assert_eq!(dispatcher.tree().parent(day), None);
+}
+
+#[test]
+fn merge_deep_chain() {
+    const DEPTH: usize = 10_000;
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let root = dispatcher.root();
+    let names: Vec<String> = (0..DEPTH).map(|i| i.to_string()).collect();
+    let tree = dispatcher.tree_mut();
+    let mut chain = |leaf: &str| {
+        let top = tree.add_node(literal("deep"));
+        let mut parent = top;
+        for name in names.iter().map(String::as_str).chain([leaf]) {
+            let child = tree.add_node(literal(name));
+            parent = tree.add_child(parent, child).unwrap();
+        }
+        top
+    };
+    let first = chain("first");
+    let second = chain("second");
+    let tree = dispatcher.tree_mut();
+    assert_eq!(tree.add_child(root, first), Ok(first));
+    assert_eq!(tree.add_child(root, second), Ok(first));
+
+    let mut path: Vec<&str> = vec!["deep"];
+    path.extend(names.iter().map(String::as_str));
+    let last = dispatcher.find_node(&path).unwrap();
+    for leaf in ["first", "second"] {
+        path.push(leaf);
+        let leaf = dispatcher.find_node(&path).unwrap();
+        assert_eq!(dispatcher.tree().parent(leaf), Some(last));
+        path.pop();
+    }
+}
+
+#[test]
+fn descendants_and_leaves() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut give = literal("give");
+    give.then(argument("flag", BoolArgumentType));
+    let give = dispatcher.register(give);
+    let flag = dispatcher.find_node(&["give", "flag"]).unwrap();
+    let mut tp = literal("tp");
+    tp.redirect(dispatcher.root());
+    let tp = dispatcher.register(tp);
+    let mut inserted = vec![give, flag, tp];
+    // A shared child and a cycle are still visited once
+    let tree = dispatcher.tree_mut();
+    let shared = tree.add_node(literal("shared"));
+    tree.add_child(give, shared).unwrap();
+    tree.add_child(flag, shared).unwrap();
+    tree.add_child(shared, give).unwrap();
+    inserted.push(shared);
+
+    let tree = dispatcher.tree();
+    let descendants: Vec<_> = tree.descendants(dispatcher.root()).collect();
+    assert_eq!(descendants, vec![give, flag, shared, tp]);
+    inserted.sort();
+    let mut visited = descendants.clone();
+    visited.sort();
+    assert_eq!(visited, inserted);
+
+    let leaves: Vec<_> = tree.leaves(dispatcher.root()).collect();
+    assert_eq!(leaves, vec![tp]);
+    assert_eq!(tree.descendants(tp).count(), 0);
+}
+
+#[test]
+fn literal_and_argument_with_same_name() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut toggle = literal("toggle");
+    let mut value = literal("value");
+    value.executes(|_| Ok(1));
+    toggle.then(value);
+    let mut flag = argument("value", BoolArgumentType);
+    flag.executes(|_| Ok(2));
+    toggle.then(flag);
+    let toggle = dispatcher.register(toggle);
+
+    let children = dispatcher.tree().descendants(toggle).collect::<Vec<_>>();
+    assert_eq!(children.len(), 2);
+    assert_ne!(children[0], children[1]);
+    assert_eq!(dispatcher.execute_input("toggle value", Source), Ok(1));
+    assert_eq!(dispatcher.execute_input("toggle true", Source), Ok(2));
+    // Registering them again merges each onto the node of the same type
+    let mut again = literal("toggle");
+    again.then(literal("value"));
+    again.then(argument("value", BoolArgumentType));
+    dispatcher.register(again);
+    assert_eq!(dispatcher.tree().descendants(toggle).count(), 2);
+}
+
+#[test]
+fn node_components_by_path() {
+    let mut dispatcher = CommandDispatcher::<Source, DoubleArgumentType>::new();
+    let mut speed = literal("speed");
+    speed.then_build(
+        argument("value", DoubleArgumentType::new(0.0..=10.0)),
+        |value| value.suggests(Shared::new(StaticSuggestions::new(["1", "2"]))),
+    );
+    speed.then(argument("factor", DoubleArgumentType::new(..)));
+    dispatcher.register(speed);
+    let tree = dispatcher.tree();
+
+    let value = dispatcher.find_node(&["speed", "value"]).unwrap();
+    let argument = tree.as_argument(value).unwrap();
+    assert_eq!(argument.name(), "value");
+    assert_eq!(argument.argument_type().range, 0.0..=10.0);
+    assert!(argument.custom_suggestions().is_some());
+    assert!(tree.custom_suggestions(value).is_some());
+    assert!(tree.as_literal(value).is_none());
+
+    let factor = dispatcher.find_node(&["speed", "factor"]).unwrap();
+    assert!(tree.custom_suggestions(factor).is_none());
+
+    let speed = dispatcher.find_node(&["speed"]).unwrap();
+    assert_eq!(tree.as_literal(speed).unwrap().literal(), "speed");
+    assert!(tree.as_argument(speed).is_none());
+    assert!(!tree.get(speed).unwrap().is_root());
+    assert!(tree.get(dispatcher.root()).unwrap().is_root());
+}
+
+#[test]
+fn write_tree_with_redirect() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut execute = literal("execute");
+    execute.then_build(argument("flag", BoolArgumentType), |flag| {
+        flag.executes(|_| Ok(1))
+    });
+    let execute = dispatcher.register(execute);
+    let mut run = literal("run");
+    run.redirect(execute);
+    let run = dispatcher.tree_mut().add_node(run);
+    let flag = dispatcher.find_node(&["execute", "flag"]).unwrap();
+    dispatcher.tree_mut().add_child(flag, run).unwrap();
+
+    let debug_node = |node| format!("{:?}", dispatcher.tree().get(node).unwrap());
+    assert!(debug_node(execute).contains("executable: false"));
+    assert!(debug_node(flag).contains("executable: true, redirect: None, children: 1"));
+
+    let mut out = Vec::new();
+    dispatcher.tree().write_tree(&mut out, execute).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out, "#2 execute\n  #3 <flag> *\n    #4 run -> #2\n");
+    let debug = format!("{:?}", dispatcher.tree());
+    assert_eq!(
+        debug,
+        "#1 <root>\n  #2 execute\n    #3 <flag> *\n      #4 run -> #2\n"
+    );
+
+    // Cycles of children are only expanded once
+    dispatcher.tree_mut().add_child(flag, execute).unwrap();
+    let mut out = Vec::new();
+    dispatcher.tree().write_tree(&mut out, execute).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "#2 execute\n  #3 <flag> *\n    #2 execute ^\n    #4 run -> #2\n"
+    );
+}
+
+#[test]
+fn structurally_equal_nodes_match() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    for (name, executable) in [("a", true), ("b", true), ("c", false)] {
+        let mut command = literal(name);
+        command.then_build(literal("x"), |x| {
+            x.then(argument("value", BoolArgumentType));
+            if executable {
+                x.executes(|_| Ok(1));
+            }
+            x
+        });
+        dispatcher.register(command);
+    }
+    let tree = dispatcher.tree();
+    let x = |name| dispatcher.find_node(&[name, "x"]).unwrap();
+    assert!(tree.nodes_match(x("a"), x("b")));
+    assert!(!tree.nodes_match(x("a"), x("c")));
+    let a = dispatcher.find_node(&["a"]).unwrap();
+    let b = dispatcher.find_node(&["b"]).unwrap();
+    assert!(!tree.nodes_match(a, b));
+    assert!(tree.nodes_match(a, a));
+
+    // Adding a child again keeps its subtree attached to it
+    let a_x = x("a");
+    let root = dispatcher.root();
+    assert_eq!(dispatcher.tree_mut().add_child(root, a), Ok(a));
+    assert_eq!(dispatcher.tree().parent(a_x), Some(a));
+}
+
+#[test]
+fn iterate_and_walk_nodes() {
+    let mut dispatcher = CommandDispatcher::<Source, BoolArgumentType>::new();
+    let mut give = literal("give");
+    give.then(argument("value", BoolArgumentType))
+        .then(literal("all"));
+    dispatcher.register(give);
+    let orphan = dispatcher.tree_mut().add_node(literal("orphan"));
+    let tree = dispatcher.tree();
+    // The root, three attached nodes and the orphan
+    assert_eq!(tree.nodes().count(), 5);
+    assert!(tree.nodes().any(|(node_id, _)| node_id == orphan));
+
+    let mut walked = Vec::new();
+    tree.walk_from(dispatcher.root(), |node_id, node| {
+        walked.push((tree.name(node_id), node.is_root()));
+    });
+    assert_eq!(
+        walked,
+        vec![
+            (Some(""), true),
+            (Some("give"), false),
+            (Some("all"), false),
+            (Some("value"), false),
+        ]
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use brigadier::{
+    arguments::BoolArgumentType,
+    builder::{argument, literal},
+    dispatcher::{CommandDispatcher, SeparatorStyle},
+    metrics::LiteralMatchCounters,
+    tree::{LiteralMatchMode, LiteralMatching},
+};
+
+use common::Source;
+
+fn dispatcher() -> CommandDispatcher<Source, BoolArgumentType> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut time = literal("time");
+    let mut set = literal("set");
+    set.then(argument("value", BoolArgumentType));
+    time.then(set);
+    dispatcher.register(time);
+    let mut teleport = literal("teleport");
+    teleport.then(argument("flag", BoolArgumentType));
+    let teleport = dispatcher.register(teleport);
+    let mut tp = literal("tp");
+    tp.redirect(teleport);
+    dispatcher.register(tp);
+    dispatcher.register(literal("tell"));
+    dispatcher.set_literal_matching(LiteralMatching {
+        case_insensitive: true,
+        unique_prefix: true,
+    });
+    dispatcher.set_capture_typed_form(true);
+    dispatcher
+}
+
+fn typed(
+    dispatcher: &CommandDispatcher<Source, BoolArgumentType>,
+    input: &str,
+) -> Vec<(String, LiteralMatchMode)> {
+    let parse = dispatcher.parse(input, Source);
+    assert!(
+        !parse.reader().can_read(),
+        "{input} should parse completely"
+    );
+    let form = parse.typed_form().unwrap();
+    assert_eq!(form.separators, SeparatorStyle::Single);
+    form.literals
+        .iter()
+        .map(|literal| (literal.typed.to_owned(), literal.mode))
+        .collect()
+}
+
+#[test]
+fn typed_form_case_insensitive_and_prefix() {
+    let dispatcher = dispatcher();
+    assert_eq!(
+        typed(&dispatcher, "TiMe se true"),
+        vec![
+            (String::from("TiMe"), LiteralMatchMode::CaseInsensitive),
+            (String::from("se"), LiteralMatchMode::UniquePrefix),
+        ]
+    );
+    assert_eq!(
+        typed(&dispatcher, "TELEP false"),
+        vec![(String::from("TELEP"), LiteralMatchMode::UniquePrefix)]
+    );
+    assert_eq!(
+        typed(&dispatcher, "time set true"),
+        vec![
+            (String::from("time"), LiteralMatchMode::Exact),
+            (String::from("set"), LiteralMatchMode::Exact),
+        ]
+    );
+}
+
+#[test]
+fn typed_form_alias() {
+    let dispatcher = dispatcher();
+    assert_eq!(
+        typed(&dispatcher, "tp true"),
+        vec![(String::from("tp"), LiteralMatchMode::Alias)]
+    );
+}
+
+#[test]
+fn ambiguous_prefix_does_not_match() {
+    let dispatcher = dispatcher();
+    let parse = dispatcher.parse("te true", Source);
+    assert_eq!(parse.reader().cursor(), 0);
+    assert!(!parse.exceptions().is_empty());
+}
+
+#[test]
+fn typed_form_is_opt_in() {
+    let mut dispatcher = dispatcher();
+    dispatcher.set_capture_typed_form(false);
+    assert!(dispatcher.parse("time", Source).typed_form().is_none());
+}
+
+#[test]
+fn literal_match_counters() {
+    let dispatcher = dispatcher();
+    let mut counters = LiteralMatchCounters::new();
+    for input in ["TIME", "time", "Time set true"] {
+        counters.record(&dispatcher.parse(input, Source).typed_form().unwrap());
+    }
+    let parse = dispatcher.parse("time", Source);
+    let time = parse.context().nodes()[0].node;
+    assert_eq!(counters.count(time, LiteralMatchMode::CaseInsensitive), 2);
+    assert_eq!(counters.count(time, LiteralMatchMode::Exact), 1);
+    assert_eq!(counters.count(time, LiteralMatchMode::UniquePrefix), 0);
+}
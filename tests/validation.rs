@@ -0,0 +1,152 @@
+mod common;
+
+use brigadier::{
+    arguments::ArgumentType,
+    builder::{argument, literal},
+    dispatcher::CommandDispatcher,
+    errors::CommandSyntaxError,
+    tree::validation::ValidationError,
+    StringReader,
+};
+
+use common::Source;
+
+enum Type {
+    Word,
+    /// Consumes the rest of the input
+    Greedy,
+}
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Type {
+    type Output = &'i str;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<&'i str, CommandSyntaxError<'i>> {
+        match self {
+            Self::Word => reader.read_unquoted_string(),
+            Self::Greedy => {
+                let rest = reader.remaining();
+                reader.set_cursor(reader.input().len());
+                Ok(rest)
+            }
+        }
+    }
+    fn is_greedy(&self) -> bool {
+        matches!(self, Self::Greedy)
+    }
+}
+
+fn executable<'a>(
+    name: &'a str,
+    argument_type: Type,
+) -> brigadier::builder::RequiredArgumentBuilder<'a, Source, Type> {
+    let mut builder = argument(name, argument_type);
+    builder.executes(|_| Ok(1));
+    builder
+}
+
+#[test]
+fn valid_tree() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let mut say = literal("say");
+    say.then(executable("message", Type::Greedy));
+    dispatcher.register(say);
+    // Arguments after a redirect are in a new context
+    let root = dispatcher.root();
+    let mut execute = literal("execute");
+    let mut as_ = literal("as");
+    let mut message = argument("message", Type::Word);
+    message.redirect(root);
+    as_.then(message);
+    execute.then(as_);
+    dispatcher.register(execute);
+    assert_eq!(dispatcher.tree().validate(), Ok(()));
+}
+
+#[test]
+fn duplicate_argument_names() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let mut tp = literal("tp");
+    let mut first = argument("target", Type::Word);
+    let mut middle = literal("to");
+    middle.then(executable("target", Type::Word));
+    first.then(middle);
+    tp.then(first);
+    dispatcher.register(tp);
+    let first = dispatcher.find_node(&["tp", "target"]).unwrap();
+    let second = dispatcher
+        .find_node(&["tp", "target", "to", "target"])
+        .unwrap();
+    let errors = dispatcher.tree().validate().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ValidationError::DuplicateArgumentName {
+            name: "target".into(),
+            first,
+            second,
+        }]
+    );
+    assert_eq!(errors[0].nodes(), vec![first, second]);
+    assert_eq!(
+        errors[0].to_string(),
+        "The argument name 'target' is used twice on one path"
+    );
+}
+
+#[test]
+fn dead_end_arguments() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let mut give = literal("give");
+    give.then(argument("item", Type::Word));
+    dispatcher.register(give);
+    let item = dispatcher.find_node(&["give", "item"]).unwrap();
+    assert_eq!(
+        dispatcher.tree().validate(),
+        Err(vec![ValidationError::DeadEnd {
+            node: item,
+            name: "item".into(),
+        }])
+    );
+}
+
+#[test]
+fn children_of_greedy_arguments() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let mut say = literal("say");
+    let mut message = executable("message", Type::Greedy);
+    let mut loudly = literal("loudly");
+    loudly.executes(|_| Ok(2));
+    message.then(loudly);
+    say.then(message);
+    dispatcher.register(say);
+    let message = dispatcher.find_node(&["say", "message"]).unwrap();
+    let loudly = dispatcher.find_node(&["say", "message", "loudly"]).unwrap();
+    let errors = dispatcher.tree().validate().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![ValidationError::UnreachableChild {
+            greedy: message,
+            greedy_name: "message".into(),
+            child: loudly,
+            child_name: "loudly".into(),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "'loudly' can never match, because 'message' consumes the rest of the input"
+    );
+}
+
+#[test]
+fn redirects_to_removed_nodes() {
+    let mut dispatcher = CommandDispatcher::<Source, Type>::new();
+    let removed = dispatcher.register(literal("removed"));
+    assert!(dispatcher.tree_mut().remove_node(removed));
+    let mut alias = literal("alias");
+    alias.redirect(removed);
+    let alias = dispatcher.register(alias);
+    assert_eq!(
+        dispatcher.tree().validate(),
+        Err(vec![ValidationError::DanglingRedirect { node: alias }])
+    );
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use brigadier::{
+    arguments::{
+        value::{ArgumentValue, FromArgumentValue},
+        ArgumentType,
+    },
+    builder::{argument, literal},
+    context::CommandContext,
+    dispatcher::CommandDispatcher,
+    errors::{CommandErrorType, CommandSyntaxError},
+    impl_from_argument_value, StringReader,
+};
+
+use common::Source;
+
+enum Type {
+    Int,
+    Word,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(i32),
+    Word(String),
+}
+
+impl_from_argument_value!(Value { Int => i32, Word => String });
+
+#[cfg_attr(feature = "send-futures", async_trait::async_trait)]
+#[cfg_attr(not(feature = "send-futures"), async_trait::async_trait(?Send))]
+impl<'i> ArgumentType<'i, Source> for Type {
+    type Output = Value;
+    fn parse(&self, reader: &mut StringReader<'i>) -> Result<Value, CommandSyntaxError<'i>> {
+        match self {
+            Self::Int => reader.read_int().map(Value::Int),
+            Self::Word => reader
+                .read_unquoted_string()
+                .map(|word| Value::Word(word.to_owned())),
+        }
+    }
+}
+
+fn give<'i>(context: &CommandContext<'i, Source, Type>) -> Result<i32, CommandSyntaxError<'i>> {
+    let item: &String = context.get_typed("item")?;
+    let amount = *context.get_typed::<i32>("amount")?;
+    Ok(item.len() as i32 * amount)
+}
+
+/// `give <item> <amount>`
+fn dispatcher() -> CommandDispatcher<Source, Type> {
+    let mut dispatcher = CommandDispatcher::new();
+    let mut give_ = literal("give");
+    give_.then_build(argument("item", Type::Word), |item| {
+        item.then_build(argument("amount", Type::Int), |amount| {
+            amount.executes(give)
+        })
+    });
+    dispatcher.register(give_);
+    dispatcher
+}
+
+#[test]
+fn typed_arguments() {
+    let dispatcher = dispatcher();
+    assert_eq!(dispatcher.execute_input("give apple 3", Source), Ok(15));
+}
+
+#[test]
+fn wrong_argument_type() {
+    let dispatcher = dispatcher();
+    let input = "give apple 3";
+    let context = dispatcher
+        .parse(input, Source)
+        .context()
+        .clone()
+        .build(input);
+    assert_eq!(context.get_typed::<i32>("amount"), Ok(&3));
+    let error = context.get_typed::<i32>("item").unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherWrongArgumentType {
+            name: "item".to_owned(),
+            expected: "i32",
+            actual: "alloc::string::String",
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "The argument 'item' is a alloc::string::String, not a i32"
+    );
+    let error = context.get_typed::<String>("count").unwrap_err();
+    assert_eq!(
+        error.error_type,
+        CommandErrorType::DispatcherNoSuchArgument {
+            name: "count".to_owned()
+        }
+    );
+}
+
+#[test]
+fn from_argument_value() {
+    let value = Value::Word("apple".to_owned());
+    assert_eq!(
+        String::from_value(&value).map(String::as_str),
+        Some("apple")
+    );
+    assert_eq!(i32::from_value(&value), None);
+    assert_eq!(value.type_name(), "alloc::string::String");
+    assert_eq!(Value::Int(1).type_name(), "i32");
+}